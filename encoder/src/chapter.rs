@@ -0,0 +1,427 @@
+pub mod export;
+pub mod journal;
+
+use std::{convert::Infallible, fmt, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{
+    source::{LiveStatus, LiveStatusType},
+    time::Time,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter(pub Time, pub LiveStatus);
+
+impl PartialOrd for Chapter {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Chapter {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.as_secs().cmp(&other.0.as_secs())
+    }
+}
+
+impl Chapter {
+    pub fn title(&self) -> String {
+        format!(
+            "{} Playing {}",
+            self.1.live_title,
+            self.1
+                .live_category
+                .as_deref()
+                .unwrap_or("unknown")
+                .replace('_', " ")
+        )
+    }
+}
+
+/// 시간순으로 정렬하고 각 챕터의 끝 시간을 붙임
+///
+/// 끝 시간은 다음 챕터의 시작 시간이고, 마지막 챕터는 `end`
+pub fn with_end_times(chapters: &[Chapter], end: Time) -> Vec<(Chapter, Time)> {
+    let mut chapters = chapters.to_vec();
+
+    chapters.sort();
+
+    let ends = chapters
+        .iter()
+        .skip(1)
+        .map(|chapter| chapter.0)
+        .chain([end])
+        .collect::<Vec<_>>();
+
+    chapters
+        .into_iter()
+        .zip(ends)
+        .map(|(chapter, end)| {
+            let end = if end.as_secs() < chapter.0.as_secs() {
+                chapter.0
+            } else {
+                end
+            };
+            (chapter, end)
+        })
+        .collect()
+}
+
+/// `start..end` 구간에 걸친 챕터만 남기고 `start` 를 0으로 맞춤
+///
+/// `start` 이전에 시작한 챕터는 0에서 시작하는 것으로 봄
+pub fn slice(chapters: &[Chapter], start: Time, end: Time) -> Vec<Chapter> {
+    let mut chapters = chapters.to_vec();
+
+    chapters.sort();
+
+    let (start, end) = (start.as_secs(), end.as_secs());
+
+    let first = chapters
+        .iter()
+        .rev()
+        .find(|chapter| chapter.0.as_secs() <= start)
+        .map(|chapter| Chapter(Time::default(), chapter.1.clone()));
+
+    let rest = chapters
+        .into_iter()
+        .filter(|chapter| chapter.0.as_secs() > start && chapter.0.as_secs() < end)
+        .map(|Chapter(time, live_status)| {
+            Chapter(
+                Time::from(Duration::from_secs(time.as_secs() - start)),
+                live_status,
+            )
+        });
+
+    first.into_iter().chain(rest).collect()
+}
+
+/// return: 추가되거나 수정된 챕터
+pub fn push_or_modify_chapter(chapters: &mut Vec<Chapter>, curr: Chapter) -> Option<Chapter> {
+    if curr.1.status == LiveStatusType::Close {
+        return None;
+    }
+
+    let prev = match chapters.last().cloned() {
+        Some(prev) => prev,
+        None => {
+            chapters.push(curr.clone());
+            return Some(curr);
+        }
+    };
+
+    // 1분내에 이전에 사용했던 타이틀이 있으면 거르기.
+    // 단, 카테고리가 바뀌었다면 이전 챕터의 카테고리를 최신 챕터의 카테고리로 수정하면됨
+    // 1분이라는 기준은 나중에 문제되는 거 같으면 늘릴 수 있음
+    let oldest_chapter_before_60s_with_same_title = chapters
+        .iter_mut()
+        .filter(|prev| curr.0.as_secs() - prev.0.as_secs() <= 60)
+        .filter(|prev| prev.1.live_title == curr.1.live_title)
+        .min_by_key(|chapter| chapter.0.as_secs());
+
+    if let Some(prev) = oldest_chapter_before_60s_with_same_title {
+        if prev.1.live_category != curr.1.live_category {
+            prev.1.live_category = curr.1.live_category;
+            return Some(prev.clone());
+        }
+        return None;
+    }
+
+    let modified = [
+        prev.1.live_title != curr.1.live_title,
+        prev.1.live_category != curr.1.live_category,
+    ]
+    .into_iter()
+    .any(|ne| ne);
+
+    if modified {
+        chapters.push(curr.clone());
+        return Some(curr);
+    }
+
+    None
+}
+
+/// ISO 639 language code. (`ko`, `kor`, `en` ...)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct ChapterLanguage(pub String);
+
+impl Default for ChapterLanguage {
+    fn default() -> Self {
+        Self("ko".to_owned())
+    }
+}
+
+impl FromStr for ChapterLanguage {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.trim().to_owned()))
+    }
+}
+
+impl fmt::Display for ChapterLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChapterAtom {
+    pub start: Time,
+    pub end: Time,
+    pub title: String,
+}
+
+/// https://www.matroska.org/technical/chapters.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatroskaChapters {
+    pub language: ChapterLanguage,
+    pub atoms: Vec<ChapterAtom>,
+}
+
+impl MatroskaChapters {
+    /// `end`: 녹화가 끝난 시간, 마지막 챕터의 끝 시간으로 사용됨
+    pub fn new(chapters: &[Chapter], end: Time, language: ChapterLanguage) -> Self {
+        let atoms = with_end_times(chapters, end)
+            .into_iter()
+            .map(|(chapter, end)| ChapterAtom {
+                start: chapter.0,
+                end,
+                title: chapter.title(),
+            })
+            .collect();
+
+        Self { language, atoms }
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut s = String::new();
+
+        s.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        s.push_str(r#"<!DOCTYPE Chapters SYSTEM "matroskachapters.dtd">"#);
+        s.push_str("<Chapters><EditionEntry>");
+
+        for ChapterAtom { start, end, title } in &self.atoms {
+            s.push_str("<ChapterAtom>");
+
+            s.push_str("<ChapterTimeStart>");
+            s.push_str(&start.to_readable(":"));
+            s.push_str(".000");
+            s.push_str("</ChapterTimeStart>");
+
+            s.push_str("<ChapterTimeEnd>");
+            s.push_str(&end.to_readable(":"));
+            s.push_str(".000");
+            s.push_str("</ChapterTimeEnd>");
+
+            s.push_str("<ChapterDisplay>");
+            s.push_str("<ChapterString>");
+            s.push_str(&escape_xml(title));
+            s.push_str("</ChapterString>");
+            s.push_str("<ChapterLanguage>");
+            s.push_str(&escape_xml(&self.language.0));
+            s.push_str("</ChapterLanguage>");
+            s.push_str("</ChapterDisplay>");
+
+            s.push_str("</ChapterAtom>");
+        }
+
+        s.push_str("</EditionEntry></Chapters>");
+
+        s
+    }
+
+    /// `to_xml` 로 만든 `metadata.xml` 을 읽음, 저널이 없는 이전 녹화에 사용함
+    ///
+    /// 다른 프로그램이 만든 XML은 읽지 못할 수 있음
+    pub fn from_xml(xml: &str) -> Option<Self> {
+        let mut atoms = Vec::new();
+        let mut language = None;
+
+        for atom in xml.split("<ChapterAtom>").skip(1) {
+            let start = parse_chapter_time(element(atom, "ChapterTimeStart")?)?;
+            let end = element(atom, "ChapterTimeEnd")
+                .and_then(parse_chapter_time)
+                .unwrap_or(start);
+            let title = unescape_xml(element(atom, "ChapterString").unwrap_or_default());
+
+            if language.is_none() {
+                language = element(atom, "ChapterLanguage").map(unescape_xml);
+            }
+
+            atoms.push(ChapterAtom { start, end, title });
+        }
+
+        if atoms.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            language: language.map(ChapterLanguage).unwrap_or_default(),
+            atoms,
+        })
+    }
+}
+
+impl ChapterAtom {
+    /// `Chapter::title` 의 반대, returns (live_title, live_category)
+    pub fn split_title(&self) -> (String, Option<String>) {
+        match self.title.rsplit_once(" Playing ") {
+            Some((title, "unknown")) => (title.to_owned(), None),
+            Some((title, category)) => (title.to_owned(), Some(category.to_owned())),
+            None => (self.title.clone(), None),
+        }
+    }
+}
+
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{name}>"))? + name.len() + 2;
+    let end = start + xml[start..].find(&format!("</{name}>"))?;
+
+    Some(&xml[start..end])
+}
+
+/// `01:02:03.000`
+fn parse_chapter_time(s: &str) -> Option<Time> {
+    let mut secs = 0.0;
+
+    for x in s.trim().split(':') {
+        secs = secs * 60.0 + x.parse::<f64>().ok()?;
+    }
+
+    (secs.is_finite() && secs >= 0.0).then(|| Time::from(Duration::from_secs_f64(secs)))
+}
+
+pub fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // XML 1.0에서 허용하지 않는 제어 문자
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+pub fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(secs: u64, title: &str) -> Chapter {
+        Chapter(
+            Time::from(Duration::from_secs(secs)),
+            LiveStatus {
+                live_title: title.to_owned(),
+                status: LiveStatusType::Open,
+                live_category: Some("Talk".to_owned()),
+                adult: false,
+                extra: Default::default(),
+            },
+        )
+    }
+
+    fn xml(chapters: &[Chapter], end: u64, language: &str) -> String {
+        MatroskaChapters::new(
+            chapters,
+            Time::from(Duration::from_secs(end)),
+            ChapterLanguage(language.to_owned()),
+        )
+        .to_xml()
+    }
+
+    fn elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+        xml.split(&format!("<{name}>"))
+            .skip(1)
+            .filter_map(|x| x.split_once(&format!("</{name}>")).map(|(x, _)| x))
+            .collect()
+    }
+
+    #[test]
+    fn chapter_ends_at_next_start() {
+        // 순서가 섞여 있어도 시간순으로 씀
+        let xml = xml(&[chapter(3600, "b"), chapter(0, "a")], 5400, "ko");
+
+        assert_eq!(
+            elements(&xml, "ChapterTimeStart"),
+            ["00:00:00.000", "01:00:00.000"]
+        );
+        assert_eq!(
+            elements(&xml, "ChapterTimeEnd"),
+            ["01:00:00.000", "01:30:00.000"]
+        );
+    }
+
+    #[test]
+    fn escapes_titles() {
+        let xml = xml(
+            &[chapter(0, "Tom & Jerry <3 'live'\u{0}\u{1b}\n")],
+            60,
+            "ko",
+        );
+
+        assert_eq!(
+            elements(&xml, "ChapterString"),
+            ["Tom &amp; Jerry &lt;3 &apos;live&apos;\n Playing Talk"]
+        );
+        assert!(!xml.contains(['\u{0}', '\u{1b}']));
+    }
+
+    #[test]
+    fn writes_language() {
+        let xml = xml(&[chapter(0, "a"), chapter(60, "b")], 120, "eng");
+
+        assert_eq!(elements(&xml, "ChapterLanguage"), ["eng", "eng"]);
+    }
+
+    #[test]
+    fn from_xml_reads_to_xml() {
+        let chapters = MatroskaChapters::new(
+            &[chapter(0, "Q&A <1>"), chapter(90, "it's \"late\"")],
+            Time::from(Duration::from_secs(7200)),
+            ChapterLanguage("ja".to_owned()),
+        );
+
+        assert_eq!(
+            MatroskaChapters::from_xml(&chapters.to_xml()),
+            Some(chapters)
+        );
+        assert_eq!(MatroskaChapters::from_xml("<Chapters></Chapters>"), None);
+    }
+
+    #[test]
+    fn live_status_keeps_platform_fields() {
+        let raw = serde_json::json!({
+            "liveTitle": "a",
+            "status": "OPEN",
+            "liveCategory": "Talk",
+            "adult": false,
+            "concurrentUserCount": 1234,
+            "liveTagList": ["tag"],
+        });
+
+        let live_status = serde_json::from_value::<LiveStatus>(raw.clone()).unwrap();
+
+        assert_eq!(live_status.live_title, "a");
+        assert_eq!(live_status.extra["concurrentUserCount"], 1234);
+        assert_eq!(serde_json::to_value(&live_status).unwrap(), raw);
+    }
+}
@@ -0,0 +1,316 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ffmpeg::escape_metadata, source::LiveStatus, time::Time};
+
+use super::{escape_xml, with_end_times, Chapter, ChapterLanguage, MatroskaChapters};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ChapterFormat {
+    /// mkvpropedit 에서 사용하는 형식
+    #[serde(rename = "matroska")]
+    Matroska,
+    #[serde(rename = "webvtt")]
+    WebVtt,
+    /// `ffmpeg -i index.mkv -i chapters.ffmetadata -map_chapters 1 ...`
+    #[serde(rename = "ffmetadata")]
+    FfMetadata,
+    #[serde(rename = "json")]
+    Json,
+    /// `00:00:00 Title (Category)`
+    #[serde(rename = "description")]
+    Description,
+}
+
+impl FromStr for ChapterFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let r = match s {
+            "matroska" => ChapterFormat::Matroska,
+            "webvtt" => ChapterFormat::WebVtt,
+            "ffmetadata" => ChapterFormat::FfMetadata,
+            "json" => ChapterFormat::Json,
+            "description" => ChapterFormat::Description,
+            _ => return Err(()),
+        };
+
+        Ok(r)
+    }
+}
+
+impl ChapterFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChapterFormat::Matroska => "matroska",
+            ChapterFormat::WebVtt => "webvtt",
+            ChapterFormat::FfMetadata => "ffmetadata",
+            ChapterFormat::Json => "json",
+            ChapterFormat::Description => "description",
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ChapterFormat::Matroska => "metadata.xml",
+            ChapterFormat::WebVtt => "chapters.vtt",
+            ChapterFormat::FfMetadata => "chapters.ffmetadata",
+            ChapterFormat::Json => "chapters.json",
+            ChapterFormat::Description => "chapters.txt",
+        }
+    }
+
+    /// `end`: 녹화가 끝난 시간
+    pub fn export(&self, chapters: &[Chapter], end: Time, language: &ChapterLanguage) -> String {
+        match self {
+            ChapterFormat::Matroska => {
+                MatroskaChapters::new(chapters, end, language.clone()).to_xml()
+            }
+            ChapterFormat::WebVtt => to_webvtt(chapters, end),
+            ChapterFormat::FfMetadata => to_ffmetadata(chapters, end),
+            ChapterFormat::Json => to_json(chapters, end),
+            ChapterFormat::Description => to_description(chapters),
+        }
+    }
+}
+
+/// https://www.w3.org/TR/webvtt1/#webvtt-chapter-cues
+fn to_webvtt(chapters: &[Chapter], end: Time) -> String {
+    let mut s = String::from("WEBVTT\n");
+
+    for (i, (chapter, end)) in with_end_times(chapters, end).into_iter().enumerate() {
+        s.push('\n');
+        s.push_str(&(i + 1).to_string());
+        s.push('\n');
+        s.push_str(&chapter.0.to_readable(":"));
+        s.push_str(".000 --> ");
+        s.push_str(&end.to_readable(":"));
+        s.push_str(".000\n");
+        // cue text 에는 `-->`, 빈 줄이 들어가면 안 됨
+        s.push_str(
+            &escape_xml(&chapter.title())
+                .replace("--&gt;", "-- &gt;")
+                .replace('\n', " "),
+        );
+        s.push('\n');
+    }
+
+    s
+}
+
+/// https://ffmpeg.org/ffmpeg-formats.html#Metadata-2
+fn to_ffmetadata(chapters: &[Chapter], end: Time) -> String {
+    let mut s = String::from(";FFMETADATA1\n");
+
+    for (chapter, end) in with_end_times(chapters, end) {
+        s.push_str("\n[CHAPTER]\n");
+        s.push_str("TIMEBASE=1/1\n");
+        s.push_str(&format!("START={}\n", chapter.0.as_secs()));
+        s.push_str(&format!("END={}\n", end.as_secs()));
+        s.push_str(&format!("title={}\n", escape_metadata(&chapter.title())));
+    }
+
+    s
+}
+
+#[derive(Serialize)]
+struct JsonChapter<'a> {
+    /// seconds
+    start: u64,
+    /// seconds
+    end: u64,
+    title: String,
+    live_status: &'a LiveStatus,
+}
+
+fn to_json(chapters: &[Chapter], end: Time) -> String {
+    let chapters = with_end_times(chapters, end);
+
+    let chapters = chapters
+        .iter()
+        .map(|(chapter, end)| JsonChapter {
+            start: chapter.0.as_secs(),
+            end: end.as_secs(),
+            title: chapter.title(),
+            live_status: &chapter.1,
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&chapters).unwrap()
+}
+
+fn to_description(chapters: &[Chapter]) -> String {
+    let mut chapters = chapters.to_vec();
+
+    chapters.sort();
+
+    chapters
+        .iter()
+        .map(|Chapter(start, live_status)| {
+            let title = live_status.live_title.replace('\n', " ");
+
+            match live_status.live_category.as_deref() {
+                Some(category) => format!(
+                    "{} {} ({})",
+                    start.to_readable(":"),
+                    title,
+                    category.replace('_', " ")
+                ),
+                None => format!("{} {}", start.to_readable(":"), title),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::source::LiveStatusType;
+
+    use super::*;
+
+    fn chapter(secs: u64, title: &str, category: Option<&str>) -> Chapter {
+        Chapter(
+            Time::from(Duration::from_secs(secs)),
+            LiveStatus {
+                live_title: title.to_owned(),
+                status: LiveStatusType::Open,
+                live_category: category.map(ToOwned::to_owned),
+                adult: false,
+                extra: Default::default(),
+            },
+        )
+    }
+
+    fn end(secs: u64) -> Time {
+        Time::from(Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn webvtt() {
+        let chapters = [
+            chapter(90, "b --> c\nd", Some("Just_Chatting")),
+            chapter(0, "<a> & 'a'", None),
+        ];
+
+        assert_eq!(
+            to_webvtt(&chapters, end(3725)),
+            "WEBVTT\n\
+             \n\
+             1\n\
+             00:00:00.000 --> 00:01:30.000\n\
+             &lt;a&gt; &amp; &apos;a&apos; Playing unknown\n\
+             \n\
+             2\n\
+             00:01:30.000 --> 01:02:05.000\n\
+             b -- &gt; c d Playing Just Chatting\n"
+        );
+    }
+
+    #[test]
+    fn ffmetadata() {
+        let chapters = [
+            chapter(0, "a=b;c#d\\e\nf", Some("Talk")),
+            chapter(90, "-->", None),
+        ];
+
+        assert_eq!(
+            to_ffmetadata(&chapters, end(3725)),
+            ";FFMETADATA1\n\
+             \n\
+             [CHAPTER]\n\
+             TIMEBASE=1/1\n\
+             START=0\n\
+             END=90\n\
+             title=a\\=b\\;c\\#d\\\\e f Playing Talk\n\
+             \n\
+             [CHAPTER]\n\
+             TIMEBASE=1/1\n\
+             START=90\n\
+             END=3725\n\
+             title=--> Playing unknown\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let mut first = chapter(0, "a\n\"b\"", Some("Talk"));
+
+        first
+            .1
+            .extra
+            .insert("liveId".to_owned(), json!(1234));
+
+        let chapters = [chapter(90, "c", None), first];
+
+        let exported =
+            serde_json::from_str::<serde_json::Value>(&to_json(&chapters, end(3725))).unwrap();
+
+        assert_eq!(
+            exported,
+            json!([
+                {
+                    "start": 0,
+                    "end": 90,
+                    "title": "a\n\"b\" Playing Talk",
+                    "live_status": {
+                        "liveTitle": "a\n\"b\"",
+                        "status": "OPEN",
+                        "liveCategory": "Talk",
+                        "adult": false,
+                        "liveId": 1234
+                    }
+                },
+                {
+                    "start": 90,
+                    "end": 3725,
+                    "title": "c Playing unknown",
+                    "live_status": {
+                        "liveTitle": "c",
+                        "status": "OPEN",
+                        "liveCategory": null,
+                        "adult": false
+                    }
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn description() {
+        let chapters = [
+            chapter(3725, "c", None),
+            chapter(0, "a\nb = c; #d", Some("Just_Chatting")),
+            chapter(90, "--> \\", Some("Talk")),
+        ];
+
+        assert_eq!(
+            to_description(&chapters),
+            "00:00:00 a b = c; #d (Just Chatting)\n\
+             00:01:30 --> \\ (Talk)\n\
+             01:02:05 c\n"
+        );
+    }
+
+    #[test]
+    fn export_by_format() {
+        let chapters = [chapter(0, "a", None)];
+        let language = ChapterLanguage::default();
+
+        assert_eq!(
+            ChapterFormat::Description.export(&chapters, end(60), &language),
+            "00:00:00 a\n"
+        );
+        assert!(ChapterFormat::Matroska
+            .export(&chapters, end(60), &language)
+            .contains("<ChapterString>a Playing unknown</ChapterString>"));
+        assert_eq!("webvtt".parse(), Ok(ChapterFormat::WebVtt));
+        assert_eq!("srt".parse::<ChapterFormat>(), Err(()));
+    }
+}
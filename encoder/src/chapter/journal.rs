@@ -0,0 +1,213 @@
+//! 챕터 변경 사항을 녹화 폴더에 한 줄씩 기록해서
+//! 프로세스가 비정상 종료되더라도 챕터를 복구할 수 있게 함
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use crate::{ffmpeg::OutputFormat, source::LiveStatus, time::Time};
+
+use super::Chapter;
+
+pub const FILE_NAME: &str = "chapters.jsonl";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Started {
+        started_at: DateTime<FixedOffset>,
+        /// 이전 버전의 저널에는 없음
+        #[serde(default = "OutputFormat::default")]
+        output_format: OutputFormat,
+    },
+    /// 방송이 끊겼다가 이어져서 새 파일에 녹화하기 시작함
+    Part {
+        index: usize,
+        /// 이전 part들의 길이 합 (seconds)
+        offset: u64,
+        started_at: DateTime<FixedOffset>,
+    },
+    /// 녹화하는 스트림, part마다 기록함
+    Stream {
+        /// `HLS`, `LLHLS`
+        media_type: String,
+        /// master playlist가 아니면 없음
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        variant: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bandwidth: Option<u64>,
+    },
+    /// 같은 `time` 의 챕터가 이미 있으면 덮어씀
+    Chapter {
+        /// seconds
+        time: u64,
+        live_status: LiveStatus,
+    },
+    /// 녹화 파일에 챕터를 적용함
+    Applied,
+}
+
+pub struct Journal {
+    file: File,
+}
+
+impl Journal {
+    pub fn create(
+        directory: &Path,
+        started_at: DateTime<FixedOffset>,
+        output_format: OutputFormat,
+    ) -> io::Result<Self> {
+        let mut journal = Self::open(directory)?;
+
+        journal.append(&JournalEntry::Started {
+            started_at,
+            output_format,
+        })?;
+
+        Ok(journal)
+    }
+
+    pub fn open(directory: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(directory.join(FILE_NAME))?;
+
+        Ok(Self { file })
+    }
+
+    pub fn append_chapter(&mut self, Chapter(time, live_status): &Chapter) -> io::Result<()> {
+        self.append(&JournalEntry::Chapter {
+            time: time.as_secs(),
+            live_status: live_status.clone(),
+        })
+    }
+
+    pub fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        self.file.write_all(&line)?;
+        self.file.sync_data()
+    }
+}
+
+pub struct ReplayedPart {
+    pub index: usize,
+    /// seconds
+    pub offset: u64,
+    pub started_at: DateTime<FixedOffset>,
+}
+
+pub struct Replayed {
+    pub started_at: Option<DateTime<FixedOffset>>,
+    pub output_format: OutputFormat,
+    /// 첫 번째 part는 포함하지 않음
+    pub parts: Vec<ReplayedPart>,
+    pub chapters: Vec<Chapter>,
+    pub applied: bool,
+}
+
+/// 마지막 줄이 잘려 있거나 깨진 줄은 무시함
+pub fn replay(directory: &Path) -> io::Result<Option<Replayed>> {
+    let file = match File::open(directory.join(FILE_NAME)) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let mut replayed = Replayed {
+        started_at: None,
+        output_format: OutputFormat::default(),
+        parts: Vec::new(),
+        chapters: Vec::new(),
+        applied: false,
+    };
+
+    for line in BufReader::new(file).lines() {
+        let Ok(entry) = serde_json::from_str::<JournalEntry>(&line?) else {
+            continue;
+        };
+
+        match entry {
+            JournalEntry::Started {
+                started_at,
+                output_format,
+            } => {
+                replayed.started_at = Some(started_at);
+                replayed.output_format = output_format;
+            }
+            JournalEntry::Part {
+                index,
+                offset,
+                started_at,
+            } => {
+                replayed.parts.push(ReplayedPart {
+                    index,
+                    offset,
+                    started_at,
+                });
+                replayed.applied = false;
+            }
+            JournalEntry::Chapter { time, live_status } => {
+                let time = Time::from(Duration::from_secs(time));
+
+                match replayed
+                    .chapters
+                    .iter_mut()
+                    .find(|chapter| chapter.0 == time)
+                {
+                    Some(chapter) => chapter.1 = live_status,
+                    None => replayed.chapters.push(Chapter(time, live_status)),
+                }
+
+                replayed.applied = false;
+            }
+            JournalEntry::Stream { .. } => {}
+            JournalEntry::Applied => {
+                replayed.applied = true;
+            }
+        }
+    }
+
+    replayed.chapters.sort();
+
+    Ok(Some(replayed))
+}
+
+/// 저널은 있지만 챕터가 적용되지 않은 녹화 폴더
+///
+/// `directory`: <channel>
+pub fn find_unapplied(directory: &Path) -> io::Result<Vec<(PathBuf, Replayed)>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut unapplied = Vec::new();
+
+    for entry in entries {
+        let path = entry?.path();
+
+        if !path.is_dir() {
+            continue;
+        }
+
+        if let Some(replayed) = replay(&path)? {
+            if !replayed.applied {
+                unapplied.push((path, replayed));
+            }
+        }
+    }
+
+    unapplied.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(unapplied)
+}
@@ -0,0 +1,349 @@
+//! 녹화하는 동안 채팅을 받아서 녹화 폴더에 `chat.jsonl` 로 저장함
+//!
+//! 연결이 끊기면 다시 연결함. 접속 정보는 [`ChatSource`] 를 통해서 가져옴
+
+pub mod chzzk;
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{
+    net::TcpStream,
+    sync::oneshot,
+    time::{sleep, Instant},
+};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+pub const FILE_NAME: &str = "chat.jsonl";
+
+/// 다시 연결하기 전에 기다리는 시간, 실패할 때마다 두 배로 늘어남
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// 이 시간 동안 받은 메시지가 없으면 ping을 보냄
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+mod cmd {
+    pub const PING: i64 = 0;
+    pub const PONG: i64 = 10000;
+    pub const CONNECT: i64 = 100;
+    pub const CONNECTED: i64 = 10100;
+    pub const CHAT: i64 = 93101;
+    pub const DONATION: i64 = 93102;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("status: {0}")]
+    Status(StatusCode),
+    #[error("websocket: {0}")]
+    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("write: {0}")]
+    Write(#[from] io::Error),
+    /// 방송 중이 아니거나 채팅을 볼 수 없는 방송
+    #[error("chat channel is not available")]
+    Unavailable,
+    #[error("connect rejected with {0}")]
+    Rejected(i64),
+    #[error("connection closed")]
+    Closed,
+}
+
+/// 채팅 서버에 접속하는데 필요한 정보
+#[derive(Debug, Clone)]
+pub struct ChatServer {
+    /// `wss://kr-ss1.chat.naver.com/chat`
+    pub url: String,
+    pub chat_channel_id: String,
+    pub access_token: String,
+}
+
+/// 접속할 때마다 호출됨 (토큰이 만료되었을 수 있음)
+#[allow(async_fn_in_trait)]
+pub trait ChatSource {
+    async fn chat_server(&self) -> Result<ChatServer, Error>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatKind {
+    Chat,
+    Donation,
+}
+
+/// `chat.jsonl` 의 한 줄
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    /// 녹화 시작부터 지난 시간(초), 이어서 녹화한 경우 앞선 파트의 길이를 포함함
+    pub time: f64,
+    pub kind: ChatKind,
+    pub user_id: Option<String>,
+    pub nickname: Option<String>,
+    pub message: String,
+    /// 채팅 서버가 기록한 시각
+    pub sent_at: Option<DateTime<Utc>>,
+    /// 후원 금액 (치즈)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pay_amount: Option<u64>,
+}
+
+impl ChatMessage {
+    pub fn read_all(directory: &Path) -> io::Result<Vec<Self>> {
+        let buf = match std::fs::read_to_string(directory.join(FILE_NAME)) {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        buf.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(io::Error::from))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Frame {
+    cmd: i64,
+    #[serde(default)]
+    ret_code: Option<i64>,
+    #[serde(default)]
+    bdy: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawChat {
+    #[serde(default)]
+    uid: Option<String>,
+    /// JSON 문자열
+    #[serde(default)]
+    profile: Option<String>,
+    #[serde(default)]
+    msg: String,
+    /// ms
+    #[serde(default)]
+    msg_time: Option<i64>,
+    /// JSON 문자열
+    #[serde(default)]
+    extras: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Profile {
+    nickname: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Extras {
+    pay_amount: Option<u64>,
+}
+
+impl RawChat {
+    fn into_message(self, kind: ChatKind, time: Duration) -> ChatMessage {
+        let nickname = self
+            .profile
+            .as_deref()
+            .and_then(|x| serde_json::from_str::<Profile>(x).ok())
+            .and_then(|x| x.nickname);
+
+        let pay_amount = match kind {
+            ChatKind::Donation => self
+                .extras
+                .as_deref()
+                .and_then(|x| serde_json::from_str::<Extras>(x).ok())
+                .and_then(|x| x.pay_amount),
+            ChatKind::Chat => None,
+        };
+
+        ChatMessage {
+            time: time.as_millis() as f64 / 1000.0,
+            kind,
+            user_id: self.uid,
+            nickname,
+            message: self.msg,
+            sent_at: self.msg_time.and_then(DateTime::from_timestamp_millis),
+            pay_amount,
+        }
+    }
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+pub struct ChatRecorder<S> {
+    channel_name: String,
+    source: S,
+    file: File,
+    /// 앞선 파트들의 길이
+    offset: Duration,
+    started_at: Instant,
+}
+
+impl<S: ChatSource> ChatRecorder<S> {
+    /// `offset`: 녹화 파일의 시간과 맞추기 위해 더하는 시간
+    pub fn new(
+        channel_name: impl Into<String>,
+        source: S,
+        directory: &Path,
+        offset: Duration,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(directory.join(FILE_NAME))?;
+
+        Ok(Self {
+            channel_name: channel_name.into(),
+            source,
+            file,
+            offset,
+            started_at: Instant::now(),
+        })
+    }
+
+    fn time(&self) -> Duration {
+        self.offset + self.started_at.elapsed()
+    }
+
+    /// `stop` 을 받을 때까지 채팅을 저장함
+    pub async fn run(mut self, mut stop: oneshot::Receiver<()>) {
+        let mut retry = RETRY_MIN;
+
+        loop {
+            let err = tokio::select! {
+                res = self.receive(&mut retry) => match res {
+                    Ok(()) => return,
+                    Err(err) => err,
+                },
+                _ = &mut stop => return,
+            };
+
+            eprintln!(
+                "[{}] chat: {err}, reconnecting in {}s",
+                self.channel_name,
+                retry.as_secs()
+            );
+
+            tokio::select! {
+                _ = sleep(retry) => {}
+                _ = &mut stop => return,
+            }
+
+            retry = (retry * 2).min(RETRY_MAX);
+        }
+    }
+
+    /// 연결이 끊길 때까지 받음, 접속에 성공하면 `retry` 를 초기화함
+    async fn receive(&mut self, retry: &mut Duration) -> Result<(), Error> {
+        let server = self.source.chat_server().await?;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(server.url.as_str()).await?;
+
+        let connect = json!({
+            "ver": "2",
+            "cmd": cmd::CONNECT,
+            "svcid": "game",
+            "cid": server.chat_channel_id,
+            "bdy": {
+                "uid": null,
+                "devType": 2001,
+                "accTkn": server.access_token,
+                "auth": "READ",
+            },
+            "tid": 1,
+        });
+
+        socket.send(Message::Text(connect.to_string())).await?;
+
+        loop {
+            let message = tokio::select! {
+                message = socket.next() => message,
+                _ = sleep(PING_INTERVAL) => {
+                    send_cmd(&mut socket, cmd::PING).await?;
+                    continue;
+                }
+            };
+
+            match message.ok_or(Error::Closed)?? {
+                Message::Text(text) => self.handle(&mut socket, &text, retry).await?,
+                Message::Close(_) => return Err(Error::Closed),
+                _ => {}
+            }
+        }
+    }
+
+    async fn handle(
+        &mut self,
+        socket: &mut Socket,
+        text: &str,
+        retry: &mut Duration,
+    ) -> Result<(), Error> {
+        let frame = match serde_json::from_str::<Frame>(text) {
+            Ok(frame) => frame,
+            Err(err) => {
+                eprintln!("[{}] chat: unknown message: {err}", self.channel_name);
+                return Ok(());
+            }
+        };
+
+        let kind = match frame.cmd {
+            cmd::PING => return send_cmd(socket, cmd::PONG).await,
+            cmd::CONNECTED => {
+                return match frame.ret_code.unwrap_or(0) {
+                    0 => {
+                        println!("[{}] chat: connected", self.channel_name);
+                        *retry = RETRY_MIN;
+                        Ok(())
+                    }
+                    code => Err(Error::Rejected(code)),
+                };
+            }
+            cmd::CHAT => ChatKind::Chat,
+            cmd::DONATION => ChatKind::Donation,
+            _ => return Ok(()),
+        };
+
+        let chats = match serde_json::from_value::<Vec<RawChat>>(frame.bdy) {
+            Ok(chats) => chats,
+            Err(err) => {
+                eprintln!("[{}] chat: unknown message: {err}", self.channel_name);
+                return Ok(());
+            }
+        };
+
+        let time = self.time();
+
+        for chat in chats {
+            let mut line = serde_json::to_vec(&chat.into_message(kind, time))?;
+            line.push(b'\n');
+
+            self.file.write_all(&line)?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn send_cmd(socket: &mut Socket, cmd: i64) -> Result<(), Error> {
+    let text = json!({ "ver": "2", "cmd": cmd }).to_string();
+
+    socket.send(Message::Text(text)).await?;
+
+    Ok(())
+}
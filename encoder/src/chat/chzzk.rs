@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use ::chzzk::request::Auth;
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::sync::RwLock;
+
+use crate::source::chzzk;
+
+use super::{ChatServer, ChatSource, Error};
+
+/// chzzk.naver.com
+///
+/// 접속할 때마다 채팅 채널과 토큰을 새로 받아옴
+pub struct ChzzkChat {
+    http: reqwest::Client,
+    auth: Arc<RwLock<Option<Auth>>>,
+    channel_id: String,
+}
+
+impl ChzzkChat {
+    pub fn new(
+        http: reqwest::Client,
+        auth: Arc<RwLock<Option<Auth>>>,
+        channel_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            http,
+            auth,
+            channel_id: channel_id.into(),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        let req = chzzk::with_auth(self.http.get(url), self.auth.read().await.as_ref());
+
+        let resp = req.send().await?;
+
+        if !resp.status().is_success() {
+            return Err(Error::Status(resp.status()));
+        }
+
+        Ok(resp.json::<Response<T>>().await?.content)
+    }
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    content: T,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LiveDetail {
+    chat_channel_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessToken {
+    access_token: String,
+}
+
+/// 채팅 채널마다 접속할 서버가 정해져 있음 (`kr-ss1` ~ `kr-ss9`)
+fn server_url(chat_channel_id: &str) -> String {
+    let n = chat_channel_id.bytes().map(u32::from).sum::<u32>() % 9 + 1;
+
+    format!("wss://kr-ss{n}.chat.naver.com/chat")
+}
+
+impl ChatSource for ChzzkChat {
+    async fn chat_server(&self) -> Result<ChatServer, Error> {
+        let live_detail = self
+            .get::<Option<LiveDetail>>(&format!(
+                "{}/service/v2/channels/{}/live-detail",
+                chzzk::API_URL,
+                self.channel_id
+            ))
+            .await?;
+
+        let chat_channel_id = live_detail
+            .and_then(|x| x.chat_channel_id)
+            .ok_or(Error::Unavailable)?;
+
+        let AccessToken { access_token } = self
+            .get(&format!(
+                "{}/nng_main/v1/chats/access-token?channelId={chat_channel_id}&chatType=STREAMING",
+                chzzk::CHAT_API_URL
+            ))
+            .await?;
+
+        Ok(ChatServer {
+            url: server_url(&chat_channel_id),
+            chat_channel_id,
+            access_token,
+        })
+    }
+}
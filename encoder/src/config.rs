@@ -1,14 +1,171 @@
-use std::{env, fmt::Debug, fs, str::FromStr};
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
 use chzzk::request::Auth;
 use serde::Deserialize;
 
-use crate::ffmpeg::Ffmpeg;
+use crate::{
+    chapter::{export::ChapterFormat, ChapterLanguage},
+    ffmpeg::{AudioOptions, Ffmpeg, FfmpegOverride, VideoOptions},
+    schedule::Poll,
+    segment::Segmentation,
+};
 
 #[derive(Deserialize)]
 pub struct Channel {
     pub channel_id: String,
     pub channel_name: String,
+
+    // 아래 값들이 없으면 전역 설정을 따름
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub quality: Option<Qualities>,
+    #[serde(default)]
+    pub media_types: Option<Vec<MediaType>>,
+    #[serde(default)]
+    pub name_template: Option<String>,
+    #[serde(rename = "post_process", default)]
+    pub ffmpeg: Option<FfmpegOverride>,
+    #[serde(default)]
+    pub segment: Option<Segmentation>,
+}
+
+impl Channel {
+    /// 덮어쓰지 않은 값을 전역 설정으로 채움
+    pub fn resolve(self, config: &Config) -> ChannelConfig {
+        let Channel {
+            channel_id,
+            channel_name,
+            path,
+            quality,
+            media_types,
+            name_template,
+            ffmpeg,
+            segment,
+        } = self;
+
+        ChannelConfig {
+            channel_id,
+            channel_name,
+            path: path.unwrap_or_else(|| config.path.clone()),
+            quality: quality.unwrap_or_else(|| config.quality.clone()),
+            media_types: media_types.unwrap_or_else(|| config.media_types.clone()),
+            name_template: name_template.unwrap_or_else(|| config.name_template.clone()),
+            ffmpeg: match ffmpeg {
+                Some(ffmpeg) => ffmpeg.apply(&config.ffmpeg),
+                None => config.ffmpeg.clone(),
+            },
+            segment: segment.unwrap_or(config.segment),
+        }
+    }
+}
+
+/// 전역 설정이 적용된 채널 설정
+pub struct ChannelConfig {
+    pub channel_id: String,
+    pub channel_name: String,
+    /// 녹화 파일을 저장할 최상위 폴더
+    pub path: String,
+    pub quality: Qualities,
+    pub media_types: Vec<MediaType>,
+    /// 녹화 폴더 이름 (strftime 형식)
+    pub name_template: String,
+    pub ffmpeg: Ffmpeg,
+    pub segment: Segmentation,
+}
+
+/// `best`, `worst`, `1080p`, `720p60` ...
+///
+/// 앞에서부터 처음으로 맞는 variant를 녹화함
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "OneOrMany")]
+pub struct Qualities(pub Vec<String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for Qualities {
+    fn from(x: OneOrMany) -> Self {
+        match x {
+            OneOrMany::One(quality) => Self(vec![quality]),
+            OneOrMany::Many(qualities) => Self(qualities),
+        }
+    }
+}
+
+impl FromStr for Qualities {
+    type Err = ();
+
+    /// 쉼표로 구분함
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            s.split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(String::from)
+                .collect(),
+        ))
+    }
+}
+
+impl fmt::Display for Qualities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.join(", "))
+    }
+}
+
+fn quality() -> Qualities {
+    Qualities(vec!["best".to_owned()])
+}
+
+/// chzzk의 `media_id`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum MediaType {
+    #[serde(rename = "HLS")]
+    Hls,
+    /// low-latency HLS
+    #[serde(rename = "LLHLS")]
+    Llhls,
+}
+
+impl MediaType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaType::Hls => "HLS",
+            MediaType::Llhls => "LLHLS",
+        }
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let r = match s {
+            "HLS" => MediaType::Hls,
+            "LLHLS" => MediaType::Llhls,
+            _ => return Err(()),
+        };
+
+        Ok(r)
+    }
+}
+
+fn media_types() -> Vec<MediaType> {
+    vec![MediaType::Hls]
+}
+
+fn name_template() -> String {
+    "%Y-%m-%d_%H-%M-%S".to_owned()
 }
 
 const fn zero() -> i8 {
@@ -62,6 +219,81 @@ impl From<Timezone> for chrono::FixedOffset {
     }
 }
 
+const fn zero_secs() -> u64 {
+    0
+}
+
+const fn concat() -> bool {
+    false
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct Resume {
+    /// 방송이 끊긴 뒤 같은 방송이 이 시간(초) 안에 다시 시작되면 같은 폴더에 이어서 녹화함
+    ///
+    /// 0이면 이어서 녹화하지 않음
+    #[serde(default = "zero_secs")]
+    pub grace_secs: u64,
+    /// 세션이 끝나면 나눠서 녹화된 파일들을 `index.mkv` 하나로 합침
+    #[serde(default = "concat")]
+    pub concat: bool,
+}
+
+impl Default for Resume {
+    fn default() -> Self {
+        Self {
+            grace_secs: zero_secs(),
+            concat: concat(),
+        }
+    }
+}
+
+const fn shutdown_timeout_secs() -> u64 {
+    20
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct Shutdown {
+    /// 종료 신호를 받은 뒤 ffmpeg가 녹화 파일을 마무리하기를 기다리는 시간(초)
+    ///
+    /// 넘으면 ffmpeg를 강제로 종료하고, 챕터를 적용하기 전에 remux로 파일을 복구함
+    #[serde(default = "shutdown_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            timeout_secs: shutdown_timeout_secs(),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+const fn chat_enabled() -> bool {
+    false
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct Chat {
+    /// 녹화하는 동안 채팅을 `chat.jsonl` 에 저장함, 기본값 false
+    #[serde(default = "chat_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self {
+            enabled: chat_enabled(),
+        }
+    }
+}
+
 const fn slave() -> bool {
     false
 }
@@ -73,24 +305,176 @@ pub struct Config {
     pub channels: Vec<Channel>,
     #[serde(rename = "post_process", default = "Ffmpeg::default")]
     pub ffmpeg: Ffmpeg,
+    /// HLS variant (`best`, `worst`, `1080p` ...) 또는 우선순위 목록
+    #[serde(default = "quality")]
+    pub quality: Qualities,
+    /// 앞에 있는 것을 먼저 사용함
+    #[serde(default = "media_types")]
+    pub media_types: Vec<MediaType>,
+    /// 녹화 폴더 이름 (strftime 형식)
+    #[serde(default = "name_template")]
+    pub name_template: String,
+    /// 녹화를 일정 시간 또는 크기마다 여러 파일로 나눔
+    #[serde(default = "Segmentation::default")]
+    pub segment: Segmentation,
     #[serde(default = "Timezone::default")]
     pub timezone: Timezone,
+    #[serde(default = "ChapterLanguage::default")]
+    pub chapter_language: ChapterLanguage,
+    /// `index.mkv` 옆에 추가로 저장할 챕터 파일
+    #[serde(default = "Vec::new")]
+    pub chapter_exports: Vec<ChapterFormat>,
+    #[serde(default = "Resume::default")]
+    pub resume: Resume,
+    /// 방송 상태를 확인하는 간격
+    #[serde(default = "Poll::default")]
+    pub poll: Poll,
+    #[serde(default = "Shutdown::default")]
+    pub shutdown: Shutdown,
+    #[serde(default = "Chat::default")]
+    pub chat: Chat,
     #[serde(default = "slave")]
     pub slave: bool,
     pub master_url: Option<String>,
+    /// master의 `ENCODER_TOKENS` 에 등록한 이 인코더의 토큰
+    pub master_token: Option<String>,
+}
+
+/// 설정을 읽어온 곳
+#[derive(Debug, Clone)]
+pub enum ConfigSource {
+    File(PathBuf),
+    Env,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Env => f.write_str("environment variables"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    /// `channels[1].channel_name`, `post_process.video_codec`, `VIDEO_CODEC` ...
+    pub key: String,
+    pub reason: String,
+}
+
+impl ConfigError {
+    pub fn new(key: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            reason: reason.into(),
+        }
+    }
 }
 
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.reason)
+    }
+}
+
+/// 설정에서 발견한 모든 문제
+#[derive(Debug)]
+pub struct ConfigErrors {
+    pub source: ConfigSource,
+    pub errors: Vec<ConfigError>,
+}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid config ({}):", self.source)?;
+
+        for error in &self.errors {
+            write!(f, "\n  - {error}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
 impl Config {
-    pub fn new() -> Self {
-        Self::from_env().or(Self::from_file()).unwrap()
+    /// `ELLIER_PATH` 환경 변수가 있으면 환경 변수에서, 없으면 `./config.json` 에서 읽음
+    pub fn new() -> Result<Self, ConfigErrors> {
+        if env::var_os("ELLIER_PATH").is_some() {
+            Self::from_env()
+        } else {
+            Self::from_file()
+        }
     }
 
-    pub fn from_file() -> Option<Self> {
-        serde_json::from_slice::<Config>(&fs::read("./config.json").ok()?).ok()
+    pub fn from_file() -> Result<Self, ConfigErrors> {
+        Self::from_path("./config.json")
     }
 
-    pub fn from_env() -> Option<Self> {
-        let auth = match (env_opt("NID_SES"), env_opt("NID_AUT"), env_opt("NID_JKL")) {
+    /// 타입이 잘못된 값은 지우고 다시 읽으면서 키마다 모아둠
+    ///
+    /// 값의 범위나 경로 같은 문제는 타입 문제가 없을 때만 확인함
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ConfigErrors> {
+        let path = path.as_ref();
+        let source = ConfigSource::File(path.to_path_buf());
+
+        let fail = |key: &str, reason: String| ConfigErrors {
+            source: source.clone(),
+            errors: vec![ConfigError::new(key, reason)],
+        };
+
+        let buf = fs::read(path).map_err(|err| fail("(file)", err.to_string()))?;
+
+        let mut value = serde_json::from_slice::<serde_json::Value>(&buf)
+            .map_err(|err| fail("(root)", err.to_string()))?;
+
+        let mut errors = Vec::<ConfigError>::new();
+        let mut removed = Vec::new();
+
+        loop {
+            let err = match serde_path_to_error::deserialize::<_, Config>(&value) {
+                Ok(config) if errors.is_empty() => return config.validated(source),
+                Ok(_) => break,
+                Err(err) => err,
+            };
+
+            let key = original_key(err.path(), &removed);
+
+            // 지운 값 때문에 생긴 `missing field` 는 이미 기록함
+            let reported = errors.iter().any(|x| {
+                x.key.starts_with(&format!("{key}.")) || x.key.starts_with(&format!("{key}["))
+            });
+
+            if !reported {
+                errors.push(ConfigError::new(&key, err.inner().to_string()));
+            }
+
+            match remove_value(&mut value, err.path()) {
+                Some(Removed::Element(index)) => {
+                    let array = key.rsplit_once('[').map_or("", |x| x.0);
+                    removed.push((array.to_owned(), index));
+                }
+                Some(Removed::Field) => {
+                    // 필수 값은 빈 값으로 바꿔서 나머지를 계속 읽음
+                    match key.as_str() {
+                        "path" => value[&key] = "".into(),
+                        "channels" => value[&key] = Vec::<serde_json::Value>::new().into(),
+                        _ => {}
+                    }
+                }
+                None => break,
+            }
+        }
+
+        Err(ConfigErrors { source, errors })
+    }
+
+    pub fn from_env() -> Result<Self, ConfigErrors> {
+        let mut env = Env::default();
+
+        let auth = match (env.opt("NID_SES"), env.opt("NID_AUT"), env.opt("NID_JKL")) {
             (Some(nid_ses), Some(nid_aut), Some(nid_jkl)) => Some(Auth {
                 nid_ses,
                 nid_aut,
@@ -99,51 +483,513 @@ impl Config {
             _ => None,
         };
 
+        let ffmpeg = FfmpegOverride {
+            post_process: env.opt("CHANNEL_ENABLE_POST_PROCESSING"),
+            video_codec: env.opt("CHANNEL_VIDEO_CODEC"),
+            audio_codec: env.opt("CHANNEL_AUDIO_CODEC"),
+            video: Some(env.video("CHANNEL_")).filter(|x| !x.is_empty()),
+            audio: Some(env.audio("CHANNEL_")).filter(|x| !x.is_empty()),
+            output_format: env.opt("CHANNEL_OUTPUT_FORMAT"),
+        };
+
         let channel = Channel {
-            channel_id: env_opt("CHANNEL_ID")?,
-            channel_name: env_opt("CHANNEL_NAME")?,
+            channel_id: env.required("CHANNEL_ID").unwrap_or_default(),
+            channel_name: env.required("CHANNEL_NAME").unwrap_or_default(),
+            path: env.opt("CHANNEL_PATH"),
+            quality: env.opt("CHANNEL_QUALITY"),
+            media_types: env
+                .opt::<String>("CHANNEL_MEDIA_TYPES")
+                .map(|_| env.list("CHANNEL_MEDIA_TYPES")),
+            name_template: env.opt("CHANNEL_NAME_TEMPLATE"),
+            ffmpeg: Some(ffmpeg),
+            segment: Some(env.segment("CHANNEL_")).filter(Segmentation::is_enabled),
         };
 
-        Some(Self {
-            path: env_opt("ELLIER_PATH")?,
+        let poll = Poll::default();
+
+        let config = Self {
+            path: env.required("ELLIER_PATH").unwrap_or_default(),
             auth,
             channels: vec![channel],
             ffmpeg: Ffmpeg {
-                post_process: env_opt("ENABLE_POST_PROCESSING").unwrap_or(false),
+                post_process: env.opt("ENABLE_POST_PROCESSING").unwrap_or(false),
                 ffmpeg_binary: String::new(),
-                video_codec: env_opt("VIDEO_CODEC").unwrap_or_default(),
-                audio_codec: env_opt("AUDIO_CODEC").unwrap_or_default(),
+                video_codec: env.opt("VIDEO_CODEC").unwrap_or_default(),
+                audio_codec: env.opt("AUDIO_CODEC").unwrap_or_default(),
+                video: env.video(""),
+                audio: env.audio(""),
+                output_format: env.opt("OUTPUT_FORMAT").unwrap_or_default(),
             },
+            quality: env.opt("QUALITY").unwrap_or_else(quality),
+            media_types: match env.list("MEDIA_TYPES") {
+                media_types if media_types.is_empty() => self::media_types(),
+                media_types => media_types,
+            },
+            name_template: env.opt("NAME_TEMPLATE").unwrap_or_else(name_template),
+            segment: env.segment(""),
             timezone: Timezone {
-                hours: env_opt("TZ_HOURS").unwrap_or(0),
-                minutes: env_opt("TZ_MINUTES").unwrap_or(0),
-                seconds: env_opt("TZ_SECONDS").unwrap_or(0),
+                hours: env.opt("TZ_HOURS").unwrap_or(0),
+                minutes: env.opt("TZ_MINUTES").unwrap_or(0),
+                seconds: env.opt("TZ_SECONDS").unwrap_or(0),
+            },
+            chapter_language: env.opt("CHAPTER_LANGUAGE").unwrap_or_default(),
+            chapter_exports: env.list("CHAPTER_EXPORTS"),
+            resume: Resume {
+                grace_secs: env.opt("RESUME_GRACE_SECS").unwrap_or(0),
+                concat: env.opt("RESUME_CONCAT").unwrap_or(false),
             },
-            slave: env_opt("SLAVE").unwrap_or(false),
-            master_url: env_opt("MASTER_URL"),
-        })
+            poll: Poll {
+                offline_secs: env.opt("POLL_OFFLINE_SECS").unwrap_or(poll.offline_secs),
+                live_secs: env.opt("POLL_LIVE_SECS").unwrap_or(poll.live_secs),
+                error_secs: env.opt("POLL_ERROR_SECS").unwrap_or(poll.error_secs),
+                max_error_secs: env
+                    .opt("POLL_MAX_ERROR_SECS")
+                    .unwrap_or(poll.max_error_secs),
+                jitter: env.opt("POLL_JITTER").unwrap_or(poll.jitter),
+            },
+            shutdown: Shutdown {
+                timeout_secs: env
+                    .opt("SHUTDOWN_TIMEOUT_SECS")
+                    .unwrap_or_else(shutdown_timeout_secs),
+            },
+            chat: Chat {
+                enabled: env.opt("CHAT_ENABLED").unwrap_or_else(chat_enabled),
+            },
+            slave: env.opt("SLAVE").unwrap_or(false),
+            master_url: env.opt("MASTER_URL"),
+            master_token: env.opt("MASTER_TOKEN"),
+        };
+
+        if !env.errors.is_empty() {
+            return Err(ConfigErrors {
+                source: ConfigSource::Env,
+                errors: env.errors,
+            });
+        }
+
+        config.validated(ConfigSource::Env)
+    }
+
+    fn validated(self, source: ConfigSource) -> Result<Self, ConfigErrors> {
+        let errors = self.validate();
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(ConfigErrors { source, errors })
+        }
+    }
+
+    /// 문제를 한 번에 모두 찾아서 반환함
+    pub fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        check_path(&mut errors, "path", &self.path);
+        check_ffmpeg(&mut errors, "post_process", &self.ffmpeg);
+        check_segment(&mut errors, "segment", &self.segment);
+
+        for (sub_key, reason) in self.poll.check() {
+            errors.push(ConfigError::new(format!("poll.{sub_key}"), reason));
+        }
+        if self.shutdown.timeout_secs == 0 {
+            errors.push(ConfigError::new(
+                "shutdown.timeout_secs",
+                "must be greater than 0",
+            ));
+        }
+        check_quality(&mut errors, "quality", &self.quality);
+        check_media_types(&mut errors, "media_types", &self.media_types);
+        check_name_template(&mut errors, "name_template", &self.name_template);
+
+        if self.channels.is_empty() {
+            errors.push(ConfigError::new("channels", "must not be empty"));
+        }
+
+        for (i, channel) in self.channels.iter().enumerate() {
+            if channel.channel_id.trim().is_empty() {
+                errors.push(ConfigError::new(
+                    format!("channels[{i}].channel_id"),
+                    "must not be empty",
+                ));
+            }
+
+            if channel.channel_name.trim().is_empty() {
+                errors.push(ConfigError::new(
+                    format!("channels[{i}].channel_name"),
+                    "must not be empty",
+                ));
+            } else if channel.channel_name.contains(['/', '\\']) {
+                errors.push(ConfigError::new(
+                    format!("channels[{i}].channel_name"),
+                    "must not contain path separators",
+                ));
+            }
+
+            if let Some(path) = &channel.path {
+                check_path(&mut errors, &format!("channels[{i}].path"), path);
+            }
+
+            if let Some(quality) = &channel.quality {
+                check_quality(&mut errors, &format!("channels[{i}].quality"), quality);
+            }
+
+            if let Some(media_types) = &channel.media_types {
+                check_media_types(
+                    &mut errors,
+                    &format!("channels[{i}].media_types"),
+                    media_types,
+                );
+            }
+
+            if let Some(name_template) = &channel.name_template {
+                check_name_template(
+                    &mut errors,
+                    &format!("channels[{i}].name_template"),
+                    name_template,
+                );
+            }
+
+            if let Some(ffmpeg) = &channel.ffmpeg {
+                check_ffmpeg(
+                    &mut errors,
+                    &format!("channels[{i}].post_process"),
+                    &ffmpeg.apply(&self.ffmpeg),
+                );
+            }
+
+            if let Some(segment) = &channel.segment {
+                check_segment(&mut errors, &format!("channels[{i}].segment"), segment);
+            }
+
+            if let Some(j) = self.channels[..i]
+                .iter()
+                .position(|x| x.channel_name == channel.channel_name)
+            {
+                errors.push(ConfigError::new(
+                    format!("channels[{i}].channel_name"),
+                    format!(
+                        "duplicate channel name {:?} (same as channels[{j}])",
+                        channel.channel_name
+                    ),
+                ));
+            }
+        }
+
+        let master_url = self.master_url.as_deref().map(str::trim);
+
+        if self.slave && matches!(master_url, None | Some("")) {
+            errors.push(ConfigError::new(
+                "master_url",
+                "required when slave is true",
+            ));
+        }
+
+        let master_token = self.master_token.as_deref().map(str::trim);
+
+        if self.slave && matches!(master_token, None | Some("")) {
+            errors.push(ConfigError::new(
+                "master_token",
+                "required when slave is true",
+            ));
+        }
+
+        let Timezone {
+            hours,
+            minutes,
+            seconds,
+        } = self.timezone;
+
+        if chrono::FixedOffset::east_opt(
+            (hours as i32 * 3600) + (minutes as i32 * 60) + seconds as i32,
+        )
+        .is_none()
+        {
+            errors.push(ConfigError::new("timezone", "out of range"));
+        }
+
+        if self.chapter_language.0.is_empty() {
+            errors.push(ConfigError::new("chapter_language", "must not be empty"));
+        }
+
+        errors
     }
 }
 
-// fn env<T>(key: &str) -> T
-// where
-//     T: FromStr,
-//     <T as FromStr>::Err: Debug,
-// {
-//     let var = match env::var(key) {
-//         Ok(r) => r,
-//         Err(_) => panic!("not set {key}"),
-//     };
+fn check_path(errors: &mut Vec<ConfigError>, key: &str, path: &str) {
+    if path.trim().is_empty() {
+        errors.push(ConfigError::new(key, "must not be empty"));
+    } else if let Err(err) = check_writable(Path::new(path)) {
+        errors.push(ConfigError::new(key, format!("not writable: {err}")));
+    }
+}
 
-//     var.parse().expect("Please set dotenv to valid value")
-// }
+fn check_ffmpeg(errors: &mut Vec<ConfigError>, key: &str, ffmpeg: &Ffmpeg) {
+    for (sub_key, reason) in ffmpeg.check() {
+        errors.push(ConfigError::new(format!("{key}.{sub_key}"), reason));
+    }
+}
+
+fn check_segment(errors: &mut Vec<ConfigError>, key: &str, segment: &Segmentation) {
+    match segment.minutes {
+        Some(0) => errors.push(ConfigError::new(
+            format!("{key}.minutes"),
+            "must be greater than 0",
+        )),
+        Some(x) if x.checked_mul(60).is_none() => errors.push(ConfigError::new(
+            format!("{key}.minutes"),
+            format!("must be at most {}", u64::MAX / 60),
+        )),
+        _ => {}
+    }
+
+    if segment.gigabytes.is_some_and(|x| x.is_nan() || x <= 0.0) {
+        errors.push(ConfigError::new(
+            format!("{key}.gigabytes"),
+            "must be greater than 0",
+        ));
+    }
+}
+
+fn check_quality(errors: &mut Vec<ConfigError>, key: &str, quality: &Qualities) {
+    if quality.0.is_empty() {
+        errors.push(ConfigError::new(key, "must not be empty"));
+    }
+
+    for (i, quality) in quality.0.iter().enumerate() {
+        if quality.trim().is_empty() {
+            errors.push(ConfigError::new(format!("{key}[{i}]"), "must not be empty"));
+        }
+    }
+}
+
+fn check_media_types(errors: &mut Vec<ConfigError>, key: &str, media_types: &[MediaType]) {
+    if media_types.is_empty() {
+        errors.push(ConfigError::new(key, "must not be empty"));
+    }
+}
+
+/// 잘못된 형식이면 `format` 이 panic 하므로 미리 확인함
+fn check_name_template(errors: &mut Vec<ConfigError>, key: &str, name_template: &str) {
+    use chrono::format::{Item, StrftimeItems};
 
-fn env_opt<T>(key: &str) -> Option<T>
-where
-    T: FromStr,
-    <T as FromStr>::Err: Debug,
-{
-    env::var(key)
-        .ok()
-        .map(|var| var.parse().expect("Please set dotenv to valid value"))
+    if name_template.trim().is_empty() {
+        errors.push(ConfigError::new(key, "must not be empty"));
+    } else if StrftimeItems::new(name_template).any(|item| matches!(item, Item::Error)) {
+        errors.push(ConfigError::new(
+            key,
+            format!("invalid strftime format {name_template:?}"),
+        ));
+    } else if name_template.contains(['/', '\\']) {
+        errors.push(ConfigError::new(key, "must not contain path separators"));
+    }
+}
+
+/// 가장 가까운 이미 있는 상위 폴더에 파일을 만들 수 있는지 확인함
+///
+/// 폴더는 녹화를 시작할 때 만듦
+fn check_writable(path: &Path) -> io::Result<()> {
+    let mut existing = path;
+
+    let metadata = loop {
+        match fs::metadata(existing) {
+            Ok(metadata) => break metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                existing = match existing.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent,
+                    _ => Path::new("."),
+                };
+            }
+            Err(err) => return Err(err),
+        }
+    };
+
+    if !metadata.is_dir() {
+        return Err(io::Error::other(format!(
+            "{} is not a directory",
+            existing.display()
+        )));
+    }
+
+    // 권한 비트만으로는 소유자, ACL, 읽기 전용 마운트를 알 수 없으므로 직접 파일을 만들어 봄
+    let probe = existing.join(format!(".write-test-{}", std::process::id()));
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", existing.display())))?;
+
+    fs::remove_file(&probe)
+}
+
+/// 설정 파일에서 지운 값
+enum Removed {
+    Field,
+    /// 배열에서 지울 때의 index
+    Element(usize),
+}
+
+/// `path` 의 값을 지움, 지울 수 없으면 `None`
+fn remove_value(
+    value: &mut serde_json::Value,
+    path: &serde_path_to_error::Path,
+) -> Option<Removed> {
+    use serde_json::Value;
+    use serde_path_to_error::Segment;
+
+    let segments = path.iter().collect::<Vec<_>>();
+    let (last, parents) = segments.split_last()?;
+
+    let mut value = value;
+
+    for segment in parents {
+        value = match (segment, value) {
+            (Segment::Seq { index }, Value::Array(x)) => x.get_mut(*index)?,
+            (Segment::Map { key } | Segment::Enum { variant: key }, Value::Object(x)) => {
+                x.get_mut(key)?
+            }
+            _ => return None,
+        };
+    }
+
+    match (last, value) {
+        (Segment::Seq { index }, Value::Array(x)) if *index < x.len() => {
+            x.remove(*index);
+            Some(Removed::Element(*index))
+        }
+        (Segment::Map { key } | Segment::Enum { variant: key }, Value::Object(x)) => {
+            x.remove(key).map(|_| Removed::Field)
+        }
+        _ => None,
+    }
+}
+
+/// 배열의 원소를 지우기 전의 index로 키를 만듦
+///
+/// `removed`: 지운 순서대로 (배열의 키, 지울 때의 index)
+fn original_key(path: &serde_path_to_error::Path, removed: &[(String, usize)]) -> String {
+    use serde_path_to_error::Segment;
+
+    let mut segments = Vec::new();
+
+    for segment in path.iter() {
+        let segment = match segment {
+            Segment::Seq { index } => {
+                let array = join_key(&segments);
+
+                let index = removed
+                    .iter()
+                    .rev()
+                    .filter(|(x, _)| *x == array)
+                    .fold(*index, |index, (_, x)| index + usize::from(index >= *x));
+
+                format!("[{index}]")
+            }
+            segment => segment.to_string(),
+        };
+
+        segments.push(segment);
+    }
+
+    let key = join_key(&segments);
+
+    if key.is_empty() {
+        "(root)".to_owned()
+    } else {
+        key
+    }
+}
+
+/// `["channels", "[1]", "path"]` -> `channels[1].path`
+fn join_key(segments: &[String]) -> String {
+    let mut key = String::new();
+
+    for segment in segments {
+        if !key.is_empty() && !segment.starts_with('[') {
+            key.push('.');
+        }
+
+        key.push_str(segment);
+    }
+
+    key
+}
+
+/// 환경 변수를 읽으면서 발견한 문제를 모아둠
+#[derive(Default)]
+struct Env {
+    errors: Vec<ConfigError>,
+}
+
+impl Env {
+    fn opt<T: FromStr>(&mut self, key: &str) -> Option<T> {
+        let var = env::var(key).ok()?;
+
+        match var.trim().parse() {
+            Ok(r) => Some(r),
+            Err(_) => {
+                self.errors
+                    .push(ConfigError::new(key, format!("invalid value {var:?}")));
+                None
+            }
+        }
+    }
+
+    fn required<T: FromStr>(&mut self, key: &str) -> Option<T> {
+        if env::var_os(key).is_none() {
+            self.errors.push(ConfigError::new(key, "not set"));
+            return None;
+        }
+
+        self.opt(key)
+    }
+
+    /// 쉼표로 구분된 목록
+    fn list<T: FromStr>(&mut self, key: &str) -> Vec<T> {
+        let Some(var) = env::var(key).ok() else {
+            return Vec::new();
+        };
+
+        var.split(',')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .filter_map(|x| match x.parse() {
+                Ok(r) => Some(r),
+                Err(_) => {
+                    self.errors
+                        .push(ConfigError::new(key, format!("invalid value {x:?}")));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// `{prefix}VIDEO_PRESET`, `{prefix}VIDEO_CRF` ...
+    fn video(&mut self, prefix: &str) -> VideoOptions {
+        VideoOptions {
+            preset: self.opt(&format!("{prefix}VIDEO_PRESET")),
+            crf: self.opt(&format!("{prefix}VIDEO_CRF")),
+            bitrate: self.opt(&format!("{prefix}VIDEO_BITRATE")),
+            width: self.opt(&format!("{prefix}VIDEO_WIDTH")),
+            height: self.opt(&format!("{prefix}VIDEO_HEIGHT")),
+            fps: self.opt(&format!("{prefix}VIDEO_FPS")),
+        }
+    }
+
+    /// `{prefix}AUDIO_BITRATE`, `{prefix}AUDIO_SAMPLE_RATE` ...
+    fn audio(&mut self, prefix: &str) -> AudioOptions {
+        AudioOptions {
+            bitrate: self.opt(&format!("{prefix}AUDIO_BITRATE")),
+            sample_rate: self.opt(&format!("{prefix}AUDIO_SAMPLE_RATE")),
+            channels: self.opt(&format!("{prefix}AUDIO_CHANNELS")),
+        }
+    }
+
+    /// `{prefix}SEGMENT_MINUTES`, `{prefix}SEGMENT_GIGABYTES`
+    fn segment(&mut self, prefix: &str) -> Segmentation {
+        Segmentation {
+            minutes: self.opt(&format!("{prefix}SEGMENT_MINUTES")),
+            gigabytes: self.opt(&format!("{prefix}SEGMENT_GIGABYTES")),
+        }
+    }
 }
@@ -0,0 +1,194 @@
+//! master에 인코더를 등록하고 채널마다 녹화 상태를 보고함
+
+use std::{path::Path, time::Duration};
+
+use reqwest::StatusCode;
+use serde::Serialize;
+use tokio::{
+    sync::watch,
+    time::{interval, MissedTickBehavior},
+};
+
+use crate::{
+    recorder::{Phase, Status},
+    time::Time,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("status: {0}")]
+    Status(StatusCode),
+    /// master가 재시작되어 등록 정보가 없음
+    #[error("not registered")]
+    NotRegistered,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelInfo {
+    pub channel_id: String,
+    pub channel_name: String,
+}
+
+/// `POST /encoders/register`
+#[derive(Debug, Clone, Serialize)]
+pub struct Register {
+    /// 쿠버네티스 pod 이름
+    pub pod: String,
+    pub version: String,
+    pub channels: Vec<ChannelInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelReport {
+    pub channel_id: String,
+    pub channel_name: String,
+    pub state: Phase,
+    pub live_id: Option<String>,
+    pub title: Option<String>,
+    pub category: Option<String>,
+    /// 녹화된 길이(초)
+    pub elapsed_secs: u64,
+    /// `01:02:03`
+    pub elapsed: String,
+    /// 녹화 폴더에 있는 파일들의 크기 합
+    pub bytes_written: u64,
+}
+
+impl ChannelReport {
+    /// 녹화 폴더의 크기를 구하므로 async 함수에서는 `spawn_blocking` 으로 호출해야 함
+    pub fn new(channel: &ChannelInfo, status: &Status) -> Self {
+        let elapsed = Time::from(status.elapsed());
+
+        Self {
+            channel_id: channel.channel_id.clone(),
+            channel_name: channel.channel_name.clone(),
+            state: status.phase,
+            live_id: status.live_id.clone(),
+            title: status.title.clone(),
+            category: status.category.clone(),
+            elapsed_secs: elapsed.as_secs(),
+            elapsed: elapsed.to_readable(":"),
+            bytes_written: status
+                .directory
+                .as_deref()
+                .map(directory_size)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// `POST /encoders/heartbeat`
+#[derive(Debug, Clone, Serialize)]
+pub struct Heartbeat {
+    /// 같은 토큰을 사용하는 다른 pod와 구분함
+    pub pod: String,
+    pub channels: Vec<ChannelReport>,
+}
+
+fn directory_size(directory: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+pub struct ControlClient {
+    pub http: reqwest::Client,
+    pub master_url: String,
+    pub token: String,
+}
+
+impl ControlClient {
+    async fn post(&self, path: &str, body: &impl Serialize) -> Result<(), Error> {
+        let resp = self
+            .http
+            .post(format!("{}{path}", self.master_url))
+            .bearer_auth(&self.token)
+            .json(body)
+            .send()
+            .await?;
+
+        match resp.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::NOT_FOUND => Err(Error::NotRegistered),
+            status => Err(Error::Status(status)),
+        }
+    }
+
+    pub async fn register(&self, register: &Register) -> Result<(), Error> {
+        self.post("/encoders/register", register).await
+    }
+
+    pub async fn heartbeat(&self, heartbeat: &Heartbeat) -> Result<(), Error> {
+        self.post("/encoders/heartbeat", heartbeat).await
+    }
+
+    /// `interval` 마다 상태를 보고함, 등록되지 않았으면 다시 등록함
+    pub async fn run(
+        self,
+        register: Register,
+        statuses: Vec<watch::Receiver<Status>>,
+        every: Duration,
+    ) {
+        let mut registered = false;
+        let mut ticker = interval(every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            if !registered {
+                match self.register(&register).await {
+                    Ok(()) => {
+                        println!("control: registered as {}", register.pod);
+                        registered = true;
+                    }
+                    Err(err) => {
+                        eprintln!("control: register: {err}");
+                        continue;
+                    }
+                }
+            }
+
+            let snapshot = register
+                .channels
+                .iter()
+                .cloned()
+                .zip(statuses.iter().map(|status| status.borrow().clone()))
+                .collect::<Vec<_>>();
+
+            // 녹화 폴더의 크기를 구하는 동안 다른 채널의 다운로드를 막지 않도록 blocking 스레드에서 만듦
+            let channels = tokio::task::spawn_blocking(move || {
+                snapshot
+                    .iter()
+                    .map(|(channel, status)| ChannelReport::new(channel, status))
+                    .collect()
+            })
+            .await;
+
+            let heartbeat = match channels {
+                Ok(channels) => Heartbeat {
+                    pod: register.pod.clone(),
+                    channels,
+                },
+                Err(err) => {
+                    eprintln!("control: heartbeat: {err}");
+                    continue;
+                }
+            };
+
+            match self.heartbeat(&heartbeat).await {
+                Ok(()) => {}
+                Err(Error::NotRegistered) => registered = false,
+                Err(err) => eprintln!("control: heartbeat: {err}"),
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
-use std::io;
+use std::{io, process::ExitStatus};
 
 use chzzk::live::{get_live_detail, get_live_status};
 
+use crate::hls;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("io: {0}")]
@@ -15,4 +17,22 @@ pub enum Error {
     GetLiveStatus(#[from] get_live_status::Error),
     #[error("get_live_detail: {0}")]
     GetLiveDetail(#[from] get_live_detail::Error),
+    #[error("live_source: {0}")]
+    LiveSource(String),
+
+    #[error("hls: {0}")]
+    Hls(#[from] hls::Error),
+
+    #[error("ffprobe: exited with {status}: {stderr}")]
+    Ffprobe { status: ExitStatus, stderr: String },
+}
+
+impl Error {
+    /// 방송 상태를 가져오지 못함
+    pub fn is_api(&self) -> bool {
+        matches!(
+            self,
+            Error::GetLiveStatus(_) | Error::GetLiveDetail(_) | Error::LiveSource(_)
+        )
+    }
 }
@@ -1,12 +1,13 @@
-use std::str::FromStr;
+use std::{io, path::Path, process::Stdio, str::FromStr};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio::{fs, process::Command};
 
 const fn post_process() -> bool {
     false
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct Ffmpeg {
     #[serde(rename = "enable", default = "post_process")]
     pub post_process: bool,
@@ -16,8 +17,14 @@ pub struct Ffmpeg {
     pub video_codec: VideoCodec,
     #[serde(default = "AudioCodec::default")]
     pub audio_codec: AudioCodec,
-    // #[serde(default = "OutputFormat::default")]
-    // pub output_format: OutputFormat,
+    /// `video_codec` 이 `copy` 가 아닐 때만 사용함
+    #[serde(default = "VideoOptions::default")]
+    pub video: VideoOptions,
+    /// `audio_codec` 이 `copy` 가 아닐 때만 사용함
+    #[serde(default = "AudioOptions::default")]
+    pub audio: AudioOptions,
+    #[serde(default = "OutputFormat::default")]
+    pub output_format: OutputFormat,
 }
 
 impl Default for Ffmpeg {
@@ -29,7 +36,9 @@ impl Default for Ffmpeg {
                 ffmpeg_binary: String::new(),
                 video_codec: VideoCodec::HevcVideotoolbox,
                 audio_codec: AudioCodec::Copy,
-                // output_format: OutputFormat::Matroska,
+                video: VideoOptions::default(),
+                audio: AudioOptions::default(),
+                output_format: OutputFormat::Matroska,
             }
         }
         #[cfg(not(target_os = "macos"))]
@@ -39,17 +48,276 @@ impl Default for Ffmpeg {
                 ffmpeg_binary: String::new(),
                 video_codec: VideoCodec::Copy,
                 audio_codec: AudioCodec::Copy,
-                // output_format: OutputFormat::Matroska,
+                video: VideoOptions::default(),
+                audio: AudioOptions::default(),
+                output_format: OutputFormat::Matroska,
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+impl Ffmpeg {
+    /// `-c:v ... -c:a ...`
+    ///
+    /// 후처리를 하지 않으면 둘 다 `copy`
+    pub fn codec_args(&self) -> Vec<String> {
+        let (video_codec, audio_codec) = if self.post_process {
+            (self.video_codec, self.audio_codec)
+        } else {
+            (VideoCodec::Copy, AudioCodec::Copy)
+        };
+
+        let mut args = vec!["-c:v".to_owned(), video_codec.as_str().to_owned()];
+
+        if video_codec != VideoCodec::Copy {
+            args.extend(self.video.args());
+        }
+
+        args.extend(["-c:a".to_owned(), audio_codec.as_str().to_owned()]);
+
+        if audio_codec != AudioCodec::Copy {
+            args.extend(self.audio.args());
+        }
+
+        args
+    }
+
+    /// returns (key, reason)
+    pub fn check(&self) -> Vec<(&'static str, String)> {
+        let mut errors = Vec::new();
+
+        let VideoOptions {
+            preset,
+            crf,
+            bitrate,
+            width,
+            height,
+            fps,
+        } = &self.video;
+
+        if self.video_codec == VideoCodec::Copy {
+            if !self.video.is_empty() {
+                errors.push(("video", "can't be used with video_codec copy".to_owned()));
+            }
+        } else {
+            if let Some(crf) = crf {
+                match self.video_codec.max_crf() {
+                    Some(max) if *crf > max => {
+                        errors.push(("video.crf", format!("must be between 0 and {max}")))
+                    }
+                    Some(_) => {}
+                    None => errors.push((
+                        "video.crf",
+                        format!("{} doesn't support crf", self.video_codec.as_str()),
+                    )),
+                }
+
+                if bitrate.is_some() {
+                    errors.push(("video.bitrate", "can't be used with crf".to_owned()));
+                }
+            }
+
+            if preset.is_some() && !self.video_codec.has_preset() {
+                errors.push((
+                    "video.preset",
+                    format!("{} doesn't support preset", self.video_codec.as_str()),
+                ));
+            }
+
+            if width.is_some_and(|x| x == 0) {
+                errors.push(("video.width", "must be greater than 0".to_owned()));
+            }
+
+            if height.is_some_and(|x| x == 0) {
+                errors.push(("video.height", "must be greater than 0".to_owned()));
+            }
+
+            if fps.is_some_and(|x| x.is_nan() || x <= 0.0) {
+                errors.push(("video.fps", "must be greater than 0".to_owned()));
+            }
+        }
+
+        if self.audio_codec == AudioCodec::Copy {
+            if !self.audio.is_empty() {
+                errors.push(("audio", "can't be used with audio_codec copy".to_owned()));
+            }
+        } else if self.audio.channels.is_some_and(|x| x == 0) {
+            errors.push(("audio.channels", "must be greater than 0".to_owned()));
+        }
+
+        errors
+    }
+}
+
+/// 채널마다 덮어쓸 후처리 설정
+///
+/// 없는 값은 전역 `post_process` 를 따름
+#[derive(Clone, Default, Deserialize)]
+pub struct FfmpegOverride {
+    #[serde(rename = "enable", default)]
+    pub post_process: Option<bool>,
+    #[serde(default)]
+    pub video_codec: Option<VideoCodec>,
+    #[serde(default)]
+    pub audio_codec: Option<AudioCodec>,
+    #[serde(default)]
+    pub video: Option<VideoOptions>,
+    #[serde(default)]
+    pub audio: Option<AudioOptions>,
+    #[serde(default)]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl FfmpegOverride {
+    pub fn apply(&self, ffmpeg: &Ffmpeg) -> Ffmpeg {
+        Ffmpeg {
+            post_process: self.post_process.unwrap_or(ffmpeg.post_process),
+            ffmpeg_binary: ffmpeg.ffmpeg_binary.clone(),
+            video_codec: self.video_codec.unwrap_or(ffmpeg.video_codec),
+            audio_codec: self.audio_codec.unwrap_or(ffmpeg.audio_codec),
+            video: self.video.clone().unwrap_or_else(|| ffmpeg.video.clone()),
+            audio: self.audio.clone().unwrap_or_else(|| ffmpeg.audio.clone()),
+            output_format: self.output_format.unwrap_or(ffmpeg.output_format),
+        }
+    }
+}
+
+/// 영상 인코더 옵션
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VideoOptions {
+    /// `ultrafast` ~ `veryslow` (libx264, libx265), `0` ~ `13` (libsvtav1)
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub crf: Option<u8>,
+    /// `6M`, `2500k` ...
+    #[serde(default)]
+    pub bitrate: Option<String>,
+    /// 한쪽만 지정하면 비율을 유지함
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub fps: Option<f64>,
+}
+
+impl VideoOptions {
+    pub fn is_empty(&self) -> bool {
+        let Self {
+            preset,
+            crf,
+            bitrate,
+            width,
+            height,
+            fps,
+        } = self;
+
+        preset.is_none()
+            && crf.is_none()
+            && bitrate.is_none()
+            && width.is_none()
+            && height.is_none()
+            && fps.is_none()
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        let Self {
+            preset,
+            crf,
+            bitrate,
+            width,
+            height,
+            fps,
+        } = self;
+
+        let mut args = Vec::new();
+
+        if let Some(preset) = preset {
+            args.extend(["-preset".to_owned(), preset.clone()]);
+        }
+
+        if let Some(crf) = crf {
+            args.extend(["-crf".to_owned(), crf.to_string()]);
+        }
+
+        if let Some(bitrate) = bitrate {
+            args.extend(["-b:v".to_owned(), bitrate.clone()]);
+        }
+
+        let mut filters = Vec::new();
+
+        if width.is_some() || height.is_some() {
+            // -2: 비율을 유지하면서 짝수로 맞춤
+            let size = |x: &Option<u32>| x.map_or("-2".to_owned(), |x| x.to_string());
+            filters.push(format!("scale={}:{}", size(width), size(height)));
+        }
+
+        if let Some(fps) = fps {
+            filters.push(format!("fps={fps}"));
+        }
+
+        if !filters.is_empty() {
+            args.extend(["-vf".to_owned(), filters.join(",")]);
+        }
+
+        args
+    }
+}
+
+/// 음성 인코더 옵션
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AudioOptions {
+    /// `160k` ...
+    #[serde(default)]
+    pub bitrate: Option<String>,
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+    #[serde(default)]
+    pub channels: Option<u8>,
+}
+
+impl AudioOptions {
+    pub fn is_empty(&self) -> bool {
+        self.bitrate.is_none() && self.sample_rate.is_none() && self.channels.is_none()
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        let Self {
+            bitrate,
+            sample_rate,
+            channels,
+        } = self;
+
+        let mut args = Vec::new();
+
+        if let Some(bitrate) = bitrate {
+            args.extend(["-b:a".to_owned(), bitrate.clone()]);
+        }
+
+        if let Some(sample_rate) = sample_rate {
+            args.extend(["-ar".to_owned(), sample_rate.to_string()]);
+        }
+
+        if let Some(channels) = channels {
+            args.extend(["-ac".to_owned(), channels.to_string()]);
+        }
+
+        args
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum VideoCodec {
     #[cfg(target_os = "macos")]
     #[serde(rename = "hevc_videotoolbox")]
     HevcVideotoolbox,
+    #[serde(rename = "libx264")]
+    Libx264,
+    #[serde(rename = "libx265")]
+    Libx265,
+    #[serde(rename = "libsvtav1")]
+    Libsvtav1,
     #[serde(rename = "copy")]
     Copy,
 }
@@ -72,9 +340,28 @@ impl VideoCodec {
         match self {
             #[cfg(target_os = "macos")]
             VideoCodec::HevcVideotoolbox => "hevc_videotoolbox",
+            VideoCodec::Libx264 => "libx264",
+            VideoCodec::Libx265 => "libx265",
+            VideoCodec::Libsvtav1 => "libsvtav1",
             VideoCodec::Copy => "copy",
         }
     }
+
+    /// crf를 지원하지 않으면 None
+    pub fn max_crf(&self) -> Option<u8> {
+        match self {
+            VideoCodec::Libx264 | VideoCodec::Libx265 => Some(51),
+            VideoCodec::Libsvtav1 => Some(63),
+            _ => None,
+        }
+    }
+
+    pub fn has_preset(&self) -> bool {
+        matches!(
+            self,
+            VideoCodec::Libx264 | VideoCodec::Libx265 | VideoCodec::Libsvtav1
+        )
+    }
 }
 
 impl FromStr for VideoCodec {
@@ -84,6 +371,9 @@ impl FromStr for VideoCodec {
         let r = match s {
             #[cfg(target_os = "macos")]
             "hevc_videotoolbox" => VideoCodec::HevcVideotoolbox,
+            "libx264" => VideoCodec::Libx264,
+            "libx265" => VideoCodec::Libx265,
+            "libsvtav1" => VideoCodec::Libsvtav1,
             "copy" => VideoCodec::Copy,
             _ => return Err(()),
         };
@@ -92,11 +382,15 @@ impl FromStr for VideoCodec {
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum AudioCodec {
     #[cfg(target_os = "macos")]
     #[serde(rename = "aac_at")]
     AacAudiotoolbox,
+    #[serde(rename = "aac")]
+    Aac,
+    #[serde(rename = "libopus")]
+    Libopus,
     #[serde(rename = "copy")]
     Copy,
 }
@@ -112,6 +406,8 @@ impl AudioCodec {
         match self {
             #[cfg(target_os = "macos")]
             AudioCodec::AacAudiotoolbox => "aac_at",
+            AudioCodec::Aac => "aac",
+            AudioCodec::Libopus => "libopus",
             AudioCodec::Copy => "copy",
         }
     }
@@ -124,6 +420,8 @@ impl FromStr for AudioCodec {
         let r = match s {
             #[cfg(target_os = "macos")]
             "aac_at" => AudioCodec::AacAudiotoolbox,
+            "aac" => AudioCodec::Aac,
+            "libopus" => AudioCodec::Libopus,
             "copy" => AudioCodec::Copy,
             _ => return Err(()),
         };
@@ -132,12 +430,17 @@ impl FromStr for AudioCodec {
     }
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OutputFormat {
     #[serde(rename = "matroska")]
     Matroska,
     #[serde(rename = "mpegts")]
     Mpegts,
+    /// fragmented mp4
+    ///
+    /// 녹화가 끝나면 챕터를 넣으면서 일반 mp4(faststart)로 다시 저장함
+    #[serde(rename = "mp4")]
+    Mp4,
 }
 
 impl Default for OutputFormat {
@@ -151,6 +454,7 @@ impl OutputFormat {
         match self {
             OutputFormat::Matroska => "matroska",
             OutputFormat::Mpegts => "mpegts",
+            OutputFormat::Mp4 => "mp4",
         }
     }
 
@@ -158,6 +462,187 @@ impl OutputFormat {
         match self {
             OutputFormat::Matroska => "mkv",
             OutputFormat::Mpegts => "ts",
+            OutputFormat::Mp4 => "mp4",
+        }
+    }
+
+    /// 녹화할 때 사용하는 muxer 옵션
+    pub fn muxer_args(&self) -> &'static [&'static str] {
+        match self {
+            OutputFormat::Matroska => &["-f", "matroska"],
+            OutputFormat::Mpegts => &["-f", "mpegts"],
+            // 녹화 중에 끊겨도 재생할 수 있도록 moov를 앞에 두고 조각내서 씀
+            OutputFormat::Mp4 => &[
+                "-f",
+                "mp4",
+                "-movflags",
+                "+frag_keyframe+empty_moov+default_base_moof",
+            ],
         }
     }
 }
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let r = match s {
+            "matroska" => OutputFormat::Matroska,
+            "mpegts" => OutputFormat::Mpegts,
+            "mp4" => OutputFormat::Mp4,
+            _ => return Err(()),
+        };
+
+        Ok(r)
+    }
+}
+
+/// https://ffmpeg.org/ffmpeg-formats.html#Metadata-2
+pub fn escape_metadata(s: &str) -> String {
+    // (‘=’, ‘;’, ‘#’, ‘\’ and a newline) must be escaped with a backslash ‘\’.
+
+    [
+        ('\\', "\\\\"),
+        ('=', "\\="),
+        (';', "\\;"),
+        ('#', "\\#"),
+        ('\n', " "),
+    ]
+    .into_iter()
+    .fold(s.to_owned(), |s, (a, b)| s.replace(a, b))
+}
+
+/// concat demuxer로 `files` 를 재인코딩 없이 `output` 하나로 합침
+///
+/// `files`, `output` 은 `directory` 기준 경로
+pub async fn concat(
+    ffmpeg_binary: &str,
+    directory: &Path,
+    files: &[&str],
+    output: &str,
+) -> io::Result<bool> {
+    // https://ffmpeg.org/ffmpeg-formats.html#concat-1
+    let list = files
+        .iter()
+        .map(|file| format!("file '{}'\n", file.replace('\'', "'\\''")))
+        .collect::<String>();
+
+    let list_file = directory.join("concat.txt");
+
+    fs::write(&list_file, list).await?;
+
+    let status = Command::new(ffmpeg_binary.trim())
+        .current_dir(directory)
+        .args([
+            "-hide_banner",
+            "-nostats",
+            "-loglevel",
+            "error",
+            "-y",
+            "-f",
+            "concat",
+            "-safe",
+            "0",
+            "-i",
+            "concat.txt",
+            "-map",
+            "0",
+            "-c",
+            "copy",
+            output,
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .status()
+        .await?;
+
+    fs::remove_file(&list_file).await.ok();
+
+    Ok(status.success())
+}
+
+/// 강제로 종료되어 인덱스가 없거나 끝이 잘린 `input` 을 재인코딩 없이 다시 mux해서 `output` 으로 저장함
+pub async fn remux(
+    ffmpeg_binary: &str,
+    input: &Path,
+    output: &Path,
+    format: OutputFormat,
+) -> io::Result<bool> {
+    let mut ffmpeg = Command::new(ffmpeg_binary.trim());
+
+    ffmpeg
+        .args([
+            "-hide_banner",
+            "-nostats",
+            "-loglevel",
+            "error",
+            "-y",
+            "-err_detect",
+            "ignore_err",
+            "-fflags",
+            "+genpts+discardcorrupt",
+            "-i",
+        ])
+        .arg(input)
+        .args(["-map", "0", "-c", "copy", "-f", format.as_str()]);
+
+    if format == OutputFormat::Mp4 {
+        ffmpeg.args(["-movflags", "+faststart"]);
+    }
+
+    let status = ffmpeg
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .status()
+        .await?;
+
+    Ok(status.success())
+}
+
+/// FFMETADATA 파일인 `metadata` 의 챕터를 `input` 에 넣어서 `output` 으로 저장함
+///
+/// matroska가 아닌 파일은 mkvpropedit을 사용할 수 없으므로 다시 mux함
+pub async fn mux_chapters(
+    ffmpeg_binary: &str,
+    input: &Path,
+    metadata: &Path,
+    output: &Path,
+    format: OutputFormat,
+) -> io::Result<bool> {
+    let mut ffmpeg = Command::new(ffmpeg_binary.trim());
+
+    ffmpeg
+        .args(["-hide_banner", "-nostats", "-loglevel", "error", "-y", "-i"])
+        .arg(input)
+        .args(["-f", "ffmetadata", "-i"])
+        .arg(metadata)
+        .args([
+            "-map",
+            "0",
+            "-map_metadata",
+            "0",
+            "-map_chapters",
+            "1",
+            "-c",
+            "copy",
+            "-f",
+            format.as_str(),
+        ]);
+
+    if format == OutputFormat::Mp4 {
+        ffmpeg.args(["-movflags", "+faststart"]);
+    }
+
+    let status = ffmpeg
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit())
+        .status()
+        .await?;
+
+    Ok(status.success())
+}
@@ -133,87 +133,189 @@
 }
 */
 
-use std::{path::Path, process::Command};
+use std::{collections::BTreeMap, path::Path, process::Command, time::Duration};
 
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+
+/// ffprobe는 숫자를 문자열로 주기도 함 (`"duration": "609.964000"`)
+///
+/// `"N/A"` 처럼 숫자가 아니면 없는 것으로 봄
+fn number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr + serde::de::DeserializeOwned,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber<T> {
+        Number(T),
+        String(String),
+    }
+
+    Ok(
+        match Option::<StringOrNumber<T>>::deserialize(deserializer)? {
+            Some(StringOrNumber::Number(x)) => Some(x),
+            Some(StringOrNumber::String(x)) => x.parse().ok(),
+            None => None,
+        },
+    )
+}
+
+/// 음수, NaN, `Duration` 으로 나타낼 수 없는 큰 값은 없는 것으로 봄
+fn secs(x: Option<f64>) -> Option<Duration> {
+    x.and_then(|x| Duration::try_from_secs_f64(x).ok())
+}
 
 #[derive(Debug, Deserialize)]
-#[serde(tag = "codec_type")]
-pub enum FfprobeStream {
-    #[serde(rename = "audio")]
+#[serde(tag = "codec_type", rename_all = "lowercase")]
+pub enum FfprobeStreamKind {
     Audio(FfprobeAudioStream),
-    #[serde(rename = "video")]
     Video(FfprobeVideoStream),
+    Subtitle,
+    /// HLS의 timed ID3 같은 메타데이터
+    Data,
+    Attachment,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FfprobeAudioStream {
+    #[serde(default, deserialize_with = "number")]
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FfprobeVideoStream {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pix_fmt: Option<String>,
+    /// `60/1`
+    pub avg_frame_rate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FfprobeStream {
+    pub index: u32,
+    pub codec_name: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub kind: FfprobeStreamKind,
+    /// seconds, 음수일 수 있음
+    #[serde(default, deserialize_with = "number")]
+    pub start_time: Option<f64>,
+    /// seconds, matroska는 스트림 길이가 없고 `tags.DURATION` 에 있음
+    #[serde(default, deserialize_with = "number")]
+    pub duration: Option<f64>,
+    /// bits/s
+    #[serde(default, deserialize_with = "number")]
+    pub bit_rate: Option<u64>,
+    /// `default`, `forced` ... 1이면 해당함
+    #[serde(default)]
+    pub disposition: BTreeMap<String, u8>,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
 }
 
 impl FfprobeStream {
     pub fn audio(&self) -> Option<&FfprobeAudioStream> {
-        match self {
-            FfprobeStream::Audio(x) => Some(x),
+        match &self.kind {
+            FfprobeStreamKind::Audio(x) => Some(x),
             _ => None,
         }
     }
 
     pub fn video(&self) -> Option<&FfprobeVideoStream> {
-        match self {
-            FfprobeStream::Video(x) => Some(x),
+        match &self.kind {
+            FfprobeStreamKind::Video(x) => Some(x),
             _ => None,
         }
     }
+
+    pub fn duration(&self) -> Option<Duration> {
+        secs(self.duration)
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.disposition.get("default").is_some_and(|x| *x == 1)
+    }
 }
 
 #[derive(Debug, Deserialize)]
-pub struct FfprobeAudioStream {
-    pub codec_name: String,
-    pub profile: String,
-    pub sample_rate: String,
-    pub channel_layout: String,
+pub struct FfprobeFormat {
+    pub filename: String,
+    pub nb_streams: u32,
+    /// `matroska,webm`
+    pub format_name: String,
+    pub format_long_name: Option<String>,
+    /// seconds, 음수일 수 있음
+    #[serde(default, deserialize_with = "number")]
+    pub start_time: Option<f64>,
+    /// seconds
+    #[serde(default, deserialize_with = "number")]
+    pub duration: Option<f64>,
+    /// bytes
+    #[serde(default, deserialize_with = "number")]
+    pub size: Option<u64>,
+    /// bits/s
+    #[serde(default, deserialize_with = "number")]
+    pub bit_rate: Option<u64>,
+    pub probe_score: Option<u32>,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct FfprobeVideoStream {
-    pub codec_name: String,
-    pub profile: String,
-    pub width: u16,
-    pub height: u16,
-    pub pix_fmt: String,
-    pub avg_frame_rate: String,
+impl FfprobeFormat {
+    pub fn duration(&self) -> Option<Duration> {
+        secs(self.duration)
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Ffprobe {
     pub streams: Vec<FfprobeStream>,
+    pub format: Option<FfprobeFormat>,
+}
+
+impl Ffprobe {
+    /// 파일 전체 길이, 없으면 가장 긴 스트림의 길이
+    pub fn duration(&self) -> Option<Duration> {
+        self.format
+            .as_ref()
+            .and_then(FfprobeFormat::duration)
+            .or_else(|| {
+                self.streams
+                    .iter()
+                    .filter_map(FfprobeStream::duration)
+                    .max()
+            })
+    }
 }
 
 pub fn ffprobe(path: impl AsRef<Path>) -> crate::Result<Ffprobe> {
     let res = Command::new("ffprobe")
         .arg(path.as_ref())
-        .args(["-v", "quiet", "-output_format", "json", "-show_streams"])
+        .args([
+            "-v",
+            "error",
+            "-output_format",
+            "json",
+            "-show_streams",
+            "-show_format",
+        ])
         .output()?;
 
+    if !res.status.success() {
+        return Err(crate::Error::Ffprobe {
+            status: res.status,
+            stderr: String::from_utf8_lossy(&res.stderr).trim().to_owned(),
+        });
+    }
+
     let json = serde_json::from_slice(&res.stdout).map_err(crate::Error::DeserializeJson)?;
 
     Ok(json)
 }
-
-// pub fn get_duration(p: &Path) -> io::Result<Option<Duration>> {
-//     let o = Command::new("ffprobe")
-//         // .arg("-i")
-//         .arg(p)
-//         .args([
-//             "-show_entries",
-//             "format=duration",
-//             "-of",
-//             "csv=\"p=0\"",
-//             "-v",
-//             "quiet",
-//         ])
-//         .output()?;
-
-//     let duration = String::from_utf8(o.stdout)
-//         .ok()
-//         .and_then(|x| x.parse::<f64>().ok())
-//         .map(Duration::from_secs_f64);
-
-//     Ok(duration)
-// }
@@ -0,0 +1,275 @@
+pub mod playlist;
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use chzzk::request::Auth;
+use reqwest::{StatusCode, Url};
+use tokio::time::sleep;
+
+use crate::source;
+
+use self::playlist::{MasterPlaylist, MediaPlaylist, ParseError, Playlist, Variant};
+
+/// 처음 시작할 때 라이브 끝에서 몇 개의 세그먼트부터 받을지
+const LIVE_EDGE: usize = 3;
+const MAX_RETRIES: u32 = 5;
+/// 이 횟수만큼 target duration 동안 새 세그먼트가 없으면 방송이 끝난 것으로 봄
+const MAX_STALLED_RELOADS: u32 = 10;
+/// playlist를 다시 받는 간격은 1초 ~ 30초
+const MIN_TARGET_DURATION: f64 = 1.0;
+const MAX_TARGET_DURATION: f64 = 30.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("status: {0}")]
+    Status(StatusCode),
+    #[error("url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("parse_playlist: {0}")]
+    ParsePlaylist(#[from] ParseError),
+    #[error("no variant matches {0:?}")]
+    NoVariant(String),
+    #[error("playlist has not been updated for {0:?}")]
+    Stalled(Duration),
+    #[error("write: {0}")]
+    Write(#[from] std::io::Error),
+}
+
+impl Error {
+    /// 연결 오류와 5xx만 다시 시도함, 403이나 404는 다시 요청해도 같음
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Request(_) => true,
+            Error::Status(status) => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+/// 다운로드한 세그먼트를 받음
+#[allow(async_fn_in_trait)]
+pub trait SegmentSink {
+    /// `init`: 이 세그먼트의 fMP4 init 세그먼트 (EXT-X-MAP)
+    async fn write_segment(&mut self, init: Option<&Bytes>, segment: &Bytes) -> io::Result<()>;
+}
+
+pub struct HlsDownloader {
+    pub http: reqwest::Client,
+    pub auth: Option<Auth>,
+    /// master playlist 또는 media playlist
+    pub url: String,
+    /// `best`, `worst`, `1080p`, `720p60` ...
+    ///
+    /// 앞에서부터 처음으로 맞는 variant를 받음
+    pub qualities: Vec<String>,
+}
+
+impl HlsDownloader {
+    /// 방송이 끝날 때까지 `media_url` 의 세그먼트를 받아서 `sink` 에 넘김
+    ///
+    /// `media_url`: [`HlsDownloader::resolve`] 에서 받은 media playlist
+    pub async fn download<S>(&self, media_url: &Url, sink: &mut S) -> Result<(), Error>
+    where
+        S: SegmentSink,
+    {
+        let media_url = media_url.clone();
+
+        let mut last_sequence = None::<u64>;
+        let mut last_map = None::<(Url, Bytes)>;
+        let mut last_updated_at = Instant::now();
+        let mut errors = 0;
+
+        loop {
+            let playlist = match self.fetch_media_playlist(&media_url).await {
+                Ok(playlist) => {
+                    errors = 0;
+                    playlist
+                }
+                Err(err) => {
+                    errors += 1;
+                    if !err.is_retryable() || errors >= MAX_RETRIES {
+                        return Err(err);
+                    }
+                    eprintln!("hls: {err} (retry {errors}/{MAX_RETRIES})");
+                    sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            if last_sequence.is_none() && !playlist.end_list {
+                let skip = playlist.segments.len().saturating_sub(LIVE_EDGE);
+                last_sequence = playlist
+                    .segments
+                    .get(skip)
+                    .and_then(|segment| segment.sequence.checked_sub(1));
+            }
+
+            let mut updated = false;
+
+            for segment in &playlist.segments {
+                if last_sequence.is_some_and(|last| segment.sequence <= last) {
+                    continue;
+                }
+
+                if let Some(last) = last_sequence {
+                    if segment.sequence > last + 1 {
+                        eprintln!(
+                            "hls: missed {} segments ({}..{})",
+                            segment.sequence - last - 1,
+                            last + 1,
+                            segment.sequence
+                        );
+                    }
+                }
+
+                if segment.discontinuity {
+                    println!("hls: discontinuity at segment {}", segment.sequence);
+                }
+
+                let init = match segment.map.as_deref() {
+                    Some(map) => {
+                        let map = media_url.join(map)?;
+
+                        if last_map.as_ref().map(|(url, _)| url) != Some(&map) {
+                            let init = self.fetch_segment(&map).await?;
+                            last_map = Some((map, init));
+                        }
+
+                        last_map.as_ref().map(|(_, init)| init)
+                    }
+                    None => None,
+                };
+
+                match self.fetch_segment(&media_url.join(&segment.uri)?).await {
+                    Ok(bytes) => sink.write_segment(init, &bytes).await?,
+                    Err(err) => eprintln!("hls: skipped segment {}: {err}", segment.sequence),
+                }
+
+                last_sequence = Some(segment.sequence);
+                updated = true;
+            }
+
+            if playlist.end_list {
+                return Ok(());
+            }
+
+            let target_duration = Duration::from_secs_f64(
+                playlist
+                    .target_duration
+                    .clamp(MIN_TARGET_DURATION, MAX_TARGET_DURATION),
+            );
+
+            if updated {
+                last_updated_at = Instant::now();
+            } else if last_updated_at.elapsed() > target_duration * MAX_STALLED_RELOADS {
+                return Err(Error::Stalled(last_updated_at.elapsed()));
+            }
+
+            // https://datatracker.ietf.org/doc/html/rfc8216#section-6.3.4
+            sleep(if updated {
+                target_duration
+            } else {
+                target_duration / 2
+            })
+            .await;
+        }
+    }
+
+    /// returns (media playlist, 고른 variant)
+    ///
+    /// `url` 이 media playlist면 variant는 없음
+    pub async fn resolve(&self) -> Result<(Url, Option<Variant>), Error> {
+        let url = Url::parse(&self.url)?;
+
+        match self.fetch_playlist(&url).await? {
+            Playlist::Master(master) => {
+                let variant = self
+                    .qualities
+                    .iter()
+                    .find_map(|quality| select_variant(&master, quality))
+                    .ok_or_else(|| Error::NoVariant(self.qualities.join(", ")))?
+                    .clone();
+
+                println!(
+                    "hls: selected variant {} ({} bps)",
+                    variant.name().unwrap_or_else(|| variant.uri.clone()),
+                    variant.bandwidth
+                );
+
+                Ok((url.join(&variant.uri)?, Some(variant)))
+            }
+            Playlist::Media(_) => Ok((url, None)),
+        }
+    }
+
+    async fn fetch_media_playlist(&self, url: &Url) -> Result<MediaPlaylist, Error> {
+        match self.fetch_playlist(url).await? {
+            Playlist::Media(playlist) => Ok(playlist),
+            Playlist::Master(_) => {
+                Err(ParseError::Unsupported("nested master playlist".to_owned()).into())
+            }
+        }
+    }
+
+    async fn fetch_playlist(&self, url: &Url) -> Result<Playlist, Error> {
+        let text = self.get(url).await?.text().await?;
+
+        Ok(playlist::parse(&text)?)
+    }
+
+    async fn fetch_segment(&self, url: &Url) -> Result<Bytes, Error> {
+        let mut retries = 0;
+
+        loop {
+            let res = match self.get(url).await {
+                Ok(resp) => resp.bytes().await.map_err(Error::from),
+                Err(err) => Err(err),
+            };
+
+            match res {
+                Ok(bytes) => return Ok(bytes),
+                Err(err) if !err.is_retryable() || retries + 1 >= MAX_RETRIES => return Err(err),
+                Err(_) => {}
+            }
+
+            retries += 1;
+            sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    async fn get(&self, url: &Url) -> Result<reqwest::Response, Error> {
+        let req = source::chzzk::with_auth(self.http.get(url.clone()), self.auth.as_ref());
+
+        let resp = req.send().await?;
+
+        if resp.status().is_success() {
+            Ok(resp)
+        } else {
+            Err(Error::Status(resp.status()))
+        }
+    }
+}
+
+/// `best` / `worst` 또는 variant 이름 (`1080p`, `720p60`, `720p` ...)
+pub fn select_variant<'a>(master: &'a MasterPlaylist, quality: &str) -> Option<&'a Variant> {
+    let variants = master.variants.iter();
+
+    match quality {
+        "best" => variants.max_by_key(|variant| variant.bandwidth),
+        "worst" => variants.min_by_key(|variant| variant.bandwidth),
+        quality => variants
+            .filter(|variant| {
+                variant.name().as_deref() == Some(quality)
+                    || variant
+                        .resolution
+                        .is_some_and(|(_, height)| format!("{height}p") == quality)
+            })
+            .max_by_key(|variant| variant.bandwidth),
+    }
+}
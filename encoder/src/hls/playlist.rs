@@ -0,0 +1,237 @@
+//! https://datatracker.ietf.org/doc/html/rfc8216
+
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseError {
+    #[error("missing #EXTM3U header")]
+    MissingHeader,
+    #[error("invalid tag: {0}")]
+    InvalidTag(String),
+    #[error("missing uri after {0}")]
+    MissingUri(&'static str),
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Playlist {
+    Master(MasterPlaylist),
+    Media(MediaPlaylist),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasterPlaylist {
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub uri: String,
+    pub bandwidth: u64,
+    /// (width, height)
+    pub resolution: Option<(u32, u32)>,
+    pub frame_rate: Option<f64>,
+    pub codecs: Option<String>,
+}
+
+impl Variant {
+    /// `1080p`, `720p60` 같은 streamlink 스타일의 이름
+    pub fn name(&self) -> Option<String> {
+        let (_, height) = self.resolution?;
+
+        match self.frame_rate.map(|x| x.round() as u32) {
+            Some(fps) if fps > 30 => Some(format!("{height}p{fps}")),
+            _ => Some(format!("{height}p")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaPlaylist {
+    /// seconds
+    pub target_duration: f64,
+    pub media_sequence: u64,
+    pub discontinuity_sequence: u64,
+    pub end_list: bool,
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub sequence: u64,
+    /// seconds
+    pub duration: f64,
+    pub uri: String,
+    /// 이 세그먼트 앞에 `#EXT-X-DISCONTINUITY` 가 있었는지
+    pub discontinuity: bool,
+    /// fMP4 초기화 세그먼트 (`#EXT-X-MAP`)
+    pub map: Option<String>,
+}
+
+impl FromStr for Playlist {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+pub fn parse(s: &str) -> Result<Playlist, ParseError> {
+    let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    if lines.next() != Some("#EXTM3U") {
+        return Err(ParseError::MissingHeader);
+    }
+
+    if s.contains("#EXT-X-STREAM-INF") {
+        parse_master(lines).map(Playlist::Master)
+    } else {
+        parse_media(lines).map(Playlist::Media)
+    }
+}
+
+fn parse_master<'a>(
+    mut lines: impl Iterator<Item = &'a str>,
+) -> Result<MasterPlaylist, ParseError> {
+    let mut variants = Vec::new();
+
+    while let Some(line) = lines.next() {
+        let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") else {
+            continue;
+        };
+
+        let mut variant = Variant {
+            uri: String::new(),
+            bandwidth: 0,
+            resolution: None,
+            frame_rate: None,
+            codecs: None,
+        };
+
+        for (key, value) in parse_attributes(attributes) {
+            match key {
+                "BANDWIDTH" => variant.bandwidth = parse_number(line, value)?,
+                "RESOLUTION" => {
+                    let (width, height) = value
+                        .split_once('x')
+                        .ok_or_else(|| ParseError::InvalidTag(line.to_owned()))?;
+
+                    variant.resolution =
+                        Some((parse_number(line, width)?, parse_number(line, height)?));
+                }
+                "FRAME-RATE" => variant.frame_rate = Some(parse_number(line, value)?),
+                "CODECS" => variant.codecs = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+
+        variant.uri = lines
+            .by_ref()
+            .find(|line| !line.starts_with('#'))
+            .ok_or(ParseError::MissingUri("#EXT-X-STREAM-INF"))?
+            .to_owned();
+
+        variants.push(variant);
+    }
+
+    Ok(MasterPlaylist { variants })
+}
+
+fn parse_media<'a>(lines: impl Iterator<Item = &'a str>) -> Result<MediaPlaylist, ParseError> {
+    let mut playlist = MediaPlaylist {
+        target_duration: 0.0,
+        media_sequence: 0,
+        discontinuity_sequence: 0,
+        end_list: false,
+        segments: Vec::new(),
+    };
+
+    let mut duration = None::<f64>;
+    let mut discontinuity = false;
+    let mut map = None::<String>;
+
+    for line in lines {
+        if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            playlist.target_duration = parse_duration(line, value)?;
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            playlist.media_sequence = parse_number(line, value)?;
+        } else if let Some(value) = line.strip_prefix("#EXT-X-DISCONTINUITY-SEQUENCE:") {
+            playlist.discontinuity_sequence = parse_number(line, value)?;
+        } else if line == "#EXT-X-DISCONTINUITY" {
+            discontinuity = true;
+        } else if line == "#EXT-X-ENDLIST" {
+            playlist.end_list = true;
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+            map = parse_attributes(attributes)
+                .find(|(key, _)| *key == "URI")
+                .map(|(_, uri)| uri.to_owned());
+        } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+            match parse_attributes(attributes).find(|(key, _)| *key == "METHOD") {
+                Some((_, "NONE")) | None => {}
+                Some((_, method)) => {
+                    return Err(ParseError::Unsupported(format!(
+                        "EXT-X-KEY METHOD={method}"
+                    )))
+                }
+            }
+        } else if let Some(value) = line.strip_prefix("#EXTINF:") {
+            let value = value.split(',').next().unwrap_or_default();
+            duration = Some(parse_duration(line, value)?);
+        } else if line.starts_with('#') {
+            // 그 외 태그는 무시함 (#EXT-X-PART, #EXT-X-PROGRAM-DATE-TIME ...)
+        } else {
+            let duration = duration.take().ok_or(ParseError::MissingUri("#EXTINF"))?;
+            let sequence = playlist.media_sequence + playlist.segments.len() as u64;
+
+            playlist.segments.push(Segment {
+                sequence,
+                duration,
+                uri: line.to_owned(),
+                discontinuity: std::mem::take(&mut discontinuity),
+                map: map.clone(),
+            });
+        }
+    }
+
+    Ok(playlist)
+}
+
+/// `KEY=VALUE,KEY="VALUE,VALUE"`
+fn parse_attributes(s: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = s;
+
+    std::iter::from_fn(move || {
+        let (key, after) = rest.split_once('=')?;
+
+        let (value, after) = if let Some(quoted) = after.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let after = quoted.get(end + 1..).unwrap_or_default();
+            (&quoted[..end], after)
+        } else {
+            after.split_once(',').unwrap_or((after, ""))
+        };
+
+        rest = after.trim_start_matches(',');
+
+        Some((key.trim(), value))
+    })
+}
+
+fn parse_number<T: FromStr>(line: &str, value: &str) -> Result<T, ParseError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ParseError::InvalidTag(line.to_owned()))
+}
+
+/// `inf`, `NaN`, 음수는 거름
+fn parse_duration(line: &str, value: &str) -> Result<f64, ParseError> {
+    let duration = parse_number::<f64>(line, value)?;
+
+    if duration.is_finite() && duration >= 0.0 {
+        Ok(duration)
+    } else {
+        Err(ParseError::InvalidTag(line.to_owned()))
+    }
+}
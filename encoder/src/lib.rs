@@ -1,7 +1,16 @@
+pub mod chapter;
+pub mod chat;
 pub mod config;
+pub mod control;
 mod error;
 pub mod ffmpeg;
 pub mod ffprobe;
+pub mod hls;
+pub mod recorder;
+pub mod report;
+pub mod schedule;
+pub mod segment;
+pub mod source;
 pub mod time;
 
 pub use error::Error;
@@ -1,428 +1,351 @@
 use std::{
     io,
     path::{Path, PathBuf},
-    process::{Child, Command, Stdio},
-    time::{Duration, Instant},
+    process::{Command, ExitCode, Stdio},
+    sync::Arc,
+    time::Duration,
 };
 
-use chrono::{DateTime, FixedOffset, Utc};
-use chzzk::{
-    live::{get_live_detail::GetLiveDetail, get_live_status::GetLiveStatus},
-    model::{Live, LiveDetail, LivePlaybackMedia, LiveStatus, LiveStatusType},
-    request::Auth,
-};
+use chrono::{DateTime, Utc};
+use chzzk::request::Auth;
+use clap::{Args, Parser, Subcommand};
 use encoder::{
-    config::{Channel, Config, Timezone},
-    ffmpeg::{AudioCodec, Ffmpeg, VideoCodec},
+    chapter,
+    chapter::{
+        export::ChapterFormat,
+        journal::{self, Journal, JournalEntry, Replayed},
+        Chapter, ChapterLanguage,
+    },
+    chat::{chzzk::ChzzkChat, ChatRecorder},
+    config::{Channel, ChannelConfig, Chat, Config, ConfigErrors, Resume, Shutdown, Timezone},
+    control::{ChannelInfo, ControlClient, Register},
+    ffmpeg::{concat, escape_metadata, mux_chapters, remux, Ffmpeg, OutputFormat},
+    ffprobe,
+    hls::HlsDownloader,
+    recorder::{Process, ProcessRunner, Recorder, Session, Spawn, Status},
+    report::{self, FileReport, Problem, SessionReport},
+    schedule::Poll,
+    segment::{self, Manifest, ManifestEntry, SegmentedOutput},
+    source::{chzzk::Chzzk, LiveSource},
     time::Time,
 };
 use tap::Tap;
-use tokio::{fs, signal, time::sleep};
+use tokio::{
+    fs, process, signal,
+    sync::{oneshot, watch, RwLock},
+    task::{JoinHandle, JoinSet},
+    time::sleep,
+};
 
 pub struct AddMetadata {
     /// <channel>/<date>
     directory: PathBuf,
+    /// 챕터를 적용할 파일 (`index.mkv`, `part-1.mkv` ...)
+    file_name: String,
+    format: OutputFormat,
+    ffmpeg_binary: String,
     chapters: Vec<Chapter>,
-}
-
-pub struct Chapters(pub String);
-
-impl Chapters {
-    pub fn new() -> Self {
-        let mut s = String::new();
-
-        s.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
-        s.push_str(r#"<!DOCTYPE Chapters SYSTEM "matroskachapters.dtd">"#);
-        s.push_str("<Chapters><EditionEntry>");
-
-        Self(s)
-    }
-
-    pub fn build(mut self) -> String {
-        self.0.push_str("</EditionEntry></Chapters>");
-
-        self.0
-    }
-
-    pub fn add_chapter(&mut self, title: &str, start: Time) {
-        /* <?xml version="1.0" encoding="ISO-8859-1"?>
-        <!DOCTYPE Chapters SYSTEM "matroskachapters.dtd">
-        <Chapters>
-          <EditionEntry>
-            <ChapterAtom>
-              <ChapterTimeStart>00:00:30.000</ChapterTimeStart>
-              <ChapterTimeEnd>00:01:20.000</ChapterTimeEnd>
-              <ChapterDisplay>
-                <ChapterString>A short chapter</ChapterString>
-                <ChapterLanguage>eng</ChapterLanguage>
-              </ChapterDisplay>
-            </ChapterAtom>
-            <ChapterAtom>
-                <ChapterTimeStart>00:00:46.000</ChapterTimeStart>
-                <ChapterTimeEnd>00:01:10.000</ChapterTimeEnd>
-                <ChapterDisplay>
-                  <ChapterString>A part of that short chapter</ChapterString>
-                  <ChapterLanguage>eng</ChapterLanguage>
-                </ChapterDisplay>
-              </ChapterAtom>
-          </EditionEntry>
-        </Chapters> */
-
-        self.0.push_str("<ChapterAtom>");
-
-        self.0.push_str("<ChapterTimeStart>");
-        self.0.push_str(&start.to_readable(":"));
-        self.0.push_str(".000");
-        self.0.push_str("</ChapterTimeStart>");
-
-        // self.0.push_str("<ChapterTimeEnd>");
-        // self.0.push_str(&end.to_readable(":"));
-        // self.0.push_str(".000");
-        // self.0.push_str("</ChapterTimeEnd>");
-
-        self.0.push_str("<ChapterDisplay>");
-        self.0.push_str("<ChapterString>");
-        self.0.push_str(title);
-        self.0.push_str("</ChapterString>");
-        self.0.push_str("<ChapterLanguage>");
-        self.0.push_str("ko");
-        self.0.push_str("</ChapterLanguage>");
-        self.0.push_str("</ChapterDisplay>");
-
-        self.0.push_str("</ChapterAtom>");
-    }
+    /// 녹화된 길이
+    end: Time,
+    language: ChapterLanguage,
+    /// metadata.xml 외에 추가로 저장할 챕터 파일
+    exports: Vec<ChapterFormat>,
 }
 
 impl AddMetadata {
     /// returns stdout if error
     pub async fn execute(self) -> io::Result<Option<String>> {
-        // 기존 방식 마이그레이션
-        // 1. read dir
-        // 2. 00-00-00.json 형태의 파일만 필터링
-        // 3. AddMetadata {}.execute()
-
         // https://mkvtoolnix.download/doc/mkvmerge.html#mkvmerge.chapters
 
         let Self {
             directory,
-            mut chapters,
+            file_name,
+            format,
+            ffmpeg_binary,
+            chapters,
+            end,
+            language,
+            exports,
         } = self;
 
-        chapters.sort_by_key(|chapter| chapter.0.as_secs());
-
-        let mut builder = Chapters::new();
-        let mut chapters = chapters.into_iter();
-        let mut prev_chapter = None::<Chapter>;
+        // index.mkv 가 아닌 파일의 챕터 파일은 `part-1.metadata.xml` 처럼 저장함
+        let sidecar = |name: &str| match Path::new(&file_name).file_stem() {
+            Some(stem) if stem != "index" => {
+                directory.join(format!("{}.{name}", stem.to_string_lossy()))
+            }
+            _ => directory.join(name),
+        };
 
-        while let Some(chapter) = chapters.next() {
-            let start = prev_chapter
-                .as_ref()
-                .map(|chapter| chapter.0)
-                .unwrap_or_default();
+        // 녹화 파일에 넣을 챕터 파일
+        let metadata_format = match format {
+            OutputFormat::Matroska => ChapterFormat::Matroska,
+            OutputFormat::Mpegts | OutputFormat::Mp4 => ChapterFormat::FfMetadata,
+        };
 
-            builder.add_chapter(
-                &format!(
-                    "{} Playing {}",
-                    chapter.1.live_title,
-                    chapter
-                        .1
-                        .live_category
-                        .as_deref()
-                        .unwrap_or("unknown")
-                        .replace("_", " ")
-                ),
-                start,
-            );
-
-            prev_chapter.replace(chapter);
+        for format in exports
+            .into_iter()
+            .filter(|format| *format != metadata_format)
+        {
+            fs::write(
+                sidecar(format.file_name()),
+                format.export(&chapters, end, &language),
+            )
+            .await?;
         }
 
-        let metadata_file = directory.join("metadata.xml");
+        let metadata_file = sidecar(metadata_format.file_name());
 
-        fs::write(&metadata_file, builder.build()).await?;
+        fs::write(
+            &metadata_file,
+            metadata_format.export(&chapters, end, &language),
+        )
+        .await?;
 
-        let mut mkvpropedit = Command::new("mkvpropedit");
+        let file = directory.join(&file_name);
 
-        mkvpropedit
-            .arg(directory.join("index.mkv"))
-            // .args(["--edit", "info", "--set", &format!("title={}", live_title)])
-            .args(["--edit", "track:a1", "--set", "language=ko"])
-            .arg("--chapters")
-            .arg(&metadata_file);
+        let err = match format {
+            OutputFormat::Matroska => {
+                let mut mkvpropedit = process::Command::new("mkvpropedit");
 
-        let res = mkvpropedit.output()?;
+                mkvpropedit
+                    .arg(&file)
+                    // .args(["--edit", "info", "--set", &format!("title={}", live_title)])
+                    // 챕터 언어는 오디오 트랙의 언어와 다를 수 있으므로 트랙은 바꾸지 않음
+                    .arg("--chapters")
+                    .arg(&metadata_file);
 
-        // fs::remove_file(&metadata_file).await.ok();
+                let res = mkvpropedit.output().await?;
 
-        if res.status.success() {
-            Ok(None)
-        } else {
-            let err = String::from_utf8(res.stdout).unwrap_or("unknown error".to_owned());
-            Ok(Some(err))
-        }
-    }
-}
+                // fs::remove_file(&metadata_file).await.ok();
 
-#[derive(Clone, PartialEq, Eq)]
-pub struct Chapter(pub Time, pub LiveStatus);
+                (!res.status.success())
+                    .then(|| String::from_utf8(res.stdout).unwrap_or("unknown error".to_owned()))
+            }
+            OutputFormat::Mp4 => {
+                let muxed = directory.join(format!("chapters.{file_name}"));
+
+                if mux_chapters(&ffmpeg_binary, &file, &metadata_file, &muxed, format).await? {
+                    fs::rename(&muxed, &file).await?;
+                    None
+                } else {
+                    fs::remove_file(&muxed).await.ok();
+                    Some(format!("failed to mux chapters into {file_name}"))
+                }
+            }
+            // mpegts는 챕터를 담을 수 없으므로 챕터 파일만 남김
+            OutputFormat::Mpegts => None,
+        };
 
-impl PartialOrd for Chapter {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.as_secs().partial_cmp(&other.0.as_secs())
+        Ok(err)
     }
 }
 
-impl Ord for Chapter {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.0.as_secs().cmp(&other.0.as_secs())
-    }
+/// 다운로드와 ffmpeg를 관리하는 task
+pub struct Stream {
+    /// 끝나면 녹화 파일이 마무리된 것
+    stream: JoinHandle<()>,
+    /// 보내면 다운로드를 멈추고 녹화 파일을 마무리함
+    stop: oneshot::Sender<()>,
 }
 
-pub struct Encoder {
-    streamlink: Child,
-    ffmpeg: Option<Child>,
-    /// <channel>
-    directory: PathBuf,
-    #[allow(dead_code)]
-    started_at: DateTime<FixedOffset>,
-    time: Instant,
+impl Process for Stream {
+    fn is_finished(&self) -> bool {
+        self.stream.is_finished()
+    }
 
-    chapters: Vec<Chapter>,
+    async fn stop(self) {
+        self.stop.send(()).ok();
+        self.stream.await.ok();
+    }
+
+    async fn wait(self) {
+        if let Err(err) = self.stream.await {
+            eprintln!("{err}");
+        }
+    }
 }
 
 pub struct EncodeStream<'a> {
-    auth: Option<&'a Auth>,
-    stream_url: &'a str,
-    save_directory: &'a Path,
-    timezone: Timezone,
-
-    title: &'a str,
-    artist: &'a str,
-
-    ffmpeg_binary: &'a str,
-
-    post_process: bool,
-    video_codec: VideoCodec,
-    audio_codec: AudioCodec,
+    http: &'a reqwest::Client,
+    auth: &'a Arc<RwLock<Option<Auth>>>,
+    spawn: Spawn<'a>,
+    chat: Chat,
+    /// 멈춘 뒤 ffmpeg가 파일을 마무리하기를 기다리는 시간
+    shutdown_timeout: Duration,
 }
 
 impl<'a> EncodeStream<'a> {
-    pub fn execute(self) -> io::Result<Encoder> {
+    pub async fn execute(self) -> io::Result<Stream> {
         let Self {
+            http,
             auth,
-            stream_url,
-            save_directory,
-            timezone,
-            title,
-            artist,
-            post_process,
-            ffmpeg_binary,
-            video_codec,
-            audio_codec,
+            spawn:
+                Spawn {
+                    channel,
+                    directory,
+                    part,
+                    offset,
+                    output_format,
+                    stream_url,
+                    media_type,
+                    title,
+                    artist,
+                },
+            chat,
+            shutdown_timeout,
         } = self;
 
-        let started_at = Utc::now().with_timezone(&timezone.into());
-
-        let save_directory =
-            save_directory.join(started_at.format("%Y-%m-%d_%H-%M-%S").to_string());
-
-        std::fs::create_dir_all(&save_directory)?;
-
-        let save_file_path = save_directory.join("index.mkv");
-
-        let mut streamlink = {
-            let mut streamlink = Command::new("streamlink");
-
-            streamlink.args([
-                stream_url,
-                "best",
-                "--loglevel",
-                "info",
-                "--progress",
-                "no",
-                "--ffmpeg-ffmpeg",
-                ffmpeg_binary.trim(),
-                "--ffmpeg-copyts",
-                "--ffmpeg-fout",
-                "matroska",
-            ]);
-
-            if let Some(Auth {
-                nid_ses,
-                nid_aut,
-                nid_jkl,
-            }) = auth
-            {
-                streamlink.args([
-                    "--http-cookie",
-                    &format!("NID_AUT={}", nid_aut),
-                    "--http-cookie",
-                    &format!("NID_SES={}", nid_ses),
-                    "--http-cookie",
-                    &format!("NID_JKL={}", nid_jkl),
-                ]);
-            }
-
-            if post_process {
-                streamlink
-                    .arg("--stdout")
-                    .stdin(Stdio::null())
-                    .stderr(Stdio::inherit())
-                    .stdout(Stdio::piped());
-            } else {
-                streamlink
-                    .arg("-o")
-                    .arg(save_file_path.as_os_str())
-                    .stdin(Stdio::null())
-                    .stderr(Stdio::inherit())
-                    .stdout(Stdio::null());
-            }
-
-            streamlink
-                .tap(|cmd| println!("Streamlink{:#?}", cmd.get_args()))
-                .spawn()?
-                .tap(|streamlink| println!("streamlink.pid={}", streamlink.id()))
+        let ffmpeg = &channel.ffmpeg;
+
+        // 후처리를 하지 않더라도 output_format으로 remux 해야 하므로 ffmpeg는 항상 사용함
+        let ffmpeg_binary = ffmpeg.ffmpeg_binary.trim().to_owned();
+        let mut args = [
+            "-hide_banner",
+            "-nostats",
+            "-loglevel",
+            "info",
+            "-copyts",
+            "-i",
+            "pipe:",
+        ]
+        .map(String::from)
+        .to_vec();
+        args.extend(ffmpeg.codec_args());
+        args.extend([
+            "-map_metadata".to_owned(),
+            "0".to_owned(),
+            "-metadata".to_owned(),
+            format!("title=\"{}\"", escape_metadata(title)),
+            "-metadata".to_owned(),
+            format!("artist=\"{}\"", escape_metadata(artist)),
+        ]);
+        args.extend(output_format.muxer_args().iter().map(|x| x.to_string()));
+
+        let spawn = move |path: &Path| {
+            process::Command::new(&ffmpeg_binary)
+                .args(&args)
+                .arg(path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::inherit())
+                .tap(|cmd| println!("Ffmpeg{:#?}", cmd.as_std().get_args()))
+                .spawn()
+                .tap(|ffmpeg| {
+                    if let Ok(ffmpeg) = ffmpeg {
+                        println!("ffmpeg.pid={:?}", ffmpeg.id());
+                    }
+                })
         };
 
-        let ffmpeg = if post_process {
-            fn escape_special_chars(s: &str) -> String {
-                // (‘=’, ‘;’, ‘#’, ‘\’ and a newline) must be escaped with a backslash ‘\’.
-
-                [
-                    ('=', "\\="),
-                    (';', "\\;"),
-                    ('#', "\\#"),
-                    ('\\', "\\\\"),
-                    ('\n', " "),
-                ]
-                .into_iter()
-                .fold(s.to_owned(), |s, (a, b)| s.replace(a, b))
-            }
-
-            Some(
-                Command::new("ffmpeg")
-                    .args([
-                        "-hide_banner",
-                        "-nostats",
-                        "-loglevel",
-                        "info",
-                        "-i",
-                        "pipe:",
-                        "-c:v",
-                        video_codec.as_str(),
-                        "-c:a",
-                        audio_codec.as_str(),
-                        "-map_metadata",
-                        "0",
-                        "-metadata",
-                        &format!("title=\"{}\"", escape_special_chars(&title)),
-                        "-metadata",
-                        &format!("artist=\"{}\"", escape_special_chars(&artist)),
-                    ])
-                    .arg(save_file_path.as_os_str())
-                    .stdin(streamlink.stdout.take().unwrap())
-                    .stdout(Stdio::null())
-                    .stderr(Stdio::inherit())
-                    .tap(|cmd| println!("Ffmpeg{:#?}", cmd.get_args()))
-                    .spawn()?
-                    .tap(|ffmpeg| println!("ffmpeg.pid={}", ffmpeg.id())),
-            )
+        let mut output = SegmentedOutput::new(
+            directory.to_path_buf(),
+            part,
+            output_format,
+            channel.segment,
+            offset,
+            spawn,
+            shutdown_timeout,
+        )?;
+
+        // 녹화 파일과 같은 시간 기준으로 채팅을 저장함
+        let chat = if chat.enabled {
+            let source = ChzzkChat::new(http.clone(), auth.clone(), &channel.channel_id);
+            let chat = ChatRecorder::new(&channel.channel_name, source, directory, offset)?;
+            let (stop, stopped) = oneshot::channel::<()>();
+
+            Some((tokio::spawn(chat.run(stopped)), stop))
         } else {
             None
         };
 
-        Ok(Encoder {
-            streamlink,
-            ffmpeg,
-            directory: save_directory,
-            started_at,
-            time: Instant::now(),
-            chapters: Vec::new(),
-        })
-    }
-}
-
-pub struct GetStream<'a> {
-    auth: Option<&'a Auth>,
-    channel_id: &'a str,
-}
+        let downloader = HlsDownloader {
+            http: http.clone(),
+            auth: auth.read().await.clone(),
+            url: stream_url.to_owned(),
+            qualities: channel.quality.0.clone(),
+        };
 
-impl<'a> GetStream<'a> {
-    pub async fn execute(self) -> encoder::Result<Option<(LiveDetail, LivePlaybackMedia)>> {
-        let Self { auth, channel_id } = self;
+        let directory = directory.to_path_buf();
+        let (stop, stopped) = oneshot::channel::<()>();
+
+        // 다운로드가 끝나거나 멈추면 ffmpeg의 stdin을 닫고 파일을 마무리함
+        let stream = tokio::spawn(async move {
+            let download = async {
+                let (media_url, variant) = downloader.resolve().await?;
+
+                // 어떤 스트림을 녹화했는지 남김
+                let entry = JournalEntry::Stream {
+                    media_type: media_type.as_str().to_owned(),
+                    variant: variant
+                        .as_ref()
+                        .map(|x| x.name().unwrap_or_else(|| x.uri.clone())),
+                    bandwidth: variant.as_ref().map(|x| x.bandwidth),
+                };
 
-        let live_status = GetLiveStatus { channel_id }.send(auth).await?;
+                if let Err(err) = Journal::open(&directory).and_then(|mut x| x.append(&entry)) {
+                    eprintln!("journal: {err}");
+                }
 
-        if let LiveStatusType::Open = live_status.status {
-            let live_detail = GetLiveDetail { channel_id }.send(auth).await?;
+                downloader.download(&media_url, &mut output).await
+            };
 
-            if live_detail.inherit.adult && live_detail.inherit.live_playback.is_none() {
-                eprintln!("YOU'RE NOT AN ADULT");
-                return Ok(None);
+            tokio::select! {
+                res = download => {
+                    if let Err(err) = res {
+                        eprintln!("hls: {err}");
+                    }
+                }
+                _ = stopped => {}
             }
 
-            let Some(live_playback) = live_detail.inherit.live_playback.as_ref() else {
-                return Ok(None);
-            };
+            match output.finish().await {
+                Ok(Some(status)) if !status.success() => {
+                    eprintln!("ffmpeg: exited with {status}");
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("ffmpeg: {err}"),
+            }
 
-            let stream = live_playback
-                .media
-                .iter()
-                .find(|media| media.media_id == "HLS")
-                .cloned();
+            if let Some((chat, stop)) = chat {
+                stop.send(()).ok();
+                chat.await.ok();
+            }
+        });
 
-            Ok(stream.map(|stream| (live_detail, stream)))
-        } else {
-            Ok(None)
-        }
+        Ok(Stream { stream, stop })
     }
 }
 
-pub struct WatchStream<'a> {
-    auth: Option<&'a Auth>,
-    save_directory: &'a Path,
-    timezone: Timezone,
-    channel_id: &'a str,
-    ffmpeg: &'a Ffmpeg,
+/// HLS를 받아서 ffmpeg로 녹화하고, 끝나면 챕터를 적용함
+struct FfmpegRunner {
+    ctx: Arc<Context>,
 }
 
-impl<'a> WatchStream<'a> {
-    pub async fn execute(self) -> encoder::Result<Option<(LiveDetail, Encoder)>> {
-        let Self {
-            auth,
-            save_directory,
-            timezone,
-            channel_id,
-            ffmpeg:
-                Ffmpeg {
-                    post_process,
-                    ffmpeg_binary,
-                    video_codec,
-                    audio_codec,
-                },
-        } = self;
+impl ProcessRunner for FfmpegRunner {
+    type Process = Stream;
 
-        let Some((live_detail, stream)) = GetStream { auth, channel_id }.execute().await? else {
-            return Ok(None);
-        };
-
-        fs::create_dir_all(&save_directory).await?;
-
-        let encoder = EncodeStream {
-            auth,
-            stream_url: &stream.path,
-            save_directory,
-            timezone,
-            title: &live_detail.inherit.live_title,
-            artist: &live_detail.inherit.channel.channel_name,
-            post_process: *post_process,
-            ffmpeg_binary,
-            video_codec: *video_codec,
-            audio_codec: *audio_codec,
+    async fn spawn(&mut self, spawn: Spawn<'_>) -> io::Result<Stream> {
+        EncodeStream {
+            http: &self.ctx.http,
+            auth: &self.ctx.auth,
+            spawn,
+            chat: self.ctx.chat,
+            shutdown_timeout: self.ctx.shutdown.timeout(),
         }
-        .execute()?;
+        .execute()
+        .await
+    }
 
-        Ok(Some((live_detail, encoder)))
+    async fn finalize(&mut self, channel_name: &str, session: &Session) -> bool {
+        apply_chapters(
+            &self.ctx,
+            channel_name,
+            &session.directory,
+            session.output_format,
+            &session.parts,
+            &session.chapters,
+        )
+        .await
     }
 }
 
@@ -436,87 +359,306 @@ fn get_ffmpeg_binary() -> String {
     String::from_utf8(buf).unwrap()
 }
 
-async fn get_chzzk_auth(http: &reqwest::Client, master_url: &str) -> Option<Auth> {
-    if let Ok(resp) = http.get(format!("{}/chzzk-auth", master_url)).send().await {
-        if resp.status().is_success() {
-            return resp.json::<Auth>().await.ok();
-        }
-    }
-    None
+/// 인증 정보를 나눠주는 master 서버
+struct Master {
+    url: String,
+    token: String,
 }
 
-/// return: is modified chapter
-fn push_or_modify_chapter(chapters: &mut Vec<Chapter>, curr: Chapter) -> bool {
-    if curr.1.status == LiveStatusType::Close {
-        return false;
-    }
+async fn get_chzzk_auth(http: &reqwest::Client, master: &Master) -> Option<Auth> {
+    // master의 로그에 어느 pod가 가져갔는지 남김
+    let pod = std::env::var("HOSTNAME").unwrap_or_default();
 
-    let prev = match chapters.last().cloned() {
-        Some(prev) => prev,
-        None => {
-            chapters.push(curr);
-            return true;
-        }
-    };
+    let resp = http
+        .get(format!("{}/chzzk-auth", master.url))
+        .bearer_auth(&master.token)
+        .header("x-encoder", pod)
+        .send()
+        .await
+        .ok()?;
 
-    // 1분내에 이전에 사용했던 타이틀이 있으면 거르기.
-    // 단, 카테고리가 바뀌었다면 이전 챕터의 카테고리를 최신 챕터의 카테고리로 수정하면됨
-    // 1분이라는 기준은 나중에 문제되는 거 같으면 늘릴 수 있음
-    let oldest_chapter_before_60s_with_same_title = chapters
-        .iter_mut()
-        .filter(|prev| curr.0.as_secs() - prev.0.as_secs() <= 60)
-        .filter(|prev| prev.1.live_title == curr.1.live_title)
-        .min_by_key(|chapter| chapter.0.as_secs());
-
-    if let Some(prev) = oldest_chapter_before_60s_with_same_title {
-        if prev.1.live_category != curr.1.live_category {
-            prev.1.live_category = curr.1.live_category;
-            return true;
-        }
-        return false;
+    if resp.status().is_success() {
+        resp.json::<Auth>().await.ok()
+    } else {
+        eprintln!("chzzk-auth: master responded with {}", resp.status());
+        None
     }
+}
 
-    let modified = [
-        prev.1.live_title != curr.1.live_title,
-        prev.1.live_category != curr.1.live_category,
-    ]
-    .into_iter()
-    .any(|ne| ne);
+/// 모든 채널이 공유하는 상태
+struct Context {
+    http: reqwest::Client,
+    auth: Arc<RwLock<Option<Auth>>>,
+    ffmpeg: Ffmpeg,
+    timezone: Timezone,
+    chapter_language: ChapterLanguage,
+    chapter_exports: Vec<ChapterFormat>,
+    resume: Resume,
+    poll: Poll,
+    shutdown: Shutdown,
+    chat: Chat,
+}
+
+impl Context {
+    /// returns (context, channels, master if slave)
+    fn from_config(mut config: Config) -> (Self, Vec<ChannelConfig>, Option<Master>) {
+        config.ffmpeg.ffmpeg_binary = get_ffmpeg_binary();
+
+        let channels = std::mem::take(&mut config.channels)
+            .into_iter()
+            .map(|channel| channel.resolve(&config))
+            .collect();
 
-    if modified {
-        chapters.push(curr);
-        return true;
+        let Config {
+            path: _,
+            auth,
+            channels: _,
+            ffmpeg,
+            quality: _,
+            media_types: _,
+            name_template: _,
+            segment: _,
+            timezone,
+            chapter_language,
+            chapter_exports,
+            resume,
+            poll,
+            shutdown,
+            chat,
+            slave,
+            master_url,
+            master_token,
+        } = config;
+
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap();
+
+        let ctx = Self {
+            http,
+            auth: Arc::new(RwLock::new(auth)),
+            ffmpeg,
+            timezone,
+            chapter_language,
+            chapter_exports,
+            resume,
+            poll,
+            shutdown,
+            chat,
+        };
+
+        let master = slave.then(|| Master {
+            url: master_url.expect("please set master_url"),
+            token: master_token.expect("please set master_token"),
+        });
+
+        (ctx, channels, master)
     }
+}
+
+#[derive(Parser)]
+#[command(version, about = "chzzk 방송 녹화")]
+struct Cli {
+    /// 설정 파일 경로
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "env")]
+    config: Option<PathBuf>,
+    /// 환경 변수에서 설정을 읽음
+    #[arg(long, global = true)]
+    env: bool,
+
+    #[command(subcommand)]
+    command: Option<Subcommands>,
+
+    /// 서브커맨드가 없으면 `record` 로 동작함
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+#[derive(Args)]
+struct RecordArgs {
+    /// `channels` 중 녹화할 채널의 순서 (0부터 시작)
+    #[arg(long, conflicts_with_all = ["name", "all"])]
+    index: Option<usize>,
+    /// `channels` 중 녹화할 채널의 이름
+    #[arg(long, conflicts_with = "all")]
+    name: Option<String>,
+    /// `channels` 에 있는 모든 채널을 동시에 녹화함
+    #[arg(long)]
+    all: bool,
+}
+
+#[derive(Subcommand)]
+enum Subcommands {
+    /// 방송을 녹화함
+    Record(RecordArgs),
+    /// ffprobe로 녹화 파일의 스트림 정보를 출력함
+    Probe { file: PathBuf },
+    /// 챕터 관리
+    #[command(subcommand)]
+    Chapters(ChaptersCommand),
+    /// 설정 관리
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// 설정된 채널 목록을 출력함
+    ListChannels,
+}
 
-    false
+#[derive(Subcommand)]
+enum ChaptersCommand {
+    /// 녹화 폴더의 저널을 바탕으로 챕터를 다시 적용함
+    ///
+    /// 파일을 검사하거나 합치지 않고 챕터와 메타데이터만 다시 씀
+    Apply {
+        /// <channel>/<date>
+        directory: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// 설정을 검증하고 발견한 문제를 모두 출력함
+    Check,
+}
+
+/// `--config`, `--env` 둘 다 없으면 `fallback` 을 사용함
+fn load_config(
+    cli: &Cli,
+    fallback: impl FnOnce() -> Result<Config, ConfigErrors>,
+) -> Result<Config, ConfigErrors> {
+    match (&cli.config, cli.env) {
+        (Some(path), _) => Config::from_path(path),
+        (None, true) => Config::from_env(),
+        (None, false) => fallback(),
+    }
 }
 
-async fn run() {
+async fn run() -> ExitCode {
     #[cfg(debug_assertions)]
     {
         dotenv::dotenv().ok();
     }
 
-    let index = std::env::args()
-        .find(|arg| arg.starts_with("--index="))
-        .and_then(|x| x["--index=".len()..].trim().parse::<usize>().ok());
+    let cli = Cli::parse();
 
-    let name = std::env::args()
-        .find(|arg| arg.starts_with("--name="))
-        .map(|x| x["--name=".len()..].trim().to_owned());
+    match &cli.command {
+        None => record(&cli, &cli.record).await,
+        Some(Subcommands::Record(args)) => record(&cli, args).await,
+        Some(Subcommands::Probe { file }) => match ffprobe::ffprobe(file) {
+            Ok(ffprobe) => {
+                println!("{ffprobe:#?}");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
+        },
+        Some(Subcommands::Chapters(ChaptersCommand::Apply { directory })) => {
+            let config = match load_config(&cli, Config::new) {
+                Ok(r) => r,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
 
-    let Config {
-        path,
-        mut auth,
-        channels,
-        mut ffmpeg,
-        timezone,
-        slave,
-        master_url,
-    } = if index.is_some() || name.is_some() {
-        Config::from_file().unwrap()
-    } else {
-        Config::from_env().unwrap()
+            let (ctx, _, _) = Context::from_config(config);
+
+            let replayed = match journal::replay(directory) {
+                Ok(Some(r)) => r,
+                Ok(None) => {
+                    eprintln!("{:?} has no {}", directory, journal::FILE_NAME);
+                    return ExitCode::FAILURE;
+                }
+                Err(err) => {
+                    eprintln!("journal: {err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            let Some(parts) = replayed_parts(directory, &replayed) else {
+                eprintln!("{:?} has no started_at", directory);
+                return ExitCode::FAILURE;
+            };
+
+            let channel_name = directory
+                .parent()
+                .and_then(|x| x.file_name())
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            if rewrite_chapters(
+                &ctx,
+                &channel_name,
+                directory,
+                replayed.output_format,
+                &parts,
+                &replayed.chapters,
+            )
+            .await
+            {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Some(Subcommands::Config(ConfigCommand::Check)) => match load_config(&cli, Config::new) {
+            Ok(config) => {
+                println!("save_directory = {:?}", config.path);
+                println!("channels = {}", config.channels.len());
+                println!("slave = {}", config.slave);
+                println!("ok");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
+        },
+        Some(Subcommands::ListChannels) => {
+            let config = match load_config(&cli, Config::new) {
+                Ok(r) => r,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ExitCode::FAILURE;
+                }
+            };
+
+            for (
+                index,
+                Channel {
+                    channel_id,
+                    channel_name,
+                    ..
+                },
+            ) in config.channels.iter().enumerate()
+            {
+                println!("{index}\t{channel_name}\t{channel_id}");
+            }
+
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+async fn record(cli: &Cli, args: &RecordArgs) -> ExitCode {
+    println!("Hello, world!");
+
+    let RecordArgs { index, name, all } = args;
+
+    let config = match load_config(cli, || {
+        if index.is_some() || name.is_some() || *all {
+            Config::from_file()
+        } else {
+            Config::new()
+        }
+    }) {
+        Ok(r) => r,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
     }
     .tap(|config| {
         println!("save_directory = {:?}", config.path);
@@ -529,240 +671,616 @@ async fn run() {
             "post_process.audio_codec = {:#?}",
             config.ffmpeg.audio_codec
         );
+        println!("post_process.video = {:?}", config.ffmpeg.video);
+        println!("post_process.audio = {:?}", config.ffmpeg.audio);
     });
 
-    let http = reqwest::Client::builder()
-        .timeout(Duration::from_millis(30))
-        .build()
-        .unwrap();
+    let (ctx, channels, master) = Context::from_config(config);
 
-    if slave {
-        auth = get_chzzk_auth(&http, master_url.as_deref().unwrap()).await;
+    if let Some(master) = &master {
+        *ctx.auth.write().await = get_chzzk_auth(&ctx.http, master).await;
     }
 
-    let Channel {
-        channel_id,
-        channel_name,
-    } = if let Some(index) = index {
-        channels.into_iter().nth(index).expect("hasn't channel")
-    } else if let Some(name) = name {
+    let len = channels.len();
+
+    let channels = if *all {
         channels
-            .into_iter()
-            .find(|x| x.channel_name == name)
-            .expect("hasn't channel")
+    } else if let Some(index) = index {
+        match channels.into_iter().nth(*index) {
+            Some(r) => vec![r],
+            None => {
+                eprintln!("--index {index} is out of range (channels = {len})");
+                return ExitCode::FAILURE;
+            }
+        }
+    } else if let Some(name) = name {
+        match channels.into_iter().find(|x| x.channel_name == *name) {
+            Some(r) => vec![r],
+            None => {
+                eprintln!("--name {name:?} is not in channels");
+                return ExitCode::FAILURE;
+            }
+        }
     } else {
-        channels.into_iter().next().expect("please set channel")
+        // 설정을 검증했으므로 채널이 하나 이상 있음
+        channels.into_iter().take(1).collect()
     };
 
-    let display_channel_name = (GetLiveDetail {
-        channel_id: &channel_id,
-    })
-    .send(&auth)
-    .await
-    .unwrap()
-    .inherit
-    .channel
-    .channel_name;
+    #[cfg(unix)]
+    let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate()).unwrap();
+    #[cfg(target_os = "windows")]
+    let mut ctrl_c = signal::windows::ctrl_c().unwrap();
+
+    let ctx = Arc::new(ctx);
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let mut tasks = JoinSet::new();
+
+    let statuses = channels
+        .iter()
+        .map(|_| watch::channel(Status::default()))
+        .collect::<Vec<_>>();
+
+    if let Some(master) = &master {
+        let register = Register {
+            pod: std::env::var("HOSTNAME").unwrap_or_default(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            channels: channels
+                .iter()
+                .map(|channel| ChannelInfo {
+                    channel_id: channel.channel_id.clone(),
+                    channel_name: channel.channel_name.clone(),
+                })
+                .collect(),
+        };
+
+        let client = ControlClient {
+            http: ctx.http.clone(),
+            master_url: master.url.clone(),
+            token: master.token.clone(),
+        };
+
+        tasks.spawn(client.run(
+            register,
+            statuses.iter().map(|(_, rx)| rx.clone()).collect(),
+            Duration::from_secs(10),
+        ));
+    }
+
+    if let Some(master) = master {
+        let ctx = ctx.clone();
+
+        // 모든 채널이 같은 인증 정보를 사용하므로 한 곳에서만 갱신함
+        tasks.spawn(async move {
+            loop {
+                let auth = get_chzzk_auth(&ctx.http, &master).await;
+                *ctx.auth.write().await = auth;
+
+                sleep(Duration::from_secs(5)).await;
+            }
+        });
+    }
+
+    let mut recorders = JoinSet::new();
+
+    for (channel, (status, _)) in channels.into_iter().zip(statuses) {
+        recorders.spawn(record_channel(
+            ctx.clone(),
+            channel,
+            status,
+            stop_rx.clone(),
+        ));
+    }
+
+    stop_signal(
+        #[cfg(unix)]
+        &mut sigterm,
+        #[cfg(target_os = "windows")]
+        &mut ctrl_c,
+    )
+    .await;
+
+    stop_tx.send(true).ok();
+
+    while let Some(res) = recorders.join_next().await {
+        if let Err(err) = res {
+            eprintln!("{err}");
+        }
+    }
+
+    tasks.abort_all();
+
+    ExitCode::SUCCESS
+}
+
+async fn record_channel(
+    ctx: Arc<Context>,
+    channel: ChannelConfig,
+    status: watch::Sender<Status>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let source = Chzzk::new(ctx.auth.clone());
+
+    // 로그에만 사용하므로 가져오지 못해도 녹화는 계속함
+    let display_channel_name = match source.live_detail(&channel.channel_id).await {
+        Ok(live_detail) => live_detail.channel_name,
+        Err(err) => {
+            eprintln!("[{}] can't get channel name: {err}", channel.channel_name);
+            channel.channel_name.clone()
+        }
+    };
+
+    let ChannelConfig {
+        channel_id,
+        channel_name,
+        path,
+        quality,
+        media_types,
+        ffmpeg,
+        ..
+    } = &channel;
 
     println!("channel_id = {:?}", channel_id);
     println!(
         "channel_name = {:?} / {:?}",
         channel_name, display_channel_name
     );
+    println!("[{channel_name}] path = {path:?}, quality = [{quality}], media_types = {media_types:?}, post_process.enable = {}, post_process.video_codec = {:?}, post_process.audio_codec = {:?}", ffmpeg.post_process, ffmpeg.video_codec, ffmpeg.audio_codec);
 
-    ffmpeg.ffmpeg_binary = get_ffmpeg_binary();
+    let channel_name = channel_name.clone();
 
-    #[cfg(unix)]
-    let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate()).unwrap();
-    #[cfg(target_os = "windows")]
-    let mut ctrl_c = signal::windows::ctrl_c().unwrap();
+    let mut recorder = Recorder::new(
+        channel,
+        ctx.timezone,
+        &ctx.resume,
+        ctx.poll,
+        source,
+        FfmpegRunner { ctx: ctx.clone() },
+    )
+    .with_status(status);
 
-    let save_directory = PathBuf::from(&path).join(channel_name);
-    let mut encoder = None::<Encoder>;
-    // let mut prev_live = None::<LiveStatus>;
+    recover_chapters(&ctx, &channel_name, &recorder.save_directory()).await;
 
-    loop {
-        if slave {
-            auth = get_chzzk_auth(&http, master_url.as_deref().unwrap()).await;
-        }
+    recorder.run(&mut stop).await;
+}
 
-        match encoder.as_mut() {
-            Some(Encoder {
-                streamlink,
-                ffmpeg,
-                directory,
-                started_at: _,
-                time,
-                chapters,
-            }) => match streamlink.try_wait() {
-                Ok(Some(_exit_code)) => {
-                    let time = Time::from(time.elapsed());
-
-                    if let Some(ffmpeg) = ffmpeg.as_mut() {
-                        ffmpeg.try_wait().ok();
-                    }
+/// returns (file_name, start, end)
+///
+/// `parts`: 각 part의 길이
+fn recorded_files(
+    channel_name: &str,
+    directory: &Path,
+    format: OutputFormat,
+    parts: &[Duration],
+) -> (Option<Manifest>, Vec<(String, Duration, Duration)>) {
+    let manifest = Manifest::read(directory).unwrap_or_else(|err| {
+        eprintln!("[{channel_name}] manifest: {err}");
+        None
+    });
 
-                    if time.as_secs() >= 15 {
-                        let added_metadata = AddMetadata {
-                            directory: directory.clone(),
-                            chapters: chapters.clone(),
-                        }
-                        .execute()
-                        .await;
-
-                        match added_metadata {
-                            Ok(None) => {
-                                println!("{} - closed live stream", time.to_readable(":"));
-                            }
-                            Ok(Some(err)) => {
-                                eprintln!("{err}");
-                            }
-                            Err(err) => {
-                                eprintln!("{err}");
-                            }
-                        }
-                    } else {
-                        match fs::remove_dir_all(directory).await {
-                            Ok(_) => {
-                                println!(
-                                    "{} - removed this live stream, because duration less than 15 secs",
-                                    time.to_readable(":")
-                                );
-                            }
-                            Err(err) => eprintln!("remove_dir_all: {err}"),
-                        }
-                    }
+    let files = match manifest.as_ref() {
+        Some(manifest) => manifest.files(parts),
+        // manifest가 없는 이전 버전의 녹화
+        None => {
+            let mut files = Vec::new();
+            let mut start = Duration::ZERO;
+
+            for (index, duration) in parts.iter().enumerate() {
+                files.push((
+                    segment::file_name(index, None, format),
+                    start,
+                    start + *duration,
+                ));
+                start += *duration;
+            }
 
-                    encoder = None;
-                    continue; // 예상치 않은 종료가 발생할 수 있으므로 5초 기다리지 않음
-                }
-                Err(err) => {
-                    if let Some(ffmpeg) = ffmpeg.as_mut() {
-                        ffmpeg.try_wait().ok();
-                    }
-                    eprintln!("{err}");
+            files
+        }
+    };
+
+    (manifest, files)
+}
 
-                    encoder = None;
-                    continue; // 예상치 않은 종료가 발생할 수 있으므로 5초 기다리지 않음
+/// 녹화가 끝난 파일을 검사하고 part들을 합친 뒤 챕터를 적용함
+///
+/// `parts`: 각 part의 길이
+///
+/// return: 모든 파일에 챕터를 적용했는지
+async fn apply_chapters(
+    ctx: &Context,
+    channel_name: &str,
+    directory: &Path,
+    format: OutputFormat,
+    parts: &[Duration],
+    chapters: &[Chapter],
+) -> bool {
+    let total = parts.iter().sum::<Duration>();
+
+    let (mut manifest, mut files) = recorded_files(channel_name, directory, format, parts);
+
+    check_files(
+        ctx,
+        channel_name,
+        directory,
+        format,
+        &files,
+        manifest.as_mut(),
+    )
+    .await;
+
+    // 나눠서 녹화한 파일들은 합치지 않음
+    if files.len() > 1 && files.len() == parts.len() && ctx.resume.concat {
+        let names = files
+            .iter()
+            .map(|(file_name, _, _)| file_name.as_str())
+            .collect::<Vec<_>>();
+
+        let concat_file = format!("concat.{}", format.as_ext());
+
+        match concat(&ctx.ffmpeg.ffmpeg_binary, directory, &names, &concat_file).await {
+            Ok(true) => {
+                for file_name in &names {
+                    std::fs::remove_file(directory.join(file_name)).ok();
                 }
-                Ok(None) => {
-                    let curr = GetLiveStatus {
-                        channel_id: &channel_id,
-                    }
-                    .send(&auth)
-                    .await;
-                    let time = Time::from(time.elapsed());
-
-                    match curr.map(|x| Chapter(time, x)) {
-                        Ok(curr) => {
-                            let modified = push_or_modify_chapter(chapters, curr.clone());
-
-                            if modified {
-                                println!(
-                                    "{} - {:?} Playing {}",
-                                    time.to_readable(":"),
-                                    curr.1.live_title,
-                                    curr.1.live_category.as_deref().unwrap_or("unknown")
-                                );
-                            }
-                        }
-                        Err(err) => {
-                            eprintln!("{err}");
+
+                let file_name = segment::file_name(0, None, format);
+
+                match std::fs::rename(directory.join(&concat_file), directory.join(&file_name)) {
+                    Ok(_) => {
+                        let manifest = Manifest {
+                            segments: vec![ManifestEntry {
+                                file_name: file_name.clone(),
+                                part: 0,
+                                start: 0.0,
+                                end: Some(total.as_secs_f64()),
+                                size: std::fs::metadata(directory.join(&file_name))
+                                    .map(|metadata| metadata.len())
+                                    .ok(),
+                                incomplete: false,
+                            }],
+                        };
+
+                        if let Err(err) = manifest.write(directory) {
+                            eprintln!("[{channel_name}] manifest: {err}");
                         }
+
+                        files = vec![(file_name, Duration::ZERO, total)];
                     }
-                }
-            },
-            None => {
-                let (live_detail, new_encoder) = match (WatchStream {
-                    auth: auth.as_ref(),
-                    save_directory: &save_directory,
-                    timezone,
-                    channel_id: &channel_id,
-                    ffmpeg: &ffmpeg,
-                })
-                .execute()
-                .await
-                {
-                    Ok(r) => r.unzip(),
                     Err(err) => {
-                        eprintln!("{err}");
-                        sleep(Duration::from_secs(5)).await;
-                        continue;
+                        eprintln!("[{channel_name}] rename: {err}");
+                        return false;
                     }
-                };
-
-                encoder = new_encoder;
-
-                let time = Time(0, 0, 0);
-
-                if let Some((live_detail, encoder)) = live_detail.zip(encoder.as_mut()) {
-                    let LiveDetail {
-                        inherit:
-                            Live {
-                                live_title,
-                                live_category,
-                                ..
-                            },
-                        ..
-                    } = &live_detail;
-
-                    println!(
-                        "{} - {:?} Playing {}",
-                        time.to_readable(":"),
-                        live_title,
-                        live_category.as_deref().unwrap_or("unknown")
-                    );
-                    encoder.chapters.push(Chapter(time, live_detail.into()));
                 }
             }
+            Ok(false) => eprintln!("[{channel_name}] failed to concat parts"),
+            Err(err) => eprintln!("[{channel_name}] concat: {err}"),
         }
+    }
 
-        tokio::select! {
-            _ = sleep(Duration::from_secs(5)) => {
-                continue;
+    write_chapters(ctx, channel_name, directory, format, files, chapters).await
+}
+
+/// 파일을 검사하거나 합치지 않고 챕터와 메타데이터만 다시 씀
+///
+/// return: 모든 파일에 챕터를 적용했는지
+async fn rewrite_chapters(
+    ctx: &Context,
+    channel_name: &str,
+    directory: &Path,
+    format: OutputFormat,
+    parts: &[Duration],
+    chapters: &[Chapter],
+) -> bool {
+    let (_, files) = recorded_files(channel_name, directory, format, parts);
+
+    write_chapters(ctx, channel_name, directory, format, files, chapters).await
+}
+
+/// 각 파일에 그 파일의 구간에 걸친 챕터를 씀, 모두 성공하면 저널에 남김
+///
+/// `files`: (file_name, start, end)
+async fn write_chapters(
+    ctx: &Context,
+    channel_name: &str,
+    directory: &Path,
+    format: OutputFormat,
+    files: Vec<(String, Duration, Duration)>,
+    chapters: &[Chapter],
+) -> bool {
+    let single = files.len() == 1;
+    let mut applied = true;
+
+    for (file_name, start, end) in files {
+        let chapters = if single {
+            chapters.to_vec()
+        } else {
+            chapter::slice(chapters, Time::from(start), Time::from(end))
+        };
+
+        let added_metadata = AddMetadata {
+            directory: directory.to_path_buf(),
+            file_name,
+            format,
+            ffmpeg_binary: ctx.ffmpeg.ffmpeg_binary.clone(),
+            chapters,
+            end: Time::from(end - start),
+            language: ctx.chapter_language.clone(),
+            exports: ctx.chapter_exports.clone(),
+        }
+        .execute()
+        .await;
+
+        match added_metadata {
+            Ok(None) => {}
+            Ok(Some(err)) => {
+                applied = false;
+                eprintln!("[{channel_name}] {err}");
+            }
+            Err(err) => {
+                applied = false;
+                eprintln!("[{channel_name}] {err}");
             }
-            _ = stop_signal(#[cfg(unix)] &mut sigterm, #[cfg(target_os = "windows")] &mut ctrl_c) => {}
         }
+    }
 
-        if let Some(Encoder {
-            mut streamlink,
-            mut ffmpeg,
-            directory,
-            started_at: _,
-            chapters,
-            time,
-        }) = encoder.take()
+    // 모든 파일에 적용했을 때만 남김, 하나라도 실패하면 다음에 시작할 때 다시 적용함
+    if applied {
+        if let Err(err) =
+            Journal::open(directory).and_then(|mut x| x.append(&JournalEntry::Applied))
         {
-            let time = Time::from(time.elapsed());
+            eprintln!("[{channel_name}] journal: {err}");
+            applied = false;
+        }
+    }
 
-            println!("{} - received stop signal", time.to_readable(":"));
+    applied
+}
 
-            streamlink.kill().expect("failed to kill streamlink");
+/// 녹화 파일을 ffprobe로 검사하고, 손상된 파일은 remux한 파일로 바꾼 뒤 `report.json` 에 남김
+///
+/// `files`: (file_name, start, end)
+async fn check_files(
+    ctx: &Context,
+    channel_name: &str,
+    directory: &Path,
+    format: OutputFormat,
+    files: &[(String, Duration, Duration)],
+    mut manifest: Option<&mut Manifest>,
+) {
+    let mut session_report = SessionReport::default();
+
+    for (file_name, start, end) in files {
+        let expected = end.saturating_sub(*start);
+
+        let mut file_report = match check_file(directory, file_name, format, expected).await {
+            Ok(r) => r,
+            // 검사하지 못한 파일도 남기고 다음 파일을 검사함
+            Err(err) => {
+                eprintln!("[{channel_name}] ffprobe: {file_name}: {err}");
+
+                session_report.files.push(FileReport {
+                    file_name: file_name.clone(),
+                    expected_duration: expected.as_secs_f64(),
+                    duration: None,
+                    problems: vec![Problem::Unchecked {
+                        reason: err.to_string(),
+                    }],
+                    repaired: false,
+                });
+                continue;
+            }
+        };
 
-            streamlink.wait().ok();
+        let entry = manifest.as_mut().and_then(|manifest| {
+            manifest
+                .segments
+                .iter_mut()
+                .find(|entry| entry.file_name == *file_name)
+        });
 
-            if let Some(ffmpeg) = ffmpeg.as_mut() {
-                ffmpeg.try_wait().ok();
+        if entry.as_ref().is_some_and(|entry| entry.incomplete) {
+            file_report.problems.push(Problem::Killed);
+        }
 
-                let added_metadata = AddMetadata {
-                    directory: directory.clone(),
-                    chapters: chapters.clone(),
+        if file_report.needs_repair() {
+            if let Some(repaired) =
+                repair(ctx, channel_name, directory, format, file_name, expected).await
+            {
+                if let Some(entry) = entry {
+                    entry.incomplete = false;
+                    entry.size = std::fs::metadata(directory.join(file_name))
+                        .map(|metadata| metadata.len())
+                        .ok();
                 }
-                .execute()
-                .await;
 
-                match added_metadata {
-                    Ok(None) => {}
-                    Ok(Some(err)) => {
-                        eprintln!("{err}");
-                    }
-                    Err(err) => {
-                        eprintln!("{err}");
-                    }
-                }
+                file_report = repaired;
             }
         }
-        return;
+
+        if !file_report.is_ok() {
+            eprintln!("[{channel_name}] {file_name}: {:?}", file_report.problems);
+        }
+
+        session_report.files.push(file_report);
+    }
+
+    if let Some(manifest) = manifest {
+        if let Err(err) = manifest.write(directory) {
+            eprintln!("[{channel_name}] manifest: {err}");
+        }
+    }
+
+    if let Err(err) = session_report.write(directory) {
+        eprintln!("[{channel_name}] report: {err}");
+    }
+}
+
+/// ffprobe를 기다리는 동안 다른 채널의 다운로드를 막지 않도록 blocking 스레드에서 검사함
+async fn check_file(
+    directory: &Path,
+    file_name: &str,
+    format: OutputFormat,
+    expected: Duration,
+) -> io::Result<FileReport> {
+    let directory = directory.to_path_buf();
+    let file_name = file_name.to_owned();
+
+    tokio::task::spawn_blocking(move || report::check(&directory, &file_name, format, expected))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// 재인코딩 없이 다시 mux한 파일이 원본보다 나으면 원본을 바꿈
+///
+/// return: 바꾼 파일의 검사 결과
+async fn repair(
+    ctx: &Context,
+    channel_name: &str,
+    directory: &Path,
+    format: OutputFormat,
+    file_name: &str,
+    expected: Duration,
+) -> Option<FileReport> {
+    let repaired_name = format!("repaired.{file_name}");
+    let repaired = directory.join(&repaired_name);
+
+    let remuxed = match remux(
+        &ctx.ffmpeg.ffmpeg_binary,
+        &directory.join(file_name),
+        &repaired,
+        format,
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            eprintln!("[{channel_name}] remux: {err}");
+            false
+        }
+    };
+
+    let file_report = if remuxed {
+        check_file(directory, &repaired_name, format, expected)
+            .await
+            .ok()
+            .filter(|file_report| !file_report.needs_repair())
+    } else {
+        None
+    };
+
+    let Some(mut file_report) = file_report else {
+        std::fs::remove_file(&repaired).ok();
+        eprintln!("[{channel_name}] failed to repair {file_name}");
+        return None;
+    };
+
+    if let Err(err) = std::fs::rename(&repaired, directory.join(file_name)) {
+        eprintln!("[{channel_name}] rename: {err}");
+        return None;
+    }
+
+    println!("[{channel_name}] repaired {file_name}");
+
+    file_report.file_name = file_name.to_owned();
+    file_report.repaired = true;
+
+    Some(file_report)
+}
+
+/// 저널을 바탕으로 각 part의 길이를 구함
+///
+/// 녹화 파일이 마지막으로 수정된 시간을 녹화가 끝난 시간으로 봄
+fn replayed_parts(directory: &Path, replayed: &Replayed) -> Option<Vec<Duration>> {
+    let started_at = replayed.started_at?;
+
+    let last_part = replayed.parts.last();
+
+    // 나눠서 녹화했으면 마지막 파일
+    let last_file = Manifest::read(directory)
+        .ok()
+        .flatten()
+        .and_then(|manifest| manifest.segments.last().map(|x| x.file_name.clone()))
+        .unwrap_or_else(|| {
+            segment::file_name(
+                last_part.map_or(0, |part| part.index),
+                None,
+                replayed.output_format,
+            )
+        });
+
+    let modified_at = std::fs::metadata(directory.join(last_file))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(DateTime::<Utc>::from);
+
+    let last_part_duration = modified_at
+        .and_then(|modified_at| {
+            modified_at
+                .signed_duration_since(last_part.map_or(started_at, |part| part.started_at))
+                .to_std()
+                .ok()
+        })
+        .unwrap_or_default();
+
+    let offsets = [0]
+        .into_iter()
+        .chain(replayed.parts.iter().map(|part| part.offset))
+        .collect::<Vec<_>>();
+
+    let mut parts = offsets
+        .windows(2)
+        .map(|x| Duration::from_secs(x[1].saturating_sub(x[0])))
+        .collect::<Vec<_>>();
+
+    parts.push(last_part_duration);
+
+    // 챕터가 녹화 길이보다 뒤에 있으면 마지막 part를 늘림
+    let last_chapter = replayed
+        .chapters
+        .last()
+        .map(|chapter| Duration::from_secs(chapter.0.as_secs()))
+        .unwrap_or_default();
+    let total = parts.iter().sum::<Duration>();
+
+    if last_chapter > total {
+        *parts.last_mut().unwrap() += last_chapter - total;
+    }
+
+    Some(parts)
+}
+
+/// 비정상 종료로 챕터가 적용되지 않은 녹화에 저널을 바탕으로 챕터를 적용함
+///
+/// `directory`: <channel>
+async fn recover_chapters(ctx: &Context, channel_name: &str, directory: &Path) {
+    let unapplied = match journal::find_unapplied(directory) {
+        Ok(r) => r,
+        Err(err) => {
+            eprintln!("[{channel_name}] journal: {err}");
+            return;
+        }
+    };
+
+    for (directory, replayed) in unapplied {
+        let Some(parts) = replayed_parts(&directory, &replayed) else {
+            continue;
+        };
+
+        println!(
+            "[{channel_name}] {} - recovering chapters of {:?}",
+            Time::from(parts.iter().sum::<Duration>()).to_readable(":"),
+            directory
+        );
+
+        apply_chapters(
+            ctx,
+            channel_name,
+            &directory,
+            replayed.output_format,
+            &parts,
+            &replayed.chapters,
+        )
+        .await;
     }
 }
 
@@ -780,8 +1298,6 @@ async fn stop_signal(
 }
 
 #[tokio::main]
-async fn main() {
-    println!("Hello, world!");
-
-    run().await;
+async fn main() -> ExitCode {
+    run().await
 }
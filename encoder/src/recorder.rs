@@ -0,0 +1,675 @@
+//! 채널 하나의 녹화 상태
+//!
+//! ```text
+//! Idle ──Live──▶ Recording ──StreamEnded──▶ Interrupted ──GraceExpired──▶ Idle
+//!  ▲                 │                          │
+//!  │                 └──StreamEnded (grace 0)───┼──▶ 세션 종료 ──▶ Idle
+//!  └────────────────────Live (같은 방송)─────────┘
+//! ```
+//!
+//! 세션이 끝나면 15초보다 짧은 녹화는 지우고, 나머지는 [`ProcessRunner::finalize`] 로 챕터를 적용함.
+//! 종료 신호를 받았을 때도 같은 경로로 끝냄
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+use tokio::{fs, sync::watch, time::Instant};
+
+use crate::{
+    chapter::{
+        journal::{Journal, JournalEntry},
+        push_or_modify_chapter, Chapter,
+    },
+    config::{ChannelConfig, MediaType, Resume, Timezone},
+    ffmpeg::OutputFormat,
+    schedule::{Poll, PollState, Scheduler},
+    source::{LiveDetail, LiveSource, LiveStatus, Playback},
+    time::Time,
+};
+
+/// 이보다 짧은 녹화는 지움
+pub const MIN_DURATION: Duration = Duration::from_secs(15);
+
+/// 하나의 방송
+///
+/// 방송이 잠깐 끊겼다가 이어지면 같은 폴더에 part를 추가해서 녹화함
+pub struct Session {
+    /// <channel>/<date>
+    pub directory: PathBuf,
+    pub started_at: DateTime<FixedOffset>,
+    pub live_id: String,
+    pub output_format: OutputFormat,
+    /// 녹화가 끝난 part들의 길이
+    pub parts: Vec<Duration>,
+    pub chapters: Vec<Chapter>,
+    journal: Journal,
+}
+
+impl Session {
+    /// 녹화가 끝난 part들의 길이 합
+    pub fn elapsed(&self) -> Duration {
+        self.parts.iter().sum()
+    }
+}
+
+/// 녹화할 part
+pub struct Spawn<'a> {
+    pub channel: &'a ChannelConfig,
+    /// <channel>/<date>
+    pub directory: &'a Path,
+    pub part: usize,
+    /// 이전 part들의 길이 합
+    pub offset: Duration,
+    pub output_format: OutputFormat,
+    pub stream_url: &'a str,
+    pub media_type: MediaType,
+    pub title: &'a str,
+    pub artist: &'a str,
+}
+
+/// 다운로드와 ffmpeg
+pub trait Process {
+    /// 방송이 끝났거나 다운로드가 실패함
+    fn is_finished(&self) -> bool;
+
+    /// 다운로드를 멈추고 녹화 파일이 마무리될 때까지 기다림
+    #[allow(async_fn_in_trait)]
+    async fn stop(self);
+
+    /// 이미 끝난 프로세스를 정리함
+    #[allow(async_fn_in_trait)]
+    async fn wait(self);
+}
+
+#[allow(async_fn_in_trait)]
+pub trait ProcessRunner {
+    type Process: Process;
+
+    async fn spawn(&mut self, spawn: Spawn<'_>) -> io::Result<Self::Process>;
+
+    /// 끝난 세션의 녹화 파일에 챕터를 적용함
+    ///
+    /// return: 모든 파일에 적용했는지
+    async fn finalize(&mut self, channel_name: &str, session: &Session) -> bool;
+}
+
+/// 상태를 바꾸는 입력
+#[derive(Debug)]
+pub enum Event {
+    /// 녹화 중이 아닐 때 방송 중임
+    Live(LiveDetail, Playback, MediaType),
+    /// 녹화 중이 아닐 때 방송 중이 아님
+    Offline,
+    /// 녹화 중인 방송의 상태
+    Status(LiveStatus),
+    /// 다운로드 또는 ffmpeg가 끝남
+    StreamEnded,
+    /// 끊긴 방송이 유예 시간 안에 다시 시작되지 않음
+    GraceExpired,
+    /// 방송 상태를 가져오지 못함
+    PollFailed(crate::Error),
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Idle,
+    Recording,
+    /// 방송이 끊겨서 다시 이어지기를 기다림
+    Interrupted,
+    /// 세션이 끝나서 챕터를 적용하는 중
+    ///
+    /// 세션을 닫는 동안에만 [`Status`] 로 볼 수 있음
+    Finalizing,
+}
+
+/// 밖에서 볼 수 있는 녹화 상태
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    pub phase: Phase,
+    pub live_id: Option<String>,
+    pub title: Option<String>,
+    pub category: Option<String>,
+    /// <channel>/<date>
+    pub directory: Option<PathBuf>,
+    /// 녹화가 끝난 part들의 길이 합
+    pub offset: Duration,
+    /// 현재 part를 녹화하기 시작한 시간
+    pub part_started_at: Option<Instant>,
+}
+
+impl Default for Status {
+    fn default() -> Self {
+        Self {
+            phase: Phase::Idle,
+            live_id: None,
+            title: None,
+            category: None,
+            directory: None,
+            offset: Duration::ZERO,
+            part_started_at: None,
+        }
+    }
+}
+
+impl Status {
+    fn from_session(phase: Phase, session: &Session) -> Self {
+        let live_status = session.chapters.last().map(|chapter| &chapter.1);
+
+        Self {
+            phase,
+            live_id: Some(session.live_id.clone()),
+            title: live_status.map(|x| x.live_title.clone()),
+            category: live_status.and_then(|x| x.live_category.clone()),
+            directory: Some(session.directory.clone()),
+            offset: session.elapsed(),
+            part_started_at: None,
+        }
+    }
+
+    /// 녹화된 길이
+    pub fn elapsed(&self) -> Duration {
+        self.offset
+            + self
+                .part_started_at
+                .map(|x| x.elapsed())
+                .unwrap_or_default()
+    }
+}
+
+enum State<P> {
+    Idle,
+    Recording {
+        process: P,
+        /// 현재 part를 녹화하기 시작한 시간
+        started_at: Instant,
+        session: Session,
+    },
+    Interrupted {
+        session: Session,
+        since: Instant,
+    },
+}
+
+pub struct Recorder<S, R: ProcessRunner> {
+    channel: ChannelConfig,
+    timezone: Timezone,
+    grace: Duration,
+    source: S,
+    runner: R,
+    scheduler: Scheduler,
+    state: State<R::Process>,
+    status: watch::Sender<Status>,
+}
+
+impl<S, R> Recorder<S, R>
+where
+    S: LiveSource,
+    R: ProcessRunner,
+{
+    pub fn new(
+        channel: ChannelConfig,
+        timezone: Timezone,
+        resume: &Resume,
+        poll: Poll,
+        source: S,
+        runner: R,
+    ) -> Self {
+        Self {
+            channel,
+            timezone,
+            grace: Duration::from_secs(resume.grace_secs),
+            source,
+            runner,
+            scheduler: Scheduler::new(poll),
+            state: State::Idle,
+            status: watch::channel(Status::default()).0,
+        }
+    }
+
+    /// 상태가 바뀔 때마다 `status` 로 보냄
+    pub fn with_status(mut self, status: watch::Sender<Status>) -> Self {
+        self.status = status;
+        self.publish();
+        self
+    }
+
+    pub fn status(&self) -> watch::Receiver<Status> {
+        self.status.subscribe()
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self.state {
+            State::Idle => Phase::Idle,
+            State::Recording { .. } => Phase::Recording,
+            State::Interrupted { .. } => Phase::Interrupted,
+        }
+    }
+
+    fn publish(&self) {
+        let status = match &self.state {
+            State::Idle => Status::default(),
+            State::Recording {
+                started_at,
+                session,
+                ..
+            } => Status {
+                part_started_at: Some(*started_at),
+                ..Status::from_session(Phase::Recording, session)
+            },
+            State::Interrupted { session, .. } => Status::from_session(Phase::Interrupted, session),
+        };
+
+        self.status.send_if_modified(|current| {
+            let modified = *current != status;
+            *current = status;
+            modified
+        });
+    }
+
+    /// 녹화 중이거나 이어서 녹화하기를 기다리는 세션
+    pub fn session(&self) -> Option<&Session> {
+        match &self.state {
+            State::Idle => None,
+            State::Recording { session, .. } | State::Interrupted { session, .. } => Some(session),
+        }
+    }
+
+    pub fn runner(&self) -> &R {
+        &self.runner
+    }
+
+    /// <path>/<channel>
+    pub fn save_directory(&self) -> PathBuf {
+        PathBuf::from(&self.channel.path).join(&self.channel.channel_name)
+    }
+
+    /// `stop` 이 바뀔 때까지 녹화하고, 녹화 중인 세션을 마무리함
+    pub async fn run(&mut self, stop: &mut watch::Receiver<bool>) {
+        loop {
+            let event = self.poll().await;
+
+            // 예상치 않은 종료가 발생할 수 있으므로 기다리지 않음
+            let Some(state) = self.handle(event).await else {
+                continue;
+            };
+
+            let stopped = tokio::select! {
+                _ = self.scheduler.wait(state) => false,
+                _ = stop.changed() => true,
+            };
+
+            if stopped {
+                self.handle(Event::Stop).await;
+                return;
+            }
+        }
+    }
+
+    /// 현재 상태에서 확인해야 할 것을 확인함
+    pub async fn poll(&mut self) -> Event {
+        match &self.state {
+            State::Recording { process, .. } if process.is_finished() => Event::StreamEnded,
+            State::Recording { .. } => {
+                match self.source.live_status(&self.channel.channel_id).await {
+                    Ok(live_status) => Event::Status(live_status),
+                    Err(err) => Event::PollFailed(err),
+                }
+            }
+            State::Interrupted { since, .. } if since.elapsed() >= self.grace => {
+                Event::GraceExpired
+            }
+            State::Idle | State::Interrupted { .. } => match self.find_stream().await {
+                Ok(Some((live_detail, playback, media_type))) => {
+                    Event::Live(live_detail, playback, media_type)
+                }
+                Ok(None) => Event::Offline,
+                Err(err) => Event::PollFailed(err),
+            },
+        }
+    }
+
+    /// return: 다음에 확인할 때까지 기다릴 간격, 없으면 바로 다시 확인함
+    pub async fn handle(&mut self, event: Event) -> Option<PollState> {
+        let state = self.transition(event).await;
+        self.publish();
+        state
+    }
+
+    async fn transition(&mut self, event: Event) -> Option<PollState> {
+        let channel_name = self.channel.channel_name.clone();
+
+        match event {
+            Event::Live(live_detail, playback, media_type) => {
+                if let State::Recording { .. } = self.state {
+                    return Some(PollState::Live);
+                }
+
+                match self.start(live_detail, playback, media_type).await {
+                    Ok(()) => Some(PollState::Live),
+                    Err(err) => {
+                        eprintln!("[{channel_name}] {err}");
+                        Some(PollState::Offline)
+                    }
+                }
+            }
+            Event::Offline => Some(PollState::Offline),
+            Event::Status(live_status) => {
+                if let State::Recording {
+                    started_at,
+                    session,
+                    ..
+                } = &mut self.state
+                {
+                    let time = Time::from(session.elapsed() + started_at.elapsed());
+
+                    add_chapter(&channel_name, session, Chapter(time, live_status));
+                }
+
+                Some(PollState::Live)
+            }
+            Event::StreamEnded => {
+                let State::Recording {
+                    process,
+                    started_at,
+                    mut session,
+                } = std::mem::replace(&mut self.state, State::Idle)
+                else {
+                    return None;
+                };
+
+                process.wait().await;
+                session.parts.push(started_at.elapsed());
+
+                if self.grace.is_zero() {
+                    self.close(session).await;
+                } else {
+                    println!(
+                        "[{channel_name}] {} - live stream interrupted, waiting {}s to resume",
+                        Time::from(session.elapsed()).to_readable(":"),
+                        self.grace.as_secs()
+                    );
+
+                    self.state = State::Interrupted {
+                        session,
+                        since: Instant::now(),
+                    };
+                }
+
+                None
+            }
+            Event::GraceExpired => {
+                if let State::Interrupted { session, .. } =
+                    std::mem::replace(&mut self.state, State::Idle)
+                {
+                    self.close(session).await;
+                }
+
+                None
+            }
+            Event::PollFailed(err) => {
+                if err.is_api() {
+                    eprintln!(
+                        "[{channel_name}] {err} (retry {})",
+                        self.scheduler.errors() + 1
+                    );
+                    Some(PollState::Error)
+                } else {
+                    eprintln!("[{channel_name}] {err}");
+                    Some(PollState::Offline)
+                }
+            }
+            Event::Stop => {
+                match std::mem::replace(&mut self.state, State::Idle) {
+                    State::Recording {
+                        process,
+                        started_at,
+                        mut session,
+                    } => {
+                        session.parts.push(started_at.elapsed());
+
+                        println!(
+                            "[{channel_name}] {} - received stop signal",
+                            Time::from(session.elapsed()).to_readable(":")
+                        );
+
+                        // 다운로드를 멈추면 ffmpeg의 stdin이 닫히고 파일이 마무리됨
+                        process.stop().await;
+
+                        self.close(session).await;
+                    }
+                    State::Interrupted { session, .. } => self.close(session).await,
+                    State::Idle => {}
+                }
+
+                None
+            }
+        }
+    }
+
+    async fn find_stream(&self) -> crate::Result<Option<(LiveDetail, Playback, MediaType)>> {
+        let channel_id = &self.channel.channel_id;
+        let media_types = &self.channel.media_types;
+
+        let live_status = self.source.live_status(channel_id).await?;
+
+        if !live_status.is_open() {
+            return Ok(None);
+        }
+
+        let live_detail = self.source.live_detail(channel_id).await?;
+
+        if live_detail.status.adult && live_detail.playback.is_none() {
+            // 쿠키가 없거나 만료됨, master의 `/health/auth` 에서 확인할 수 있음
+            eprintln!(
+                "[{}] YOU'RE NOT AN ADULT: chzzk cookies are missing or expired",
+                self.channel.channel_name
+            );
+            return Ok(None);
+        }
+
+        let Some(playback) = live_detail.playback.as_ref() else {
+            return Ok(None);
+        };
+
+        let stream = media_types.iter().find_map(|media_type| {
+            playback
+                .iter()
+                .find(|media| media.media_type == media_type.as_str())
+                .map(|media| (media.clone(), *media_type))
+        });
+
+        if stream.is_none() {
+            eprintln!(
+                "no media matches {:?} (available: {:?})",
+                media_types,
+                playback
+                    .iter()
+                    .map(|media| media.media_type.as_str())
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        Ok(stream.map(|(playback, media_type)| (live_detail, playback, media_type)))
+    }
+
+    /// 새 세션을 시작하거나, 같은 방송이 끊겼던 세션에 part를 추가함
+    async fn start(
+        &mut self,
+        live_detail: LiveDetail,
+        playback: Playback,
+        media_type: MediaType,
+    ) -> io::Result<()> {
+        let channel_name = self.channel.channel_name.clone();
+        let save_directory = self.save_directory();
+
+        let (resume, interrupted) = match std::mem::replace(&mut self.state, State::Idle) {
+            State::Interrupted { session, .. } if session.live_id == live_detail.live_id => {
+                (Some(session), None)
+            }
+            State::Interrupted { session, .. } => (None, Some(session)),
+            _ => (None, None),
+        };
+
+        let started_at = Utc::now().with_timezone(&self.timezone.into());
+
+        let session = match resume {
+            Some(session) => Ok(session),
+            None => fs::create_dir_all(&save_directory).await.and_then(|_| {
+                self.create_session(&save_directory, started_at, &live_detail.live_id)
+            }),
+        };
+
+        let mut session = match session {
+            Ok(session) => session,
+            Err(err) => {
+                if let Some(session) = interrupted {
+                    self.close(session).await;
+                }
+                return Err(err);
+            }
+        };
+
+        let part = session.parts.len();
+
+        let spawned = async {
+            if part > 0 {
+                session.journal.append(&JournalEntry::Part {
+                    index: part,
+                    offset: session.elapsed().as_secs(),
+                    started_at,
+                })?;
+            }
+
+            self.runner
+                .spawn(Spawn {
+                    channel: &self.channel,
+                    directory: &session.directory,
+                    part,
+                    offset: session.elapsed(),
+                    output_format: session.output_format,
+                    stream_url: &playback.url,
+                    media_type,
+                    title: &live_detail.status.live_title,
+                    artist: &live_detail.channel_name,
+                })
+                .await
+        }
+        .await;
+
+        // 다른 방송이 시작됨
+        if let Some(session) = interrupted {
+            self.close(session).await;
+        }
+
+        let process = match spawned {
+            Ok(process) => process,
+            Err(err) => {
+                self.close(session).await;
+                return Err(err);
+            }
+        };
+
+        let time = Time::from(session.elapsed());
+
+        if part > 0 {
+            println!(
+                "[{channel_name}] {} - resumed live stream as part {part}",
+                time.to_readable(":"),
+            );
+        }
+
+        add_chapter(
+            &channel_name,
+            &mut session,
+            Chapter(time, live_detail.status),
+        );
+
+        self.state = State::Recording {
+            process,
+            started_at: Instant::now(),
+            session,
+        };
+
+        Ok(())
+    }
+
+    fn create_session(
+        &self,
+        save_directory: &Path,
+        started_at: DateTime<FixedOffset>,
+        live_id: &str,
+    ) -> io::Result<Session> {
+        let output_format = self.channel.ffmpeg.output_format;
+
+        let directory =
+            save_directory.join(started_at.format(&self.channel.name_template).to_string());
+
+        std::fs::create_dir_all(&directory)?;
+
+        let journal = Journal::create(&directory, started_at, output_format)?;
+
+        Ok(Session {
+            directory,
+            started_at,
+            live_id: live_id.to_owned(),
+            output_format,
+            parts: Vec::new(),
+            chapters: Vec::new(),
+            journal,
+        })
+    }
+
+    /// 짧은 녹화는 지우고, 나머지는 챕터를 적용함
+    async fn close(&mut self, session: Session) {
+        let channel_name = &self.channel.channel_name;
+        let time = Time::from(session.elapsed());
+
+        self.status
+            .send_replace(Status::from_session(Phase::Finalizing, &session));
+
+        if session.elapsed() < MIN_DURATION {
+            match fs::remove_dir_all(&session.directory).await {
+                Ok(_) => {
+                    println!(
+                        "[{channel_name}] {} - removed this live stream, because duration less than {} secs",
+                        time.to_readable(":"),
+                        MIN_DURATION.as_secs()
+                    );
+                }
+                Err(err) => eprintln!("[{channel_name}] remove_dir_all: {err}"),
+            }
+            return;
+        }
+
+        if self.runner.finalize(channel_name, &session).await {
+            println!(
+                "[{channel_name}] {} - closed live stream",
+                time.to_readable(":")
+            );
+        }
+    }
+}
+
+fn add_chapter(channel_name: &str, session: &mut Session, chapter: Chapter) {
+    let Some(modified) = push_or_modify_chapter(&mut session.chapters, chapter) else {
+        return;
+    };
+
+    if let Err(err) = session.journal.append_chapter(&modified) {
+        eprintln!("[{channel_name}] journal: {err}");
+    }
+
+    println!(
+        "[{channel_name}] {} - {:?} Playing {}",
+        modified.0.to_readable(":"),
+        modified.1.live_title,
+        modified.1.live_category.as_deref().unwrap_or("unknown")
+    );
+}
@@ -0,0 +1,210 @@
+//! 녹화가 끝난 파일을 ffprobe로 검사한 결과
+//!
+//! 녹화 폴더의 `report.json` 에 남겨서 손상된 녹화를 하나씩 열어보지 않고 찾을 수 있게 함
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ffmpeg::OutputFormat,
+    ffprobe::{ffprobe, Ffprobe},
+    Error,
+};
+
+pub const FILE_NAME: &str = "report.json";
+
+/// Matroska Cues element ID
+const CUES_ID: [u8; 4] = [0x1C, 0x53, 0xBB, 0x6B];
+/// ffmpeg는 Cues를 파일 끝에 씀
+const CUES_SEARCH_SIZE: u64 = 16 * 1024 * 1024;
+
+/// 녹화한 시간과 파일 길이의 차이가 이보다 크면 문제로 봄
+const DURATION_TOLERANCE: Duration = Duration::from_secs(30);
+const DURATION_TOLERANCE_RATIO: f64 = 0.05;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Problem {
+    Missing,
+    /// ffprobe가 파일을 읽지 못함
+    Unreadable {
+        reason: String,
+    },
+    NoVideo,
+    NoAudio,
+    /// 녹화한 시간과 파일 길이가 다름 (seconds)
+    DurationMismatch {
+        expected: f64,
+        actual: Option<f64>,
+    },
+    /// matroska 파일에 탐색 인덱스가 없음
+    MissingCues,
+    /// 종료할 때 ffmpeg가 제시간에 끝나지 않아서 강제로 종료함
+    Killed,
+    /// ffprobe를 실행하지 못하거나 파일을 읽지 못해서 검사하지 못함
+    Unchecked {
+        reason: String,
+    },
+}
+
+impl Problem {
+    /// 재인코딩 없이 다시 mux하면 고칠 수 있음
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            Problem::Unreadable { .. } | Problem::MissingCues | Problem::Killed
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReport {
+    pub file_name: String,
+    /// 녹화한 시간 (seconds)
+    pub expected_duration: f64,
+    /// ffprobe로 읽은 길이 (seconds)
+    pub duration: Option<f64>,
+    pub problems: Vec<Problem>,
+    /// 손상되어 remux한 파일로 바꿈
+    #[serde(default)]
+    pub repaired: bool,
+}
+
+impl FileReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn needs_repair(&self) -> bool {
+        self.problems.iter().any(Problem::is_repairable)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionReport {
+    pub files: Vec<FileReport>,
+}
+
+impl SessionReport {
+    pub fn is_ok(&self) -> bool {
+        self.files.iter().all(FileReport::is_ok)
+    }
+
+    pub fn read(directory: &Path) -> io::Result<Option<Self>> {
+        match fs::read(directory.join(FILE_NAME)) {
+            Ok(buf) => Ok(serde_json::from_slice(&buf).ok()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn write(&self, directory: &Path) -> io::Result<()> {
+        fs::write(directory.join(FILE_NAME), serde_json::to_vec_pretty(self)?)
+    }
+}
+
+/// `directory` 의 `file_name` 을 검사함
+///
+/// `expected`: 녹화한 시간
+///
+/// ffprobe를 실행하지 못하면 에러를 반환함
+pub fn check(
+    directory: &Path,
+    file_name: &str,
+    format: OutputFormat,
+    expected: Duration,
+) -> io::Result<FileReport> {
+    check_with(directory, file_name, format, expected, |path| ffprobe(path))
+}
+
+/// `probe`: 파일 경로를 받아서 ffprobe 결과를 반환함
+pub fn check_with<P>(
+    directory: &Path,
+    file_name: &str,
+    format: OutputFormat,
+    expected: Duration,
+    probe: P,
+) -> io::Result<FileReport>
+where
+    P: FnOnce(&Path) -> crate::Result<Ffprobe>,
+{
+    let path = directory.join(file_name);
+
+    let mut report = FileReport {
+        file_name: file_name.to_owned(),
+        expected_duration: expected.as_secs_f64(),
+        duration: None,
+        problems: Vec::new(),
+        repaired: false,
+    };
+
+    if !path.exists() {
+        report.problems.push(Problem::Missing);
+        return Ok(report);
+    }
+
+    match probe(&path) {
+        Ok(probe) => {
+            if !probe.streams.iter().any(|x| x.video().is_some()) {
+                report.problems.push(Problem::NoVideo);
+            }
+
+            if !probe.streams.iter().any(|x| x.audio().is_some()) {
+                report.problems.push(Problem::NoAudio);
+            }
+
+            report.duration = probe.duration().map(|x| x.as_secs_f64());
+
+            if !duration_matches(expected, report.duration) {
+                report.problems.push(Problem::DurationMismatch {
+                    expected: report.expected_duration,
+                    actual: report.duration,
+                });
+            }
+        }
+        Err(Error::Io(err)) => return Err(err),
+        Err(err) => report.problems.push(Problem::Unreadable {
+            reason: err.to_string(),
+        }),
+    }
+
+    if format == OutputFormat::Matroska && !has_cues(&path)? {
+        report.problems.push(Problem::MissingCues);
+    }
+
+    Ok(report)
+}
+
+fn duration_matches(expected: Duration, actual: Option<f64>) -> bool {
+    let Some(actual) = actual else {
+        return false;
+    };
+
+    let expected = expected.as_secs_f64();
+    let tolerance = DURATION_TOLERANCE
+        .as_secs_f64()
+        .max(expected * DURATION_TOLERANCE_RATIO);
+
+    (expected - actual).abs() <= tolerance
+}
+
+/// 파일 끝 부분에서 Cues element를 찾음
+fn has_cues(path: &Path) -> io::Result<bool> {
+    let mut file = File::open(path)?;
+
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(CUES_SEARCH_SIZE);
+
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut buf = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut buf)?;
+
+    Ok(buf.windows(CUES_ID.len()).any(|x| x == CUES_ID))
+}
@@ -0,0 +1,160 @@
+//! 방송 상태를 확인하는 간격
+//!
+//! 오류가 계속되면 간격을 지수적으로 늘리고, 여러 채널이 동시에 요청하지 않도록 jitter를 더함
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::time::sleep;
+
+const fn offline_secs() -> f64 {
+    5.0
+}
+
+const fn live_secs() -> f64 {
+    5.0
+}
+
+const fn error_secs() -> f64 {
+    5.0
+}
+
+const fn max_error_secs() -> f64 {
+    300.0
+}
+
+const fn jitter() -> f64 {
+    0.1
+}
+
+/// 간격은 하루를 넘을 수 없음
+const MAX_SECS: f64 = 24.0 * 60.0 * 60.0;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Poll {
+    /// 방송 중이 아닐 때
+    #[serde(default = "offline_secs")]
+    pub offline_secs: f64,
+    /// 녹화 중일 때
+    #[serde(default = "live_secs")]
+    pub live_secs: f64,
+    /// 처음 오류가 났을 때, 이후 오류가 날 때마다 두 배씩 늘어남
+    #[serde(default = "error_secs")]
+    pub error_secs: f64,
+    #[serde(default = "max_error_secs")]
+    pub max_error_secs: f64,
+    /// 간격을 ±`jitter` 비율만큼 무작위로 바꿈 (0 ~ 1)
+    #[serde(default = "jitter")]
+    pub jitter: f64,
+}
+
+impl Default for Poll {
+    fn default() -> Self {
+        Self {
+            offline_secs: offline_secs(),
+            live_secs: live_secs(),
+            error_secs: error_secs(),
+            max_error_secs: max_error_secs(),
+            jitter: jitter(),
+        }
+    }
+}
+
+impl Poll {
+    /// returns (key, reason)
+    pub fn check(&self) -> Vec<(&'static str, String)> {
+        let mut errors = Vec::new();
+
+        for (key, secs) in [
+            ("offline_secs", self.offline_secs),
+            ("live_secs", self.live_secs),
+            ("error_secs", self.error_secs),
+            ("max_error_secs", self.max_error_secs),
+        ] {
+            if secs.is_nan() || secs <= 0.0 {
+                errors.push((key, "must be greater than 0".to_owned()));
+            } else if secs > MAX_SECS {
+                errors.push((key, format!("must be less than or equal to {MAX_SECS}")));
+            }
+        }
+
+        if self.max_error_secs < self.error_secs {
+            errors.push((
+                "max_error_secs",
+                "must be greater than or equal to error_secs".to_owned(),
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.jitter) {
+            errors.push(("jitter", "must be between 0 and 1".to_owned()));
+        }
+
+        errors
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollState {
+    Offline,
+    Live,
+    /// chzzk API 요청이 실패함
+    Error,
+}
+
+pub struct Scheduler {
+    poll: Poll,
+    /// 연속으로 실패한 횟수
+    errors: u32,
+    rng: fastrand::Rng,
+}
+
+impl Scheduler {
+    pub fn new(poll: Poll) -> Self {
+        Self::with_rng(poll, fastrand::Rng::new())
+    }
+
+    /// jitter를 재현할 수 있도록 난수 생성기를 지정함
+    pub fn with_rng(poll: Poll, rng: fastrand::Rng) -> Self {
+        Self {
+            poll,
+            errors: 0,
+            rng,
+        }
+    }
+
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+
+    /// 다음 요청까지 기다릴 시간
+    ///
+    /// `Error` 가 아니면 실패 횟수를 초기화함
+    pub fn next_delay(&mut self, state: PollState) -> Duration {
+        let secs = match state {
+            PollState::Offline => {
+                self.errors = 0;
+                self.poll.offline_secs
+            }
+            PollState::Live => {
+                self.errors = 0;
+                self.poll.live_secs
+            }
+            PollState::Error => {
+                self.errors = self.errors.saturating_add(1);
+
+                let exp = 2f64.powi(self.errors.saturating_sub(1).min(31) as i32);
+
+                (self.poll.error_secs * exp).min(self.poll.max_error_secs)
+            }
+        };
+
+        let jitter = self.poll.jitter * (self.rng.f64() * 2.0 - 1.0);
+
+        // 검증하지 않은 값이어도 panic 하지 않음, NaN이나 너무 큰 값은 `MAX_SECS`, 음수는 0
+        Duration::try_from_secs_f64((secs * (1.0 + jitter)).min(MAX_SECS)).unwrap_or_default()
+    }
+
+    pub async fn wait(&mut self, state: PollState) {
+        sleep(self.next_delay(state)).await;
+    }
+}
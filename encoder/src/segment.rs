@@ -0,0 +1,334 @@
+//! 녹화를 일정 시간 또는 크기마다 여러 파일로 나눔
+//!
+//! HLS 세그먼트는 키프레임에서 시작하므로 세그먼트 사이에서만 파일을 나눔
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::AsyncWriteExt,
+    process::{Child, ChildStdin},
+    time::{timeout, Instant},
+};
+
+use crate::{ffmpeg::OutputFormat, hls::SegmentSink};
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// 둘 다 없으면 나누지 않음
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Segmentation {
+    #[serde(default)]
+    pub minutes: Option<u64>,
+    #[serde(default)]
+    pub gigabytes: Option<f64>,
+}
+
+impl Segmentation {
+    pub fn is_enabled(&self) -> bool {
+        self.minutes.is_some() || self.gigabytes.is_some()
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        self.minutes
+            .map(|x| Duration::from_secs(x.saturating_mul(60)))
+    }
+
+    /// bytes
+    pub fn size(&self) -> Option<u64> {
+        self.gigabytes
+            .map(|x| (x * 1024.0 * 1024.0 * 1024.0) as u64)
+    }
+}
+
+/// 첫 번째 part는 `index.mkv`, 나눠서 녹화하면 `index-000.mkv`, `part-1-000.mkv` ...
+pub fn file_name(part: usize, segment: Option<usize>, format: OutputFormat) -> String {
+    let stem = if part == 0 {
+        "index".to_owned()
+    } else {
+        format!("part-{part}")
+    };
+
+    match segment {
+        Some(segment) => format!("{stem}-{segment:03}.{}", format.as_ext()),
+        None => format!("{stem}.{}", format.as_ext()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file_name: String,
+    pub part: usize,
+    /// 세션이 시작된 시간 기준 (seconds)
+    pub start: f64,
+    /// 녹화 중이거나 비정상 종료되었으면 없음
+    pub end: Option<f64>,
+    /// bytes
+    pub size: Option<u64>,
+    /// ffmpeg가 제시간에 끝나지 않아서 강제로 종료함, 챕터를 적용하기 전에 remux로 복구해야 함
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub incomplete: bool,
+}
+
+/// 녹화 폴더에 있는 파일들과 각 파일의 시간 범위
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub segments: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn read(directory: &Path) -> io::Result<Option<Self>> {
+        match fs::read(directory.join(MANIFEST_FILE_NAME)) {
+            Ok(buf) => Ok(serde_json::from_slice(&buf).ok()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// 쓰는 도중에 종료되어도 이전 내용이 남도록 임시 파일에 쓴 뒤 바꿈
+    pub fn write(&self, directory: &Path) -> io::Result<()> {
+        let tmp = directory.join(format!("{MANIFEST_FILE_NAME}.tmp"));
+
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(tmp, directory.join(MANIFEST_FILE_NAME))
+    }
+
+    /// returns (file_name, start, end)
+    ///
+    /// `parts`: 각 part의 길이, 끝나지 않은 파일의 끝을 구하는데 사용함
+    pub fn files(&self, parts: &[Duration]) -> Vec<(String, Duration, Duration)> {
+        let part_end = |part: usize| parts.iter().take(part + 1).sum::<Duration>();
+
+        self.segments
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let start = Duration::from_secs_f64(entry.start.max(0.0));
+                let end = match entry.end {
+                    Some(end) => Duration::from_secs_f64(end.max(0.0)),
+                    None => match self.segments.get(i + 1) {
+                        Some(next) if next.part == entry.part => {
+                            Duration::from_secs_f64(next.start.max(0.0))
+                        }
+                        _ => part_end(entry.part),
+                    },
+                };
+
+                (entry.file_name.clone(), start, end.max(start))
+            })
+            .collect()
+    }
+}
+
+struct CurrentFile {
+    path: PathBuf,
+    ffmpeg: Child,
+    stdin: ChildStdin,
+    started_at: Instant,
+    init: Option<Bytes>,
+}
+
+/// HLS 세그먼트를 ffmpeg에 넘기고, 제한을 넘으면 ffmpeg를 새로 띄워서 다음 파일에 씀
+pub struct SegmentedOutput<F> {
+    directory: PathBuf,
+    part: usize,
+    format: OutputFormat,
+    segmentation: Segmentation,
+    /// 이 part가 시작된 시간 (세션 기준)
+    offset: Duration,
+    started_at: Instant,
+    /// 파일 경로를 받아서 stdin으로 입력을 받는 ffmpeg를 띄움
+    spawn: F,
+    /// stdin을 닫은 뒤 ffmpeg가 파일을 마무리하기를 기다리는 시간
+    timeout: Duration,
+    current: Option<CurrentFile>,
+    manifest: Manifest,
+}
+
+impl<F> SegmentedOutput<F>
+where
+    F: FnMut(&Path) -> io::Result<Child>,
+{
+    pub fn new(
+        directory: PathBuf,
+        part: usize,
+        format: OutputFormat,
+        segmentation: Segmentation,
+        offset: Duration,
+        spawn: F,
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        let manifest = Manifest::read(&directory)?.unwrap_or_default();
+
+        Ok(Self {
+            directory,
+            part,
+            format,
+            segmentation,
+            offset,
+            started_at: Instant::now(),
+            spawn,
+            timeout,
+            current: None,
+            manifest,
+        })
+    }
+
+    fn elapsed(&self) -> f64 {
+        (self.offset + self.started_at.elapsed()).as_secs_f64()
+    }
+
+    async fn open(&mut self) -> io::Result<()> {
+        let segment = self.segmentation.is_enabled().then(|| {
+            self.manifest
+                .segments
+                .iter()
+                .filter(|entry| entry.part == self.part)
+                .count()
+        });
+
+        let file_name = file_name(self.part, segment, self.format);
+        let path = self.directory.join(&file_name);
+
+        let mut ffmpeg = (self.spawn)(&path)?;
+
+        let Some(stdin) = ffmpeg.stdin.take() else {
+            ffmpeg.kill().await.ok();
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "ffmpeg has no stdin",
+            ));
+        };
+
+        println!("segment: started {file_name}");
+
+        self.manifest.segments.push(ManifestEntry {
+            file_name,
+            part: self.part,
+            start: self.elapsed(),
+            end: None,
+            size: None,
+            incomplete: false,
+        });
+        self.manifest.write(&self.directory)?;
+
+        self.current = Some(CurrentFile {
+            path,
+            ffmpeg,
+            stdin,
+            started_at: Instant::now(),
+            init: None,
+        });
+
+        Ok(())
+    }
+
+    /// stdin을 닫고 ffmpeg가 파일을 마무리할 때까지 기다림
+    ///
+    /// `timeout` 안에 끝나지 않으면 ffmpeg를 강제로 종료하고 파일을 `incomplete` 로 표시함
+    async fn close(&mut self) -> io::Result<Option<ExitStatus>> {
+        let Some(CurrentFile {
+            path,
+            mut ffmpeg,
+            mut stdin,
+            ..
+        }) = self.current.take()
+        else {
+            return Ok(None);
+        };
+
+        stdin.shutdown().await.ok();
+        drop(stdin);
+
+        let (status, incomplete) = match timeout(self.timeout, ffmpeg.wait()).await {
+            Ok(status) => (status?, false),
+            Err(_) => {
+                eprintln!(
+                    "segment: ffmpeg did not finish {:?} within {}s, killing",
+                    path,
+                    self.timeout.as_secs()
+                );
+
+                ffmpeg.kill().await?;
+
+                (ffmpeg.wait().await?, true)
+            }
+        };
+
+        let end = self.elapsed();
+        let size = fs::metadata(&path).map(|metadata| metadata.len()).ok();
+
+        if let Some(entry) = self.manifest.segments.last_mut() {
+            entry.end = Some(end);
+            entry.size = size;
+            entry.incomplete = incomplete;
+        }
+        self.manifest.write(&self.directory)?;
+
+        Ok(Some(status))
+    }
+
+    async fn should_rotate(&self) -> bool {
+        let Some(current) = self.current.as_ref() else {
+            return false;
+        };
+
+        if self
+            .segmentation
+            .duration()
+            .is_some_and(|duration| current.started_at.elapsed() >= duration)
+        {
+            return true;
+        }
+
+        match self.segmentation.size() {
+            Some(size) => tokio::fs::metadata(&current.path)
+                .await
+                .is_ok_and(|metadata| metadata.len() >= size),
+            None => false,
+        }
+    }
+
+    /// 녹화를 끝냄
+    pub async fn finish(mut self) -> io::Result<Option<ExitStatus>> {
+        self.close().await
+    }
+}
+
+impl<F> SegmentSink for SegmentedOutput<F>
+where
+    F: FnMut(&Path) -> io::Result<Child>,
+{
+    async fn write_segment(&mut self, init: Option<&Bytes>, segment: &Bytes) -> io::Result<()> {
+        if self.should_rotate().await {
+            if let Some(status) = self.close().await? {
+                if !status.success() {
+                    eprintln!("segment: ffmpeg exited with {status}");
+                }
+            }
+        }
+
+        if self.current.is_none() {
+            self.open().await?;
+        }
+
+        let current = self.current.as_mut().unwrap();
+
+        // 새 파일에는 init 세그먼트를 다시 써야 함
+        if let Some(init) = init {
+            if current.init.as_ref() != Some(init) {
+                current.stdin.write_all(init).await?;
+                current.init = Some(init.clone());
+            }
+        }
+
+        current.stdin.write_all(segment).await?;
+        current.stdin.flush().await
+    }
+}
@@ -0,0 +1,67 @@
+//! 방송 상태를 가져오는 곳
+//!
+//! 녹화 루프는 플랫폼 API를 직접 호출하지 않고 [`LiveSource`] 를 통해서 가져옴
+
+pub mod chzzk;
+pub mod scripted;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LiveStatusType {
+    #[serde(alias = "open")]
+    Open,
+    #[serde(alias = "close")]
+    Close,
+}
+
+/// 챕터를 만드는데 필요한 방송 정보
+///
+/// 이전 버전의 저널에 기록된 chzzk 응답도 읽을 수 있도록 필드 이름을 맞춤
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveStatus {
+    #[serde(alias = "live_title")]
+    pub live_title: String,
+    pub status: LiveStatusType,
+    #[serde(default, alias = "live_category")]
+    pub live_category: Option<String>,
+    #[serde(default)]
+    pub adult: bool,
+    /// 플랫폼 응답의 나머지 필드, 저널과 `chapters.json` 에 그대로 남김
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl LiveStatus {
+    pub fn is_open(&self) -> bool {
+        self.status == LiveStatusType::Open
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Playback {
+    /// `HLS`, `LLHLS`
+    pub media_type: String,
+    /// master playlist
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveDetail {
+    /// 방송마다 다름, 끊겼다가 이어진 방송인지 확인하는데 사용함
+    pub live_id: String,
+    /// 플랫폼에 표시되는 채널 이름
+    pub channel_name: String,
+    pub status: LiveStatus,
+    /// 방송 중이 아니거나 성인 인증이 필요하면 없음
+    pub playback: Option<Vec<Playback>>,
+}
+
+#[allow(async_fn_in_trait)]
+pub trait LiveSource {
+    async fn live_status(&self, channel_id: &str) -> crate::Result<LiveStatus>;
+
+    async fn live_detail(&self, channel_id: &str) -> crate::Result<LiveDetail>;
+}
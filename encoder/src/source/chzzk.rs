@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use ::chzzk::{
+    live::{get_live_detail::GetLiveDetail, get_live_status::GetLiveStatus},
+    model,
+    request::Auth,
+};
+use tokio::sync::RwLock;
+
+use super::{LiveDetail, LiveSource, LiveStatus, Playback};
+
+/// chzzk API
+pub const API_URL: &str = "https://api.chzzk.naver.com";
+/// 채팅 API
+pub const CHAT_API_URL: &str = "https://comm-api.game.naver.com";
+
+/// 로그인 쿠키를 붙임, 없으면 그대로
+pub fn with_auth(req: reqwest::RequestBuilder, auth: Option<&Auth>) -> reqwest::RequestBuilder {
+    match auth {
+        Some(Auth {
+            nid_ses,
+            nid_aut,
+            nid_jkl,
+        }) => req.header(
+            reqwest::header::COOKIE,
+            format!("NID_AUT={nid_aut}; NID_SES={nid_ses}; NID_JKL={nid_jkl}"),
+        ),
+        None => req,
+    }
+}
+
+/// chzzk.naver.com
+///
+/// master에서 받아온 인증 정보가 바뀌면 다음 요청부터 사용함
+pub struct Chzzk {
+    auth: Arc<RwLock<Option<Auth>>>,
+}
+
+impl Chzzk {
+    pub fn new(auth: Arc<RwLock<Option<Auth>>>) -> Self {
+        Self { auth }
+    }
+}
+
+/// 챕터에 필요한 필드만 읽고 나머지는 `extra` 에 그대로 남김
+fn live_status(live_status: model::LiveStatus) -> crate::Result<LiveStatus> {
+    let value = serde_json::to_value(live_status).map_err(crate::Error::SerializeJson)?;
+
+    serde_json::from_value(value).map_err(crate::Error::DeserializeJson)
+}
+
+impl LiveSource for Chzzk {
+    async fn live_status(&self, channel_id: &str) -> crate::Result<LiveStatus> {
+        let auth = self.auth.read().await.clone();
+
+        let live_status = GetLiveStatus { channel_id }.send(auth.as_ref()).await?;
+
+        self::live_status(live_status)
+    }
+
+    async fn live_detail(&self, channel_id: &str) -> crate::Result<LiveDetail> {
+        let auth = self.auth.read().await.clone();
+
+        let live_detail = GetLiveDetail { channel_id }.send(auth.as_ref()).await?;
+
+        let model::Live {
+            live_id,
+            channel,
+            live_playback,
+            ..
+        } = &live_detail.inherit;
+
+        let playback = live_playback.as_ref().map(|live_playback| {
+            live_playback
+                .media
+                .iter()
+                .map(|media| Playback {
+                    media_type: media.media_id.clone(),
+                    url: media.path.clone(),
+                })
+                .collect()
+        });
+
+        Ok(LiveDetail {
+            live_id: live_id.to_string(),
+            channel_name: channel.channel_name.clone(),
+            playback,
+            status: live_status(live_detail.into())?,
+        })
+    }
+}
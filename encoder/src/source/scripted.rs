@@ -0,0 +1,124 @@
+//! 네트워크 없이 녹화 흐름을 시험하기 위해 미리 정한 순서대로 방송 상태를 돌려줌
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::Error;
+
+use super::{LiveDetail, LiveSource, LiveStatus, LiveStatusType, Playback};
+
+#[derive(Debug, Clone)]
+pub enum Step {
+    Offline,
+    Live(LiveDetail),
+    /// API 요청이 실패함
+    Error(String),
+}
+
+impl Step {
+    /// `HLS` 스트림 하나만 있는 방송
+    pub fn live(live_id: &str, title: &str, category: Option<&str>, url: &str) -> Self {
+        Self::Live(LiveDetail {
+            live_id: live_id.to_owned(),
+            channel_name: String::new(),
+            status: LiveStatus {
+                live_title: title.to_owned(),
+                status: LiveStatusType::Open,
+                live_category: category.map(ToOwned::to_owned),
+                adult: false,
+                extra: Default::default(),
+            },
+            playback: Some(vec![Playback {
+                media_type: "HLS".to_owned(),
+                url: url.to_owned(),
+            }]),
+        })
+    }
+}
+
+struct State {
+    steps: VecDeque<Step>,
+    current: Step,
+    polls: usize,
+}
+
+/// `live_status` 를 호출할 때마다 다음 단계로 넘어가고,
+/// `live_detail` 은 현재 단계를 돌려줌
+///
+/// 단계가 다 떨어지면 마지막 단계를 계속 돌려줌
+pub struct Scripted {
+    channel_name: String,
+    state: Mutex<State>,
+}
+
+impl Scripted {
+    pub fn new(channel_name: &str, steps: impl IntoIterator<Item = Step>) -> Self {
+        Self {
+            channel_name: channel_name.to_owned(),
+            state: Mutex::new(State {
+                steps: steps.into_iter().collect(),
+                current: Step::Offline,
+                polls: 0,
+            }),
+        }
+    }
+
+    /// `live_status` 를 호출한 횟수
+    pub fn polls(&self) -> usize {
+        self.state.lock().unwrap().polls
+    }
+
+    /// 남은 단계가 없음
+    pub fn is_done(&self) -> bool {
+        self.state.lock().unwrap().steps.is_empty()
+    }
+
+    fn offline(&self) -> LiveDetail {
+        LiveDetail {
+            live_id: String::new(),
+            channel_name: self.channel_name.clone(),
+            status: LiveStatus {
+                live_title: String::new(),
+                status: LiveStatusType::Close,
+                live_category: None,
+                adult: false,
+                extra: Default::default(),
+            },
+            playback: None,
+        }
+    }
+}
+
+impl LiveSource for Scripted {
+    async fn live_status(&self, _channel_id: &str) -> crate::Result<LiveStatus> {
+        let current = {
+            let mut state = self.state.lock().unwrap();
+
+            state.polls += 1;
+
+            if let Some(step) = state.steps.pop_front() {
+                state.current = step;
+            }
+
+            state.current.clone()
+        };
+
+        match current {
+            Step::Offline => Ok(self.offline().status),
+            Step::Live(live_detail) => Ok(live_detail.status),
+            Step::Error(err) => Err(Error::LiveSource(err)),
+        }
+    }
+
+    async fn live_detail(&self, _channel_id: &str) -> crate::Result<LiveDetail> {
+        let current = self.state.lock().unwrap().current.clone();
+
+        match current {
+            Step::Offline => Ok(self.offline()),
+            Step::Live(live_detail) => Ok(LiveDetail {
+                channel_name: self.channel_name.clone(),
+                ..live_detail
+            }),
+            Step::Error(err) => Err(Error::LiveSource(err)),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use encoder::chat::{ChatKind, ChatMessage, ChatRecorder, ChatServer, ChatSource, Error};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, sync::oneshot, time::sleep};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+/// 로컬 websocket 서버에 접속함
+#[derive(Clone)]
+struct Local {
+    url: String,
+    calls: Arc<AtomicUsize>,
+}
+
+impl ChatSource for Local {
+    async fn chat_server(&self) -> Result<ChatServer, Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+
+        Ok(ChatServer {
+            url: self.url.clone(),
+            chat_channel_id: "cid".to_owned(),
+            access_token: "token".to_owned(),
+        })
+    }
+}
+
+type Socket = WebSocketStream<tokio::net::TcpStream>;
+
+async fn listen() -> (TcpListener, Local) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+    let local = Local {
+        url: format!("ws://{}", listener.local_addr().unwrap()),
+        calls: Arc::default(),
+    };
+
+    (listener, local)
+}
+
+/// 접속 요청을 확인하고 접속 완료를 보냄
+async fn accept(listener: &TcpListener) -> Socket {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+    let connect = recv(&mut socket).await;
+
+    assert_eq!(connect["cmd"], 100);
+    assert_eq!(connect["cid"], "cid");
+    assert_eq!(connect["bdy"]["accTkn"], "token");
+
+    send(
+        &mut socket,
+        json!({ "ver": "2", "cmd": 10100, "retCode": 0 }),
+    )
+    .await;
+
+    socket
+}
+
+async fn recv(socket: &mut Socket) -> Value {
+    loop {
+        match socket.next().await.unwrap().unwrap() {
+            Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+            _ => continue,
+        }
+    }
+}
+
+async fn send(socket: &mut Socket, frame: Value) {
+    socket.send(Message::Text(frame.to_string())).await.unwrap();
+}
+
+fn chat(uid: &str, nickname: &str, msg: &str) -> Value {
+    json!({
+        "uid": uid,
+        "profile": json!({ "nickname": nickname }).to_string(),
+        "msg": msg,
+        "msgTime": 1709000000000i64,
+        "msgTypeCode": 1,
+    })
+}
+
+/// `count` 개가 저장될 때까지 기다림
+async fn wait_for(directory: &Path, count: usize) -> Vec<ChatMessage> {
+    for _ in 0..100 {
+        let messages = ChatMessage::read_all(directory).unwrap();
+
+        if messages.len() >= count {
+            return messages;
+        }
+
+        sleep(Duration::from_millis(50)).await;
+    }
+
+    panic!("expected {count} messages");
+}
+
+#[tokio::test]
+async fn records_chat_with_recording_time() {
+    let (listener, local) = listen().await;
+    let directory = tempfile::tempdir().unwrap();
+
+    let recorder =
+        ChatRecorder::new("test", local, directory.path(), Duration::from_secs(100)).unwrap();
+    let (stop, stopped) = oneshot::channel();
+    let handle = tokio::spawn(recorder.run(stopped));
+
+    let mut socket = accept(&listener).await;
+
+    // 서버의 ping에 응답함
+    send(&mut socket, json!({ "ver": "2", "cmd": 0 })).await;
+    assert_eq!(recv(&mut socket).await["cmd"], 10000);
+
+    send(
+        &mut socket,
+        json!({
+            "cmd": 93101,
+            "bdy": [chat("a", "alice", "hello"), chat("b", "bob", "hi")],
+        }),
+    )
+    .await;
+    send(
+        &mut socket,
+        json!({
+            "cmd": 93102,
+            "bdy": [{
+                "uid": "anonymous",
+                "profile": null,
+                "msg": "thanks",
+                "extras": json!({ "payAmount": 1000 }).to_string(),
+            }],
+        }),
+    )
+    .await;
+    // 모르는 메시지는 무시함
+    send(&mut socket, json!({ "cmd": 94008, "bdy": {} })).await;
+
+    let messages = wait_for(directory.path(), 3).await;
+
+    stop.send(()).unwrap();
+    handle.await.unwrap();
+
+    assert_eq!(
+        messages
+            .iter()
+            .map(|x| (x.kind, x.nickname.as_deref(), x.message.as_str()))
+            .collect::<Vec<_>>(),
+        [
+            (ChatKind::Chat, Some("alice"), "hello"),
+            (ChatKind::Chat, Some("bob"), "hi"),
+            (ChatKind::Donation, None, "thanks"),
+        ]
+    );
+    assert!(messages.iter().all(|x| (100.0..110.0).contains(&x.time)));
+    assert_eq!(messages[0].user_id.as_deref(), Some("a"));
+    assert_eq!(
+        messages[0].sent_at.map(|x| x.timestamp_millis()),
+        Some(1709000000000)
+    );
+    assert_eq!(messages[2].pay_amount, Some(1000));
+}
+
+#[tokio::test]
+async fn reconnects_after_drop() {
+    let (listener, local) = listen().await;
+    let calls = local.calls.clone();
+    let directory = tempfile::tempdir().unwrap();
+
+    let recorder = ChatRecorder::new("test", local, directory.path(), Duration::ZERO).unwrap();
+    let (stop, stopped) = oneshot::channel();
+    let handle = tokio::spawn(recorder.run(stopped));
+
+    let mut socket = accept(&listener).await;
+    send(
+        &mut socket,
+        json!({ "cmd": 93101, "bdy": [chat("a", "alice", "before")] }),
+    )
+    .await;
+    drop(socket);
+
+    // 끊긴 뒤 다시 접속 정보를 받아서 연결함
+    let mut socket = accept(&listener).await;
+    send(
+        &mut socket,
+        json!({ "cmd": 93101, "bdy": [chat("a", "alice", "after")] }),
+    )
+    .await;
+
+    let messages = wait_for(directory.path(), 2).await;
+
+    stop.send(()).unwrap();
+    handle.await.unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        messages
+            .iter()
+            .map(|x| x.message.as_str())
+            .collect::<Vec<_>>(),
+        ["before", "after"]
+    );
+}
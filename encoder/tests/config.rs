@@ -0,0 +1,109 @@
+use std::fs;
+
+use encoder::config::Config;
+
+fn write_config(config: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+
+    fs::write(&path, config).unwrap();
+
+    (dir, path)
+}
+
+#[test]
+fn collects_type_errors_per_key() {
+    let (_dir, path) = write_config(
+        r#"{
+            "path": 1,
+            "channels": [
+                { "channel_id": 1, "channel_name": "a" },
+                { "channel_id": "b", "channel_name": "b", "media_types": ["HLS", "DASH", "FLV"] }
+            ],
+            "chat": { "enabled": "yes" }
+        }"#,
+    );
+
+    let errors = Config::from_path(&path).err().unwrap();
+
+    assert_eq!(
+        errors
+            .errors
+            .iter()
+            .map(|x| x.key.as_str())
+            .collect::<Vec<_>>(),
+        [
+            "channels[0].channel_id",
+            "channels[1].media_types[1]",
+            "channels[1].media_types[2]",
+            "chat.enabled",
+            "path"
+        ]
+    );
+}
+
+#[test]
+fn check_does_not_create_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let recordings = dir.path().join("recordings/a");
+
+    let (_config_dir, path) = write_config(&format!(
+        r#"{{ "path": {:?}, "channels": [{{ "channel_id": "a", "channel_name": "a" }}] }}"#,
+        recordings.to_str().unwrap()
+    ));
+
+    Config::from_path(&path).unwrap();
+
+    assert!(!dir.path().join("recordings").exists());
+
+    // 폴더가 아닌 파일
+    fs::write(dir.path().join("recordings"), []).unwrap();
+
+    let errors = Config::from_path(&path).err().unwrap();
+
+    assert_eq!(errors.errors[0].key, "path");
+}
+
+#[test]
+fn check_leaves_no_probe_file() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let (_config_dir, path) = write_config(&format!(
+        r#"{{ "path": {:?}, "channels": [{{ "channel_id": "a", "channel_name": "a" }}] }}"#,
+        dir.path().to_str().unwrap()
+    ));
+
+    Config::from_path(&path).unwrap();
+
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn rejects_out_of_range_segments() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let (_config_dir, path) = write_config(&format!(
+        r#"{{
+            "path": {:?},
+            "segment": {{ "minutes": 0 }},
+            "channels": [
+                {{ "channel_id": "a", "channel_name": "a", "segment": {{ "minutes": {} }} }},
+                {{ "channel_id": "b", "channel_name": "b", "segment": {{ "minutes": {} }} }}
+            ]
+        }}"#,
+        dir.path().to_str().unwrap(),
+        u64::MAX / 60,
+        u64::MAX / 60 + 1,
+    ));
+
+    let errors = Config::from_path(&path).err().unwrap();
+
+    assert_eq!(
+        errors
+            .errors
+            .iter()
+            .map(|x| x.key.as_str())
+            .collect::<Vec<_>>(),
+        ["segment.minutes", "channels[1].segment.minutes"]
+    );
+}
@@ -0,0 +1,34 @@
+//! 환경 변수를 바꾸므로 다른 테스트와 같은 프로세스에서 실행하지 않도록 따로 둠
+
+use encoder::config::Config;
+
+#[test]
+fn env_channel_overrides() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for (key, value) in [
+        ("ELLIER_PATH", dir.path().to_str().unwrap()),
+        ("CHANNEL_ID", "a"),
+        ("CHANNEL_NAME", "a"),
+        ("ENABLE_POST_PROCESSING", "true"),
+        ("VIDEO_CODEC", "libx264"),
+        ("VIDEO_CRF", "28"),
+        ("CHANNEL_VIDEO_CRF", "23"),
+        ("CHANNEL_AUDIO_CODEC", "aac"),
+        ("CHANNEL_AUDIO_BITRATE", "160k"),
+        ("SEGMENT_MINUTES", "60"),
+        ("CHANNEL_SEGMENT_GIGABYTES", "4"),
+    ] {
+        std::env::set_var(key, value);
+    }
+
+    let mut config = Config::from_env().unwrap();
+    let channel = config.channels.remove(0).resolve(&config);
+
+    assert_eq!(config.ffmpeg.video.crf, Some(28));
+    assert_eq!(channel.ffmpeg.video.crf, Some(23));
+    assert_eq!(channel.ffmpeg.audio.bitrate.as_deref(), Some("160k"));
+    // 채널 설정이 있으면 전역 설정을 통째로 덮어씀
+    assert_eq!(channel.segment.minutes, None);
+    assert_eq!(channel.segment.gigabytes, Some(4.0));
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use encoder::ffprobe::{Ffprobe, FfprobeStreamKind};
+
+const SAMPLE: &str = r#"{
+    "streams": [
+        {
+            "index": 0,
+            "codec_name": "hevc",
+            "profile": "Main",
+            "codec_type": "video",
+            "width": 1920,
+            "height": 1080,
+            "pix_fmt": "yuv420p",
+            "avg_frame_rate": "60/1",
+            "start_time": "0.150000",
+            "disposition": { "default": 1, "forced": 0 },
+            "tags": { "VARIANT_BITRATE": "8192000" }
+        },
+        {
+            "index": 1,
+            "codec_name": "aac",
+            "profile": "LC",
+            "codec_type": "audio",
+            "sample_rate": "48000",
+            "channels": 2,
+            "channel_layout": "stereo",
+            "start_time": "-0.044000",
+            "duration": "N/A",
+            "bit_rate": "320000"
+        },
+        {
+            "index": 2,
+            "codec_name": "timed_id3",
+            "codec_type": "data",
+            "duration": "609.9"
+        },
+        {
+            "index": 3,
+            "codec_type": "something_new"
+        }
+    ],
+    "format": {
+        "filename": "2024-02-28_02-34-48.mkv",
+        "nb_streams": 4,
+        "format_name": "matroska,webm",
+        "start_time": "-0.044000",
+        "duration": "609.964000",
+        "size": "289736025",
+        "bit_rate": "3800040",
+        "probe_score": 100,
+        "tags": { "ENCODER": "Lavf60.16.100" }
+    }
+}"#;
+
+#[test]
+fn parses_streams_and_format() {
+    let probe = serde_json::from_str::<Ffprobe>(SAMPLE).unwrap();
+
+    let video = probe.streams[0].video().unwrap();
+    assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+    assert!(probe.streams[0].is_default());
+    assert_eq!(probe.streams[0].start_time, Some(0.15));
+
+    let audio = probe.streams[1].audio().unwrap();
+    assert_eq!(audio.sample_rate, Some(48000));
+    assert_eq!(probe.streams[1].start_time, Some(-0.044));
+    assert_eq!(probe.streams[1].duration, None);
+    assert_eq!(probe.streams[1].bit_rate, Some(320000));
+
+    assert!(matches!(probe.streams[2].kind, FfprobeStreamKind::Data));
+    assert!(matches!(probe.streams[3].kind, FfprobeStreamKind::Unknown));
+
+    let format = probe.format.as_ref().unwrap();
+    assert_eq!(format.size, Some(289736025));
+    assert_eq!(format.bit_rate, Some(3800040));
+    assert_eq!(format.tags["ENCODER"], "Lavf60.16.100");
+
+    assert_eq!(probe.duration(), Some(Duration::from_secs_f64(609.964)));
+}
+
+#[test]
+fn falls_back_to_longest_stream() {
+    let probe = serde_json::from_str::<Ffprobe>(
+        r#"{
+            "streams": [
+                { "index": 0, "codec_type": "video", "duration": "10.5" },
+                { "index": 1, "codec_type": "audio", "duration": 12 }
+            ]
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(probe.duration(), Some(Duration::from_secs(12)));
+}
+
+#[test]
+fn ignores_durations_out_of_range() {
+    let probe = serde_json::from_str::<Ffprobe>(
+        r#"{
+            "streams": [
+                { "index": 0, "codec_type": "video", "duration": "1e300" },
+                { "index": 1, "codec_type": "audio", "duration": -1 },
+                { "index": 2, "codec_type": "data", "duration": "7" }
+            ],
+            "format": {
+                "filename": "index.mkv",
+                "nb_streams": 3,
+                "format_name": "matroska,webm",
+                "duration": "1e300"
+            }
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(probe.duration(), Some(Duration::from_secs(7)));
+}
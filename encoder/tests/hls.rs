@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use axum::{extract::State, http::StatusCode, http::Uri, Router};
+use bytes::Bytes;
+use encoder::hls::{Error, HlsDownloader, SegmentSink};
+use reqwest::Url;
+use tokio::net::TcpListener;
+
+const MASTER: &str = "#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=1280000,RESOLUTION=854x480
+480p/media.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=8192000,RESOLUTION=1920x1080,FRAME-RATE=60.000
+1080p60/media.m3u8
+";
+
+/// 요청할 때마다 다음 playlist를 줌, 마지막 playlist는 `#EXT-X-ENDLIST` 로 끝남
+const MEDIA: [&str; 4] = [
+    "#EXTM3U
+#EXT-X-TARGETDURATION:1
+#EXT-X-MEDIA-SEQUENCE:10
+#EXTINF:1,
+10.ts
+#EXTINF:1,
+11.ts
+",
+    // 12는 처음 요청하면 500
+    "#EXTM3U
+#EXT-X-TARGETDURATION:1
+#EXT-X-MEDIA-SEQUENCE:10
+#EXTINF:1,
+10.ts
+#EXTINF:1,
+11.ts
+#EXTINF:1,
+12.ts
+",
+    // 14는 404
+    "#EXTM3U
+#EXT-X-TARGETDURATION:1
+#EXT-X-MEDIA-SEQUENCE:12
+#EXTINF:1,
+12.ts
+#EXT-X-DISCONTINUITY
+#EXTINF:1,
+13.ts
+#EXTINF:1,
+14.ts
+",
+    // 15를 놓침
+    "#EXTM3U
+#EXT-X-TARGETDURATION:1
+#EXT-X-MEDIA-SEQUENCE:16
+#EXTINF:1,
+16.ts
+#EXT-X-ENDLIST
+",
+];
+
+/// path -> 요청 횟수
+type Requests = Arc<Mutex<HashMap<String, usize>>>;
+
+async fn serve(State(requests): State<Requests>, uri: Uri) -> (StatusCode, Bytes) {
+    let path = uri.path().to_owned();
+
+    let count = {
+        let mut requests = requests.lock().unwrap();
+        let count = requests.entry(path.clone()).or_default();
+        *count += 1;
+        *count
+    };
+
+    match path.as_str() {
+        "/master.m3u8" => (StatusCode::OK, Bytes::from_static(MASTER.as_bytes())),
+        "/1080p60/media.m3u8" => (
+            StatusCode::OK,
+            Bytes::from_static(MEDIA[(count - 1).min(MEDIA.len() - 1)].as_bytes()),
+        ),
+        "/1080p60/12.ts" if count == 1 => (StatusCode::INTERNAL_SERVER_ERROR, Bytes::new()),
+        "/1080p60/14.ts" => (StatusCode::NOT_FOUND, Bytes::new()),
+        "/forbidden/media.m3u8" => (StatusCode::FORBIDDEN, Bytes::new()),
+        segment if segment.starts_with("/1080p60/") => {
+            (StatusCode::OK, Bytes::from(segment.to_owned()))
+        }
+        _ => (StatusCode::NOT_FOUND, Bytes::new()),
+    }
+}
+
+#[derive(Default)]
+struct Segments(Vec<Bytes>);
+
+impl SegmentSink for Segments {
+    async fn write_segment(&mut self, _: Option<&Bytes>, segment: &Bytes) -> io::Result<()> {
+        self.0.push(segment.clone());
+        Ok(())
+    }
+}
+
+/// returns (요청 횟수, master playlist를 받는 downloader)
+async fn start() -> (Requests, HlsDownloader) {
+    let requests = Requests::default();
+
+    let app = Router::new().fallback(serve).with_state(requests.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let downloader = HlsDownloader {
+        http: reqwest::Client::new(),
+        auth: None,
+        url: format!("http://{addr}/master.m3u8"),
+        qualities: vec!["1440p".to_owned(), "1080p60".to_owned()],
+    };
+
+    (requests, downloader)
+}
+
+#[tokio::test]
+async fn downloads_until_end_list() {
+    let (requests, downloader) = start().await;
+
+    let (media_url, variant) = downloader.resolve().await.unwrap();
+
+    assert_eq!(media_url.path(), "/1080p60/media.m3u8");
+    assert_eq!(variant.unwrap().bandwidth, 8192000);
+
+    let mut segments = Segments::default();
+
+    downloader
+        .download(&media_url, &mut segments)
+        .await
+        .unwrap();
+
+    // 이미 받은 세그먼트는 다시 받지 않고, 404는 건너뜀
+    assert_eq!(
+        segments.0,
+        ["10", "11", "12", "13", "16"]
+            .map(|x| Bytes::from(format!("/1080p60/{x}.ts")))
+            .to_vec()
+    );
+
+    let requests = requests.lock().unwrap();
+
+    assert_eq!(requests["/1080p60/media.m3u8"], MEDIA.len());
+    // 500은 다시 시도하고 404는 다시 시도하지 않음
+    assert_eq!(requests["/1080p60/12.ts"], 2);
+    assert_eq!(requests["/1080p60/14.ts"], 1);
+    assert_eq!(requests["/1080p60/10.ts"], 1);
+}
+
+#[tokio::test]
+async fn does_not_retry_forbidden_playlist() {
+    let (requests, downloader) = start().await;
+
+    let media_url = Url::parse(&downloader.url)
+        .unwrap()
+        .join("forbidden/media.m3u8")
+        .unwrap();
+
+    let err = downloader
+        .download(&media_url, &mut Segments::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::Status(status) if status.as_u16() == 403));
+    assert_eq!(requests.lock().unwrap()["/forbidden/media.m3u8"], 1);
+}
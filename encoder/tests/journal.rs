@@ -0,0 +1,146 @@
+use std::{fs, io::Write, path::Path, time::Duration};
+
+use chrono::DateTime;
+use encoder::{
+    chapter::{
+        journal::{self, Journal, JournalEntry, FILE_NAME},
+        Chapter,
+    },
+    ffmpeg::OutputFormat,
+    source::{LiveStatus, LiveStatusType},
+    time::Time,
+};
+use tempfile::TempDir;
+
+fn chapter(secs: u64, title: &str) -> Chapter {
+    Chapter(
+        Time::from(Duration::from_secs(secs)),
+        LiveStatus {
+            live_title: title.to_owned(),
+            status: LiveStatusType::Open,
+            live_category: None,
+            adult: false,
+            extra: Default::default(),
+        },
+    )
+}
+
+fn create(directory: &Path) -> Journal {
+    fs::create_dir_all(directory).unwrap();
+
+    Journal::create(
+        directory,
+        DateTime::parse_from_rfc3339("2024-02-28T02:34:48+09:00").unwrap(),
+        OutputFormat::Matroska,
+    )
+    .unwrap()
+}
+
+fn titles(chapters: &[Chapter]) -> Vec<(u64, &str)> {
+    chapters
+        .iter()
+        .map(|Chapter(time, live_status)| (time.as_secs(), live_status.live_title.as_str()))
+        .collect()
+}
+
+#[test]
+fn ignores_truncated_last_line() {
+    let dir = TempDir::new().unwrap();
+    let mut journal = create(dir.path());
+
+    journal.append_chapter(&chapter(0, "a")).unwrap();
+
+    // 쓰는 도중에 종료됨
+    fs::OpenOptions::new()
+        .append(true)
+        .open(dir.path().join(FILE_NAME))
+        .unwrap()
+        .write_all(br#"{"type":"chapter","time":60,"live_status":{"liveTi"#)
+        .unwrap();
+
+    let replayed = journal::replay(dir.path()).unwrap().unwrap();
+
+    assert_eq!(
+        replayed.started_at.unwrap().to_rfc3339(),
+        "2024-02-28T02:34:48+09:00"
+    );
+    assert_eq!(titles(&replayed.chapters), [(0, "a")]);
+    assert!(!replayed.applied);
+}
+
+#[test]
+fn chapters_after_applied_are_unapplied() {
+    let dir = TempDir::new().unwrap();
+    let mut journal = create(dir.path());
+
+    journal.append_chapter(&chapter(0, "a")).unwrap();
+    journal.append(&JournalEntry::Applied).unwrap();
+
+    assert!(journal::replay(dir.path()).unwrap().unwrap().applied);
+
+    journal.append_chapter(&chapter(60, "b")).unwrap();
+
+    let replayed = journal::replay(dir.path()).unwrap().unwrap();
+
+    assert!(!replayed.applied);
+    assert_eq!(titles(&replayed.chapters), [(0, "a"), (60, "b")]);
+}
+
+#[test]
+fn later_change_at_same_time_wins() {
+    let dir = TempDir::new().unwrap();
+    let mut journal = create(dir.path());
+
+    journal.append_chapter(&chapter(60, "b")).unwrap();
+    journal.append_chapter(&chapter(0, "a")).unwrap();
+    journal.append_chapter(&chapter(60, "c")).unwrap();
+
+    let replayed = journal::replay(dir.path()).unwrap().unwrap();
+
+    // 시간순으로 정렬됨
+    assert_eq!(titles(&replayed.chapters), [(0, "a"), (60, "c")]);
+}
+
+#[test]
+fn finds_only_unapplied_sessions() {
+    let dir = TempDir::new().unwrap();
+
+    create(&dir.path().join("2024-02-28_02-34-48"))
+        .append_chapter(&chapter(0, "a"))
+        .unwrap();
+
+    let mut applied = create(&dir.path().join("2024-02-27_02-34-48"));
+    applied.append_chapter(&chapter(0, "b")).unwrap();
+    applied.append(&JournalEntry::Applied).unwrap();
+
+    // 저널이 없는 녹화 폴더
+    fs::create_dir(dir.path().join("2024-02-26_02-34-48")).unwrap();
+    assert!(journal::replay(&dir.path().join("2024-02-26_02-34-48"))
+        .unwrap()
+        .is_none());
+
+    // 폴더가 아닌 파일
+    fs::write(dir.path().join("recorder.log"), []).unwrap();
+
+    let unapplied = journal::find_unapplied(dir.path()).unwrap();
+
+    assert_eq!(
+        unapplied
+            .iter()
+            .map(|(path, replayed)| (
+                path.file_name().unwrap().to_str().unwrap(),
+                titles(&replayed.chapters)
+            ))
+            .collect::<Vec<_>>(),
+        [("2024-02-28_02-34-48", vec![(0, "a")])]
+    );
+}
+
+#[test]
+fn missing_channel_directory_has_nothing_to_recover() {
+    let dir = TempDir::new().unwrap();
+
+    assert!(journal::find_unapplied(&dir.path().join("a"))
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,162 @@
+use encoder::hls::{
+    playlist::{parse, ParseError, Playlist},
+    select_variant,
+};
+
+const MASTER: &str = "#EXTM3U
+#EXT-X-VERSION:3
+#EXT-X-STREAM-INF:BANDWIDTH=1280000,RESOLUTION=854x480,FRAME-RATE=30.000,CODECS=\"avc1.4d401f,mp4a.40.2\"
+480p/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=2560000,RESOLUTION=1280x720,FRAME-RATE=30.000
+720p/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=4096000,RESOLUTION=1280x720,FRAME-RATE=60.000
+720p60/index.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=8192000,RESOLUTION=1920x1080,FRAME-RATE=60.000
+1080p60/index.m3u8
+";
+
+fn media(s: &str) -> encoder::hls::playlist::MediaPlaylist {
+    match parse(s).unwrap() {
+        Playlist::Media(playlist) => playlist,
+        Playlist::Master(_) => panic!("expected media playlist"),
+    }
+}
+
+#[test]
+fn parses_master_and_selects_variants() {
+    let Playlist::Master(master) = parse(MASTER).unwrap() else {
+        panic!("expected master playlist");
+    };
+
+    assert_eq!(master.variants.len(), 4);
+    assert_eq!(master.variants[0].resolution, Some((854, 480)));
+    assert_eq!(
+        master.variants[0].codecs.as_deref(),
+        Some("avc1.4d401f,mp4a.40.2")
+    );
+    assert_eq!(
+        master
+            .variants
+            .iter()
+            .map(|x| x.name().unwrap())
+            .collect::<Vec<_>>(),
+        ["480p", "720p", "720p60", "1080p60"]
+    );
+
+    let select = |quality| select_variant(&master, quality).map(|x| x.uri.as_str());
+
+    assert_eq!(select("best"), Some("1080p60/index.m3u8"));
+    assert_eq!(select("worst"), Some("480p/index.m3u8"));
+    assert_eq!(select("720p60"), Some("720p60/index.m3u8"));
+    // 높이만 지정하면 그 중 가장 높은 bandwidth
+    assert_eq!(select("720p"), Some("720p60/index.m3u8"));
+    assert_eq!(select("1440p"), None);
+}
+
+#[test]
+fn parses_media_playlist() {
+    let playlist = media(
+        "#EXTM3U
+#EXT-X-TARGETDURATION:2
+#EXT-X-MEDIA-SEQUENCE:100
+#EXT-X-DISCONTINUITY-SEQUENCE:3
+#EXT-X-MAP:URI=\"init.mp4\"
+#EXTINF:2.000,
+100.m4s
+#EXT-X-PROGRAM-DATE-TIME:2024-03-01T00:00:02Z
+#EXTINF:1.500,live
+101.m4s
+#EXT-X-DISCONTINUITY
+#EXT-X-MAP:URI=\"init-2.mp4\"
+#EXTINF:2.000,
+102.m4s
+",
+    );
+
+    assert_eq!(playlist.target_duration, 2.0);
+    assert_eq!(playlist.media_sequence, 100);
+    assert_eq!(playlist.discontinuity_sequence, 3);
+    assert!(!playlist.end_list);
+
+    assert_eq!(
+        playlist
+            .segments
+            .iter()
+            .map(|x| (
+                x.sequence,
+                x.duration,
+                x.uri.as_str(),
+                x.discontinuity,
+                x.map.as_deref()
+            ))
+            .collect::<Vec<_>>(),
+        [
+            (100, 2.0, "100.m4s", false, Some("init.mp4")),
+            (101, 1.5, "101.m4s", false, Some("init.mp4")),
+            (102, 2.0, "102.m4s", true, Some("init-2.mp4")),
+        ]
+    );
+}
+
+#[test]
+fn sequence_gap_and_end_list() {
+    // 이전 playlist의 마지막이 101이었다면 102..104를 놓침
+    let playlist = media(
+        "#EXTM3U
+#EXT-X-TARGETDURATION:2
+#EXT-X-MEDIA-SEQUENCE:105
+#EXTINF:2,
+105.ts
+#EXTINF:2,
+106.ts
+#EXT-X-ENDLIST
+",
+    );
+
+    assert_eq!(
+        playlist
+            .segments
+            .iter()
+            .map(|x| x.sequence)
+            .collect::<Vec<_>>(),
+        [105, 106]
+    );
+    assert!(playlist.end_list);
+    assert!(playlist.segments.iter().all(|x| x.map.is_none()));
+}
+
+#[test]
+fn rejects_invalid_playlists() {
+    assert!(matches!(
+        parse("#EXTINF:2,\n0.ts"),
+        Err(ParseError::MissingHeader)
+    ));
+    assert!(matches!(
+        parse("#EXTM3U\n#EXT-X-TARGETDURATION:abc"),
+        Err(ParseError::InvalidTag(_))
+    ));
+    assert!(matches!(
+        parse("#EXTM3U\n#EXT-X-TARGETDURATION:inf"),
+        Err(ParseError::InvalidTag(_))
+    ));
+    assert!(matches!(
+        parse("#EXTM3U\n#EXTINF:NaN,\n0.ts"),
+        Err(ParseError::InvalidTag(_))
+    ));
+    assert!(matches!(
+        parse("#EXTM3U\n#EXTINF:-1,\n0.ts"),
+        Err(ParseError::InvalidTag(_))
+    ));
+    assert!(matches!(
+        parse("#EXTM3U\n0.ts"),
+        Err(ParseError::MissingUri(_))
+    ));
+    assert!(matches!(
+        parse("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1"),
+        Err(ParseError::MissingUri(_))
+    ));
+    assert!(matches!(
+        parse("#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n#EXTINF:2,\n0.ts"),
+        Err(ParseError::Unsupported(_))
+    ));
+}
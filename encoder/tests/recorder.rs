@@ -0,0 +1,423 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use encoder::{
+    config::{ChannelConfig, MediaType, Qualities, Resume, Timezone},
+    ffmpeg::Ffmpeg,
+    recorder::{Event, Phase, Process, ProcessRunner, Recorder, Session, Spawn, Status},
+    schedule::{Poll, PollState},
+    segment::Segmentation,
+    source::scripted::{Scripted, Step},
+};
+use tempfile::TempDir;
+use tokio::{sync::watch, time::advance};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Call {
+    Spawn {
+        directory: PathBuf,
+        part: usize,
+        offset: Duration,
+        url: String,
+    },
+    Stop,
+    Finalize {
+        directory: PathBuf,
+        parts: Vec<Duration>,
+        chapters: Vec<String>,
+    },
+}
+
+/// ffmpeg를 띄우지 않고 호출만 기록함
+#[derive(Clone, Default)]
+struct FakeRunner {
+    calls: Arc<Mutex<Vec<Call>>>,
+    /// true로 바꾸면 녹화 중인 프로세스가 끝난 것으로 봄
+    finished: Arc<AtomicBool>,
+}
+
+impl FakeRunner {
+    fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn end_stream(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+    }
+}
+
+struct FakeProcess {
+    calls: Arc<Mutex<Vec<Call>>>,
+    finished: Arc<AtomicBool>,
+}
+
+impl Process for FakeProcess {
+    fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    async fn stop(self) {
+        self.calls.lock().unwrap().push(Call::Stop);
+    }
+
+    async fn wait(self) {}
+}
+
+impl ProcessRunner for FakeRunner {
+    type Process = FakeProcess;
+
+    async fn spawn(&mut self, spawn: Spawn<'_>) -> std::io::Result<FakeProcess> {
+        self.finished.store(false, Ordering::SeqCst);
+
+        self.calls.lock().unwrap().push(Call::Spawn {
+            directory: spawn.directory.to_path_buf(),
+            part: spawn.part,
+            offset: spawn.offset,
+            url: spawn.stream_url.to_owned(),
+        });
+
+        Ok(FakeProcess {
+            calls: self.calls.clone(),
+            finished: self.finished.clone(),
+        })
+    }
+
+    async fn finalize(&mut self, _channel_name: &str, session: &Session) -> bool {
+        self.calls.lock().unwrap().push(Call::Finalize {
+            directory: session.directory.clone(),
+            parts: session.parts.clone(),
+            chapters: session
+                .chapters
+                .iter()
+                .map(|chapter| chapter.1.live_title.clone())
+                .collect(),
+        });
+
+        true
+    }
+}
+
+/// 녹화 폴더는 `TempDir` 을 drop 할 때 지워짐
+fn recorder(
+    grace_secs: u64,
+    steps: impl IntoIterator<Item = Step>,
+) -> (Recorder<Scripted, FakeRunner>, FakeRunner, TempDir) {
+    let directory = tempfile::tempdir().unwrap();
+
+    let channel = ChannelConfig {
+        channel_id: "channel".to_owned(),
+        channel_name: "test".to_owned(),
+        path: directory.path().to_string_lossy().into_owned(),
+        quality: Qualities(vec!["best".to_owned()]),
+        media_types: vec![MediaType::Hls],
+        // 같은 초에 세션이 여러 개 생겨도 겹치지 않게 함
+        name_template: "%H-%M-%S-%f".to_owned(),
+        ffmpeg: Ffmpeg::default(),
+        segment: Segmentation::default(),
+    };
+
+    let resume = Resume {
+        grace_secs,
+        concat: false,
+    };
+
+    let runner = FakeRunner::default();
+
+    let recorder = Recorder::new(
+        channel,
+        Timezone::default(),
+        &resume,
+        Poll::default(),
+        Scripted::new("test", steps),
+        runner.clone(),
+    );
+
+    (recorder, runner, directory)
+}
+
+fn live(live_id: &str, title: &str) -> Step {
+    Step::live(
+        live_id,
+        title,
+        Some("Talk"),
+        &format!("https://example.com/{live_id}.m3u8"),
+    )
+}
+
+/// 상태를 한 번 확인하고 처리함
+async fn step(recorder: &mut Recorder<Scripted, FakeRunner>) -> Option<PollState> {
+    let event = recorder.poll().await;
+    recorder.handle(event).await
+}
+
+#[tokio::test(start_paused = true)]
+async fn records_until_stream_ends() {
+    let (mut recorder, runner, _directory) =
+        recorder(0, [Step::Offline, live("1", "a"), live("1", "b")]);
+
+    assert_eq!(step(&mut recorder).await, Some(PollState::Offline));
+    assert_eq!(recorder.phase(), Phase::Idle);
+
+    assert_eq!(step(&mut recorder).await, Some(PollState::Live));
+    assert_eq!(recorder.phase(), Phase::Recording);
+
+    let directory = recorder.session().unwrap().directory.clone();
+
+    assert!(directory.join("chapters.jsonl").exists());
+    assert_eq!(
+        runner.calls(),
+        [Call::Spawn {
+            directory: directory.clone(),
+            part: 0,
+            offset: Duration::ZERO,
+            url: "https://example.com/1.m3u8".to_owned(),
+        }]
+    );
+
+    advance(Duration::from_secs(10)).await;
+
+    // 제목이 바뀌면 챕터가 추가됨
+    assert_eq!(step(&mut recorder).await, Some(PollState::Live));
+    assert_eq!(recorder.session().unwrap().chapters.len(), 2);
+
+    advance(Duration::from_secs(10)).await;
+    runner.end_stream();
+
+    assert_eq!(step(&mut recorder).await, None);
+    assert_eq!(recorder.phase(), Phase::Idle);
+
+    assert_eq!(
+        runner.calls().last(),
+        Some(&Call::Finalize {
+            directory,
+            parts: vec![Duration::from_secs(20)],
+            chapters: vec!["a".to_owned(), "b".to_owned()],
+        })
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn discards_short_recording() {
+    let (mut recorder, runner, _directory) = recorder(0, [live("1", "a")]);
+
+    step(&mut recorder).await;
+
+    let directory = recorder.session().unwrap().directory.clone();
+
+    advance(Duration::from_secs(5)).await;
+    runner.end_stream();
+
+    step(&mut recorder).await;
+
+    assert_eq!(recorder.phase(), Phase::Idle);
+    assert!(!directory.exists());
+    assert!(!runner
+        .calls()
+        .iter()
+        .any(|call| matches!(call, Call::Finalize { .. })));
+}
+
+/// 종료 신호를 받으면 후처리 여부와 상관 없이 챕터를 적용함
+#[tokio::test(start_paused = true)]
+async fn stop_finalizes_recording() {
+    let (mut recorder, runner, _directory) = recorder(0, [live("1", "a")]);
+
+    step(&mut recorder).await;
+
+    let directory = recorder.session().unwrap().directory.clone();
+
+    advance(Duration::from_secs(30)).await;
+
+    assert_eq!(recorder.handle(Event::Stop).await, None);
+    assert_eq!(recorder.phase(), Phase::Idle);
+
+    let calls = runner.calls();
+
+    assert_eq!(
+        calls[1..],
+        [
+            Call::Stop,
+            Call::Finalize {
+                directory,
+                parts: vec![Duration::from_secs(30)],
+                chapters: vec!["a".to_owned()],
+            }
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn resumes_same_live_as_next_part() {
+    let (mut recorder, runner, _directory) = recorder(
+        60,
+        [
+            live("1", "a"),
+            live("1", "a"),
+            Step::Offline,
+            live("1", "a"),
+        ],
+    );
+
+    step(&mut recorder).await;
+
+    let directory = recorder.session().unwrap().directory.clone();
+
+    advance(Duration::from_secs(20)).await;
+    step(&mut recorder).await;
+    runner.end_stream();
+
+    assert_eq!(step(&mut recorder).await, None);
+    assert_eq!(recorder.phase(), Phase::Interrupted);
+
+    advance(Duration::from_secs(10)).await;
+    assert_eq!(step(&mut recorder).await, Some(PollState::Offline));
+    assert_eq!(recorder.phase(), Phase::Interrupted);
+
+    advance(Duration::from_secs(10)).await;
+    assert_eq!(step(&mut recorder).await, Some(PollState::Live));
+    assert_eq!(recorder.phase(), Phase::Recording);
+
+    assert_eq!(
+        runner.calls().last(),
+        Some(&Call::Spawn {
+            directory: directory.clone(),
+            part: 1,
+            offset: Duration::from_secs(20),
+            url: "https://example.com/1.m3u8".to_owned(),
+        })
+    );
+
+    advance(Duration::from_secs(15)).await;
+    recorder.handle(Event::Stop).await;
+
+    assert_eq!(
+        runner.calls().last(),
+        Some(&Call::Finalize {
+            directory,
+            parts: vec![Duration::from_secs(20), Duration::from_secs(15)],
+            chapters: vec!["a".to_owned()],
+        })
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn closes_interrupted_session_when_other_live_starts() {
+    let (mut recorder, runner, _directory) = recorder(60, [live("1", "a"), live("2", "b")]);
+
+    step(&mut recorder).await;
+
+    let first = recorder.session().unwrap().directory.clone();
+
+    advance(Duration::from_secs(20)).await;
+    runner.end_stream();
+    step(&mut recorder).await;
+
+    assert_eq!(step(&mut recorder).await, Some(PollState::Live));
+
+    let second = recorder.session().unwrap().directory.clone();
+
+    assert_ne!(first, second);
+    assert!(runner.calls().contains(&Call::Finalize {
+        directory: first,
+        parts: vec![Duration::from_secs(20)],
+        chapters: vec!["a".to_owned()],
+    }));
+    assert_eq!(recorder.session().unwrap().live_id, "2");
+}
+
+#[tokio::test(start_paused = true)]
+async fn closes_interrupted_session_after_grace() {
+    let (mut recorder, runner, _directory) = recorder(30, [live("1", "a"), Step::Offline]);
+
+    step(&mut recorder).await;
+    advance(Duration::from_secs(20)).await;
+    runner.end_stream();
+    step(&mut recorder).await;
+
+    assert_eq!(recorder.phase(), Phase::Interrupted);
+
+    advance(Duration::from_secs(30)).await;
+
+    assert!(matches!(recorder.poll().await, Event::GraceExpired));
+
+    assert_eq!(recorder.handle(Event::GraceExpired).await, None);
+    assert_eq!(recorder.phase(), Phase::Idle);
+    assert!(matches!(runner.calls().last(), Some(Call::Finalize { .. })));
+}
+
+#[tokio::test(start_paused = true)]
+async fn api_error_backs_off() {
+    let (mut recorder, runner, _directory) =
+        recorder(0, [Step::Error("timeout".to_owned()), live("1", "a")]);
+
+    assert_eq!(step(&mut recorder).await, Some(PollState::Error));
+    assert_eq!(recorder.phase(), Phase::Idle);
+
+    assert_eq!(step(&mut recorder).await, Some(PollState::Live));
+    assert_eq!(runner.calls().len(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_stops_on_signal() {
+    let (mut recorder, runner, _directory) = recorder(0, [Step::Offline, live("1", "a")]);
+
+    let (stop_tx, mut stop_rx) = watch::channel(false);
+
+    let handle = tokio::spawn(async move {
+        recorder.run(&mut stop_rx).await;
+        recorder
+    });
+
+    // 처음 확인한 뒤 5초 간격으로 확인함
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    stop_tx.send(true).unwrap();
+
+    let recorder = handle.await.unwrap();
+
+    assert_eq!(recorder.phase(), Phase::Idle);
+
+    let calls = runner.calls();
+
+    assert!(matches!(calls.first(), Some(Call::Spawn { part: 0, .. })));
+    assert_eq!(calls[1], Call::Stop);
+    assert!(matches!(calls[2], Call::Finalize { .. }));
+}
+
+#[tokio::test(start_paused = true)]
+async fn status_follows_recording() {
+    let (recorder, runner, _directory) = recorder(0, [live("1", "a"), live("1", "b")]);
+
+    let (status, rx) = watch::channel(Status::default());
+    let mut recorder = recorder.with_status(status);
+
+    step(&mut recorder).await;
+
+    let status = rx.borrow().clone();
+
+    assert_eq!(status.phase, Phase::Recording);
+    assert_eq!(status.live_id.as_deref(), Some("1"));
+    assert_eq!(status.title.as_deref(), Some("a"));
+    assert_eq!(status.category.as_deref(), Some("Talk"));
+    assert_eq!(
+        status.directory.as_ref(),
+        Some(&recorder.session().unwrap().directory)
+    );
+
+    advance(Duration::from_secs(30)).await;
+
+    assert_eq!(rx.borrow().elapsed(), Duration::from_secs(30));
+
+    step(&mut recorder).await;
+    assert_eq!(rx.borrow().title.as_deref(), Some("b"));
+
+    runner.end_stream();
+    step(&mut recorder).await;
+
+    assert_eq!(*rx.borrow(), Status::default());
+}
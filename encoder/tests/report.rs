@@ -0,0 +1,258 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use encoder::{
+    ffmpeg::OutputFormat,
+    ffprobe::Ffprobe,
+    report::{check_with, FileReport, Problem},
+    Error,
+};
+use tempfile::TempDir;
+
+const VIDEO: &str = r#"{ "index": 0, "codec_type": "video", "width": 1920, "height": 1080 }"#;
+const AUDIO: &str = r#"{ "index": 1, "codec_type": "audio", "channels": 2 }"#;
+/// Matroska Cues element ID
+const CUES: [u8; 4] = [0x1C, 0x53, 0xBB, 0x6B];
+
+fn probe(streams: &[&str], duration: Option<f64>) -> Ffprobe {
+    let duration = duration.map_or("N/A".to_owned(), |x| x.to_string());
+
+    serde_json::from_str(&format!(
+        r#"{{
+            "streams": [{}],
+            "format": {{
+                "filename": "index.mkv",
+                "nb_streams": {},
+                "format_name": "matroska,webm",
+                "duration": "{duration}"
+            }}
+        }}"#,
+        streams.join(","),
+        streams.len()
+    ))
+    .unwrap()
+}
+
+/// `cues`: 파일 끝에 Cues element가 있는지
+fn write_file(directory: &Path, cues: bool) {
+    let mut buf = vec![0; 1024];
+
+    if cues {
+        buf.extend(CUES);
+    }
+
+    fs::write(directory.join("index.mkv"), buf).unwrap();
+}
+
+fn check(
+    directory: &Path,
+    format: OutputFormat,
+    expected: u64,
+    probe: encoder::Result<Ffprobe>,
+) -> io::Result<FileReport> {
+    check_with(
+        directory,
+        "index.mkv",
+        format,
+        Duration::from_secs(expected),
+        |path| {
+            assert_eq!(path, directory.join("index.mkv"));
+            probe
+        },
+    )
+}
+
+#[test]
+fn healthy_file() {
+    let dir = TempDir::new().unwrap();
+    write_file(dir.path(), true);
+
+    let report = check(
+        dir.path(),
+        OutputFormat::Matroska,
+        3600,
+        Ok(probe(&[VIDEO, AUDIO], Some(3599.5))),
+    )
+    .unwrap();
+
+    assert_eq!(report.problems, []);
+    assert_eq!(report.duration, Some(3599.5));
+    assert!(report.is_ok());
+    assert!(!report.needs_repair());
+}
+
+#[test]
+fn missing_file_is_not_probed() {
+    let dir = TempDir::new().unwrap();
+
+    let report = check_with(
+        dir.path(),
+        "index.mkv",
+        OutputFormat::Matroska,
+        Duration::from_secs(60),
+        |_| -> encoder::Result<Ffprobe> { unreachable!() },
+    )
+    .unwrap();
+
+    assert_eq!(report.problems, [Problem::Missing]);
+    assert!(!report.needs_repair());
+}
+
+#[test]
+fn missing_cues_needs_repair() {
+    let dir = TempDir::new().unwrap();
+    write_file(dir.path(), false);
+
+    let report = check(
+        dir.path(),
+        OutputFormat::Matroska,
+        60,
+        Ok(probe(&[VIDEO, AUDIO], Some(60.0))),
+    )
+    .unwrap();
+
+    assert_eq!(report.problems, [Problem::MissingCues]);
+    assert!(report.needs_repair());
+
+    // matroska가 아니면 Cues를 찾지 않음
+    let report = check(
+        dir.path(),
+        OutputFormat::Mpegts,
+        60,
+        Ok(probe(&[VIDEO, AUDIO], Some(60.0))),
+    )
+    .unwrap();
+
+    assert!(report.is_ok());
+}
+
+#[test]
+fn missing_streams_are_not_repairable() {
+    let dir = TempDir::new().unwrap();
+    write_file(dir.path(), true);
+
+    let report = check(
+        dir.path(),
+        OutputFormat::Matroska,
+        60,
+        Ok(probe(&[], Some(60.0))),
+    )
+    .unwrap();
+
+    assert_eq!(report.problems, [Problem::NoVideo, Problem::NoAudio]);
+    assert!(!report.needs_repair());
+
+    let report = check(
+        dir.path(),
+        OutputFormat::Matroska,
+        60,
+        Ok(probe(&[AUDIO], Some(60.0))),
+    )
+    .unwrap();
+
+    assert_eq!(report.problems, [Problem::NoVideo]);
+}
+
+#[test]
+fn duration_tolerance() {
+    let dir = TempDir::new().unwrap();
+    write_file(dir.path(), true);
+
+    let problems = |expected: u64, actual: Option<f64>| {
+        check(
+            dir.path(),
+            OutputFormat::Matroska,
+            expected,
+            Ok(probe(&[VIDEO, AUDIO], actual)),
+        )
+        .unwrap()
+        .problems
+    };
+
+    // 짧은 녹화는 30초까지
+    assert_eq!(problems(100, Some(130.0)), []);
+    assert_eq!(
+        problems(100, Some(131.0)),
+        [Problem::DurationMismatch {
+            expected: 100.0,
+            actual: Some(131.0)
+        }]
+    );
+    // 긴 녹화는 5%까지
+    assert_eq!(problems(3600, Some(3420.0)), []);
+    assert_eq!(
+        problems(3600, Some(3419.0)),
+        [Problem::DurationMismatch {
+            expected: 3600.0,
+            actual: Some(3419.0)
+        }]
+    );
+    // 길이를 읽지 못함
+    assert_eq!(
+        problems(60, None),
+        [Problem::DurationMismatch {
+            expected: 60.0,
+            actual: None
+        }]
+    );
+    // 다시 mux해도 길이는 바뀌지 않음
+    assert!(!Problem::DurationMismatch {
+        expected: 60.0,
+        actual: None
+    }
+    .is_repairable());
+}
+
+#[test]
+fn unreadable_file_needs_repair() {
+    let dir = TempDir::new().unwrap();
+    write_file(dir.path(), false);
+
+    let err = serde_json::from_str::<Ffprobe>("").unwrap_err();
+
+    let report = check(
+        dir.path(),
+        OutputFormat::Matroska,
+        60,
+        Err(Error::DeserializeJson(err)),
+    )
+    .unwrap();
+
+    assert!(matches!(
+        report.problems.as_slice(),
+        [Problem::Unreadable { .. }, Problem::MissingCues]
+    ));
+    assert_eq!(report.duration, None);
+    assert!(report.needs_repair());
+}
+
+#[test]
+fn ffprobe_failure_is_an_error() {
+    let dir = TempDir::new().unwrap();
+    write_file(dir.path(), true);
+
+    let err = check(
+        dir.path(),
+        OutputFormat::Matroska,
+        60,
+        Err(Error::Io(io::ErrorKind::NotFound.into())),
+    )
+    .unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn repairable_problems() {
+    assert!(Problem::Killed.is_repairable());
+    assert!(Problem::MissingCues.is_repairable());
+    assert!(Problem::Unreadable {
+        reason: String::new()
+    }
+    .is_repairable());
+
+    assert!(!Problem::Missing.is_repairable());
+    assert!(!Problem::Unchecked {
+        reason: String::new()
+    }
+    .is_repairable());
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use encoder::schedule::{Poll, PollState, Scheduler};
+use tokio::time::Instant;
+
+fn poll(jitter: f64) -> Poll {
+    Poll {
+        offline_secs: 30.0,
+        live_secs: 10.0,
+        error_secs: 5.0,
+        max_error_secs: 60.0,
+        jitter,
+    }
+}
+
+#[test]
+fn interval_by_state() {
+    let mut scheduler = Scheduler::new(poll(0.0));
+
+    assert_eq!(
+        scheduler.next_delay(PollState::Offline),
+        Duration::from_secs(30)
+    );
+    assert_eq!(
+        scheduler.next_delay(PollState::Live),
+        Duration::from_secs(10)
+    );
+}
+
+#[test]
+fn error_backoff_doubles_until_max() {
+    let mut scheduler = Scheduler::new(poll(0.0));
+
+    let delays = (0..6)
+        .map(|_| scheduler.next_delay(PollState::Error).as_secs())
+        .collect::<Vec<_>>();
+
+    assert_eq!(delays, [5, 10, 20, 40, 60, 60]);
+    assert_eq!(scheduler.errors(), 6);
+}
+
+#[test]
+fn success_resets_backoff() {
+    let mut scheduler = Scheduler::new(poll(0.0));
+
+    scheduler.next_delay(PollState::Error);
+    scheduler.next_delay(PollState::Error);
+    scheduler.next_delay(PollState::Live);
+
+    assert_eq!(scheduler.errors(), 0);
+    assert_eq!(
+        scheduler.next_delay(PollState::Error),
+        Duration::from_secs(5)
+    );
+}
+
+#[test]
+fn jitter_stays_in_range() {
+    let mut scheduler = Scheduler::with_rng(poll(0.1), fastrand::Rng::with_seed(7));
+
+    for _ in 0..1000 {
+        let secs = scheduler.next_delay(PollState::Offline).as_secs_f64();
+
+        assert!((27.0..=33.0).contains(&secs), "{secs}");
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn wait_uses_tokio_clock() {
+    let mut scheduler = Scheduler::new(poll(0.0));
+    let start = Instant::now();
+
+    for _ in 0..4 {
+        scheduler.wait(PollState::Error).await;
+    }
+    scheduler.wait(PollState::Live).await;
+
+    // 5 + 10 + 20 + 40 + 10
+    assert_eq!(start.elapsed(), Duration::from_secs(85));
+}
+
+#[test]
+fn check_rejects_invalid_values() {
+    let invalid = Poll {
+        offline_secs: 0.0,
+        live_secs: f64::NAN,
+        error_secs: 10.0,
+        max_error_secs: 5.0,
+        jitter: 1.5,
+    };
+
+    let keys = invalid
+        .check()
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        keys,
+        ["offline_secs", "live_secs", "max_error_secs", "jitter"]
+    );
+    assert!(Poll::default().check().is_empty());
+
+    let huge = Poll {
+        offline_secs: f64::INFINITY,
+        live_secs: 1e300,
+        ..Poll::default()
+    };
+
+    assert_eq!(
+        huge.check()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>(),
+        ["offline_secs", "live_secs"]
+    );
+}
+
+#[test]
+fn unchecked_values_do_not_panic() {
+    let mut scheduler = Scheduler::with_rng(
+        Poll {
+            offline_secs: f64::INFINITY,
+            live_secs: f64::NAN,
+            error_secs: -1.0,
+            max_error_secs: f64::INFINITY,
+            jitter: 0.0,
+        },
+        fastrand::Rng::with_seed(0),
+    );
+
+    let max = Duration::from_secs(24 * 60 * 60);
+
+    assert_eq!(scheduler.next_delay(PollState::Offline), max);
+    assert_eq!(scheduler.next_delay(PollState::Live), max);
+    assert_eq!(scheduler.next_delay(PollState::Error), Duration::ZERO);
+}
@@ -0,0 +1,366 @@
+//! ffmpeg 대신 `sh`, `sleep` 을 띄움
+#![cfg(unix)]
+
+use std::{
+    fs, io,
+    os::unix::process::ExitStatusExt,
+    path::Path,
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use encoder::{
+    chapter::{self, Chapter},
+    ffmpeg::OutputFormat,
+    hls::SegmentSink,
+    segment::{Manifest, ManifestEntry, Segmentation, SegmentedOutput},
+    source::{LiveStatus, LiveStatusType},
+    time::Time,
+};
+use tempfile::TempDir;
+use tokio::{
+    process::{Child, Command},
+    time::{self, sleep},
+};
+
+/// ffmpeg 대신 stdin을 그대로 파일에 씀
+fn cat(path: &Path) -> io::Result<Child> {
+    Command::new("sh")
+        .arg("-c")
+        .arg("cat > \"$0\"")
+        .arg(path)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+/// stdin이 닫혀도 끝나지 않는 ffmpeg
+fn hang(_: &Path) -> io::Result<Child> {
+    Command::new("sleep")
+        .arg("1000")
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+fn output(
+    directory: &Path,
+    segmentation: Segmentation,
+) -> SegmentedOutput<fn(&Path) -> io::Result<Child>> {
+    output_with(directory, segmentation, cat)
+}
+
+fn output_with(
+    directory: &Path,
+    segmentation: Segmentation,
+    spawn: fn(&Path) -> io::Result<Child>,
+) -> SegmentedOutput<fn(&Path) -> io::Result<Child>> {
+    SegmentedOutput::new(
+        directory.to_path_buf(),
+        0,
+        OutputFormat::Matroska,
+        segmentation,
+        Duration::ZERO,
+        spawn,
+        Duration::from_secs(10),
+    )
+    .unwrap()
+}
+
+/// cat이 파일에 쓸 때까지 기다림
+async fn wait_for_size(path: &Path, size: u64) {
+    let started_at = Instant::now();
+
+    while fs::metadata(path).map(|x| x.len()).unwrap_or(0) < size {
+        assert!(started_at.elapsed() < Duration::from_secs(5), "{path:?}");
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+fn entry(file_name: &str, part: usize, start: f64, end: Option<f64>) -> ManifestEntry {
+    ManifestEntry {
+        file_name: file_name.to_owned(),
+        part,
+        start,
+        end,
+        size: None,
+        incomplete: false,
+    }
+}
+
+fn chapter(secs: u64, title: &str) -> Chapter {
+    Chapter(
+        Time::from(Duration::from_secs(secs)),
+        LiveStatus {
+            live_title: title.to_owned(),
+            status: LiveStatusType::Open,
+            live_category: None,
+            adult: false,
+            extra: Default::default(),
+        },
+    )
+}
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+#[tokio::test]
+async fn rotates_by_duration() {
+    let dir = TempDir::new().unwrap();
+    let init = Bytes::from_static(b"init;");
+
+    time::pause();
+
+    let mut output = output(
+        dir.path(),
+        Segmentation {
+            minutes: Some(1),
+            gigabytes: None,
+        },
+    );
+
+    output
+        .write_segment(Some(&init), &Bytes::from_static(b"a;"))
+        .await
+        .unwrap();
+    output
+        .write_segment(Some(&init), &Bytes::from_static(b"b;"))
+        .await
+        .unwrap();
+
+    time::advance(Duration::from_secs(61)).await;
+    // ffmpeg가 끝나기를 기다리는 timeout이 바로 지나가지 않도록 시계를 다시 움직임
+    time::resume();
+
+    output
+        .write_segment(Some(&init), &Bytes::from_static(b"c;"))
+        .await
+        .unwrap();
+    assert!(output.finish().await.unwrap().unwrap().success());
+
+    // 새 파일에는 init 세그먼트를 다시 씀
+    assert_eq!(
+        fs::read(dir.path().join("index-000.mkv")).unwrap(),
+        b"init;a;b;"
+    );
+    assert_eq!(
+        fs::read(dir.path().join("index-001.mkv")).unwrap(),
+        b"init;c;"
+    );
+
+    let manifest = Manifest::read(dir.path()).unwrap().unwrap();
+    let segments = &manifest.segments;
+
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].file_name, "index-000.mkv");
+    assert_eq!(segments[0].start, 0.0);
+    assert!(segments[0].end.unwrap() >= 61.0);
+    assert_eq!(segments[0].size, Some(9));
+    assert_eq!(segments[1].file_name, "index-001.mkv");
+    assert!(segments[1].start >= segments[0].end.unwrap());
+    assert!(segments[1].end.is_some());
+    assert_eq!(segments[1].size, Some(7));
+    assert!(segments.iter().all(|x| !x.incomplete));
+}
+
+#[tokio::test]
+async fn rotates_by_size() {
+    let dir = TempDir::new().unwrap();
+
+    let mut output = output(
+        dir.path(),
+        Segmentation {
+            minutes: None,
+            // 8 bytes
+            gigabytes: Some(8.0 / 1024.0 / 1024.0 / 1024.0),
+        },
+    );
+
+    output
+        .write_segment(None, &Bytes::from_static(b"1234"))
+        .await
+        .unwrap();
+    wait_for_size(&dir.path().join("index-000.mkv"), 4).await;
+
+    // 아직 8 bytes가 되지 않음
+    output
+        .write_segment(None, &Bytes::from_static(b"5678"))
+        .await
+        .unwrap();
+    wait_for_size(&dir.path().join("index-000.mkv"), 8).await;
+
+    output
+        .write_segment(None, &Bytes::from_static(b"9"))
+        .await
+        .unwrap();
+    output.finish().await.unwrap();
+
+    assert_eq!(
+        fs::read(dir.path().join("index-000.mkv")).unwrap(),
+        b"12345678"
+    );
+    assert_eq!(fs::read(dir.path().join("index-001.mkv")).unwrap(), b"9");
+
+    let manifest = Manifest::read(dir.path()).unwrap().unwrap();
+
+    assert_eq!(
+        manifest
+            .segments
+            .iter()
+            .map(|x| (x.file_name.as_str(), x.size))
+            .collect::<Vec<_>>(),
+        [("index-000.mkv", Some(8)), ("index-001.mkv", Some(1))]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn kills_ffmpeg_that_does_not_finish() {
+    let dir = TempDir::new().unwrap();
+    let started_at = Instant::now();
+
+    let mut output = output_with(dir.path(), Segmentation::default(), hang);
+
+    output
+        .write_segment(None, &Bytes::from_static(b"a"))
+        .await
+        .unwrap();
+
+    // 시계가 멈춰 있으므로 timeout을 실제로 기다리지 않음
+    let status = output.finish().await.unwrap().unwrap();
+
+    // SIGKILL
+    assert_eq!(status.signal(), Some(9));
+    assert!(started_at.elapsed() < Duration::from_secs(5));
+
+    let manifest = Manifest::read(dir.path()).unwrap().unwrap();
+    let segments = &manifest.segments;
+
+    assert_eq!(segments.len(), 1);
+    assert_eq!(segments[0].file_name, "index.mkv");
+    // timeout만큼 기다린 뒤에 끝남
+    assert!(segments[0].end.unwrap() >= 10.0);
+    assert!(segments[0].incomplete);
+}
+
+#[tokio::test]
+async fn continues_numbering_from_manifest() {
+    let dir = TempDir::new().unwrap();
+
+    Manifest {
+        segments: vec![entry("index-000.mkv", 0, 0.0, Some(60.0))],
+    }
+    .write(dir.path())
+    .unwrap();
+
+    let mut output = output(
+        dir.path(),
+        Segmentation {
+            minutes: Some(1),
+            gigabytes: None,
+        },
+    );
+
+    output
+        .write_segment(None, &Bytes::from_static(b"a"))
+        .await
+        .unwrap();
+    output.finish().await.unwrap();
+
+    let manifest = Manifest::read(dir.path()).unwrap().unwrap();
+
+    assert_eq!(
+        manifest
+            .segments
+            .iter()
+            .map(|x| x.file_name.as_str())
+            .collect::<Vec<_>>(),
+        ["index-000.mkv", "index-001.mkv"]
+    );
+}
+
+#[test]
+fn infers_missing_end_times() {
+    let manifest = Manifest {
+        segments: vec![
+            // 다음 파일이 같은 part이면 다음 파일의 시작
+            entry("index-000.mkv", 0, 0.0, None),
+            // part의 마지막 파일이면 part의 끝
+            entry("index-001.mkv", 0, 60.0, None),
+            entry("part-1-000.mkv", 1, 100.0, Some(130.0)),
+            // 마지막 part
+            entry("part-1-001.mkv", 1, 130.0, None),
+        ],
+    };
+
+    assert_eq!(
+        manifest.files(&[secs(90), secs(60)]),
+        [
+            ("index-000.mkv".to_owned(), secs(0), secs(60)),
+            ("index-001.mkv".to_owned(), secs(60), secs(90)),
+            ("part-1-000.mkv".to_owned(), secs(100), secs(130)),
+            ("part-1-001.mkv".to_owned(), secs(130), secs(150)),
+        ]
+    );
+}
+
+#[test]
+fn end_is_never_before_start() {
+    let manifest = Manifest {
+        segments: vec![
+            entry("index-000.mkv", 0, -1.0, Some(-0.5)),
+            // part 길이보다 늦게 시작함
+            entry("index-001.mkv", 0, 120.0, None),
+        ],
+    };
+
+    assert_eq!(
+        manifest.files(&[secs(90)]),
+        [
+            ("index-000.mkv".to_owned(), secs(0), secs(0)),
+            ("index-001.mkv".to_owned(), secs(120), secs(120)),
+        ]
+    );
+}
+
+#[test]
+fn chapters_are_offset_per_segment() {
+    let manifest = Manifest {
+        segments: vec![
+            entry("index-000.mkv", 0, 0.0, Some(60.0)),
+            entry("index-001.mkv", 0, 60.0, Some(120.0)),
+            entry("index-002.mkv", 0, 120.0, None),
+        ],
+    };
+    let chapters = [chapter(0, "a"), chapter(30, "b"), chapter(90, "c")];
+
+    let sliced = manifest
+        .files(&[secs(150)])
+        .into_iter()
+        .map(|(file_name, start, end)| {
+            let chapters = chapter::slice(&chapters, Time::from(start), Time::from(end))
+                .into_iter()
+                .map(|Chapter(time, live_status)| (time.as_secs(), live_status.live_title))
+                .collect::<Vec<_>>();
+
+            (file_name, chapters)
+        })
+        .collect::<Vec<_>>();
+
+    let chapters = |x: &[(u64, &str)]| {
+        x.iter()
+            .map(|(secs, title)| (*secs, (*title).to_owned()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        sliced,
+        [
+            ("index-000.mkv".to_owned(), chapters(&[(0, "a"), (30, "b")])),
+            // 이전 파일에서 시작한 챕터는 0에서 시작함
+            ("index-001.mkv".to_owned(), chapters(&[(0, "b"), (30, "c")])),
+            ("index-002.mkv".to_owned(), chapters(&[(0, "c")])),
+        ]
+    );
+}
@@ -0,0 +1,530 @@
+//! 녹화 목록
+//!
+//! `<path>/<channel_name>/<session>/` 녹화 폴더를 읽어서 SQLite에 저장함.
+//! 녹화 폴더가 원본이므로 DB를 지우거나 스키마가 바뀌면 다시 만듦
+
+pub mod scan;
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use encoder::report::Problem;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tokio::time::{interval, MissedTickBehavior};
+
+use self::scan::Session;
+
+/// 바뀌면 DB를 지우고 다시 만듦
+const SCHEMA_VERSION: i32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE recordings (
+    id TEXT PRIMARY KEY,
+    channel_name TEXT NOT NULL,
+    started_at INTEGER,
+    local_date TEXT,
+    fingerprint TEXT NOT NULL,
+    detail TEXT NOT NULL
+);
+CREATE INDEX recordings_started_at ON recordings (channel_name, started_at);
+CREATE TABLE chapters (
+    recording_id TEXT NOT NULL REFERENCES recordings (id) ON DELETE CASCADE,
+    start INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    category TEXT
+);
+CREATE INDEX chapters_recording_id ON chapters (recording_id);
+";
+
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 500;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("io: {0}")]
+    Io(#[from] io::Error),
+    #[error("sqlite: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("join: {0}")]
+    Join(#[from] tokio::task::JoinError),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterInfo {
+    /// seconds
+    pub start: u64,
+    /// seconds
+    pub end: u64,
+    pub title: String,
+    pub category: Option<String>,
+    pub adult: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamInfo {
+    pub index: u32,
+    /// `video`, `audio` ...
+    pub codec_type: String,
+    pub codec_name: Option<String>,
+    pub profile: Option<String>,
+    pub bit_rate: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// `60/1`
+    pub frame_rate: Option<String>,
+    pub pix_fmt: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+}
+
+/// ffprobe로 읽은 정보
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeInfo {
+    /// `matroska,webm`
+    pub format_name: Option<String>,
+    /// seconds
+    pub duration: Option<f64>,
+    /// bits/s
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileInfo {
+    pub file_name: String,
+    pub part: usize,
+    /// 세션이 시작된 시간 기준 (seconds), manifest가 없으면 없음
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    /// bytes, 파일이 없으면 없음
+    pub size: Option<u64>,
+    /// 녹화 중이면 검사하지 않음
+    pub probe: Option<ProbeInfo>,
+    pub probe_error: Option<String>,
+    /// `report.json` 에 기록된 문제
+    pub problems: Vec<Problem>,
+    pub repaired: bool,
+}
+
+/// `GET /recordings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    /// `<channel_name>:<session>`
+    pub id: String,
+    pub channel_name: String,
+    /// 녹화 폴더 이름
+    pub session: String,
+    pub started_at: Option<DateTime<FixedOffset>>,
+    /// seconds
+    pub duration: Option<f64>,
+    /// 녹화 파일들의 크기 합 (bytes)
+    pub size: u64,
+    /// 첫 번째 챕터의 제목
+    pub title: Option<String>,
+    pub categories: Vec<String>,
+    /// 챕터를 적용함, 녹화 중이면 false
+    pub finished: bool,
+    /// `report.json` 에 문제가 없음, 검사하지 않았으면 없음
+    pub ok: Option<bool>,
+    pub indexed_at: DateTime<Utc>,
+}
+
+/// `GET /recordings/:id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingDetail {
+    #[serde(flatten)]
+    pub recording: Recording,
+    pub directory: String,
+    pub parts: usize,
+    pub chapters: Vec<ChapterInfo>,
+    pub files: Vec<FileInfo>,
+    /// `chat.jsonl` 이 있음
+    pub chat: bool,
+}
+
+/// `2024-03-01` 은 녹화한 곳의 날짜로 비교함
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateBound {
+    Date(NaiveDate),
+    Time(DateTime<FixedOffset>),
+}
+
+impl FromStr for DateBound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(Self::Date(date));
+        }
+
+        DateTime::parse_from_rfc3339(s)
+            .map(Self::Time)
+            .map_err(|_| format!("{s:?} is neither YYYY-MM-DD nor RFC 3339"))
+    }
+}
+
+impl<'de> Deserialize<'de> for DateBound {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// `GET /recordings?channel=...&from=2024-03-01&to=2024-03-31&title=...&category=...`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Query {
+    pub channel: Option<String>,
+    /// 이 시간 이후에 시작한 녹화
+    pub from: Option<DateBound>,
+    /// 날짜면 그 날 녹화도 포함함
+    pub to: Option<DateBound>,
+    /// 챕터 제목에 포함된 글자
+    pub title: Option<String>,
+    /// 챕터 카테고리에 포함된 글자
+    pub category: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// `GET /recordings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingList {
+    /// `limit`, `offset` 을 적용하기 전의 개수
+    pub total: u64,
+    pub recordings: Vec<Recording>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SyncReport {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    /// 읽지 못한 녹화 폴더
+    pub failed: usize,
+}
+
+#[derive(Clone)]
+pub struct Catalog {
+    connection: Arc<Mutex<Connection>>,
+    /// 녹화 폴더들이 있는 곳 (`ELLIER_PATH`)
+    root: PathBuf,
+    timezone: FixedOffset,
+}
+
+impl Catalog {
+    /// `database` 의 스키마가 다르면 지우고 새로 만듦, 다음 `sync` 에서 다시 채움
+    pub fn open(
+        database: impl AsRef<Path>,
+        root: impl Into<PathBuf>,
+        timezone: FixedOffset,
+    ) -> Result<Self, Error> {
+        let connection = Connection::open(database)?;
+
+        connection.pragma_update(None, "foreign_keys", true)?;
+
+        let version: i32 = connection.pragma_query_value(None, "user_version", |x| x.get(0))?;
+
+        if version != SCHEMA_VERSION {
+            connection
+                .execute_batch("DROP TABLE IF EXISTS chapters; DROP TABLE IF EXISTS recordings;")?;
+            connection.execute_batch(SCHEMA)?;
+            connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            root: root.into(),
+            timezone,
+        })
+    }
+
+    async fn blocking<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> Result<T, Error> + Send + 'static,
+    {
+        let this = self.clone();
+
+        tokio::task::spawn_blocking(move || f(&this)).await?
+    }
+
+    /// 바뀐 녹화 폴더만 다시 읽고, 없어진 녹화는 지움
+    pub async fn sync(&self) -> Result<SyncReport, Error> {
+        self.blocking(Self::sync_blocking).await
+    }
+
+    /// DB를 비우고 녹화 폴더를 모두 다시 읽음
+    pub async fn rebuild(&self) -> Result<SyncReport, Error> {
+        self.blocking(|this| {
+            this.connection
+                .lock()
+                .unwrap()
+                .execute("DELETE FROM recordings", [])?;
+
+            this.sync_blocking()
+        })
+        .await
+    }
+
+    fn sync_blocking(&self) -> Result<SyncReport, Error> {
+        let sessions = match scan::sessions(&self.root) {
+            Ok(sessions) => sessions,
+            // 아직 녹화한 적이 없음
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut indexed = self.fingerprints()?;
+        let mut report = SyncReport::default();
+
+        for session in &sessions {
+            let id = session.id();
+            let previous = indexed.remove(&id);
+
+            let fingerprint = match scan::fingerprint(&session.directory) {
+                Ok(fingerprint) => fingerprint,
+                Err(err) => {
+                    eprintln!(
+                        "[{}] catalog: {}: {err}",
+                        session.channel_name, session.name
+                    );
+                    report.failed += 1;
+                    continue;
+                }
+            };
+
+            if previous.as_ref() == Some(&fingerprint) {
+                report.unchanged += 1;
+                continue;
+            }
+
+            // ffprobe를 실행하는 동안 DB를 잠그지 않음
+            match scan::read(session, self.timezone) {
+                Ok(detail) => {
+                    self.upsert(session, &fingerprint, &detail)?;
+                    report.indexed += 1;
+                }
+                Err(err) => {
+                    eprintln!(
+                        "[{}] catalog: {}: {err}",
+                        session.channel_name, session.name
+                    );
+                    report.failed += 1;
+                }
+            }
+        }
+
+        // 남은 것은 지워진 녹화 폴더
+        let connection = self.connection.lock().unwrap();
+
+        for id in indexed.keys() {
+            connection.execute("DELETE FROM recordings WHERE id = ?1", [id])?;
+            report.removed += 1;
+        }
+
+        Ok(report)
+    }
+
+    /// returns id -> fingerprint
+    fn fingerprints(&self) -> Result<HashMap<String, String>, Error> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT id, fingerprint FROM recordings")?;
+
+        let rows = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(rows)
+    }
+
+    fn upsert(
+        &self,
+        session: &Session,
+        fingerprint: &str,
+        detail: &RecordingDetail,
+    ) -> Result<(), Error> {
+        let started_at = detail.recording.started_at;
+        let json = serde_json::to_string(detail)?;
+
+        let mut connection = self.connection.lock().unwrap();
+        let tx = connection.transaction()?;
+
+        tx.execute(
+            "DELETE FROM recordings WHERE id = ?1",
+            [&detail.recording.id],
+        )?;
+        tx.execute(
+            "INSERT INTO recordings (id, channel_name, started_at, local_date, fingerprint, detail)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                detail.recording.id,
+                session.channel_name,
+                started_at.map(|x| x.timestamp()),
+                started_at.map(|x| x.format("%Y-%m-%d").to_string()),
+                fingerprint,
+                json,
+            ],
+        )?;
+
+        for chapter in &detail.chapters {
+            tx.execute(
+                "INSERT INTO chapters (recording_id, start, title, category) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    detail.recording.id,
+                    chapter.start,
+                    chapter.title,
+                    chapter.category
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// 최근에 시작한 녹화부터
+    pub async fn list(&self, query: Query) -> Result<RecordingList, Error> {
+        self.blocking(move |this| this.list_blocking(&query)).await
+    }
+
+    fn list_blocking(&self, query: &Query) -> Result<RecordingList, Error> {
+        let mut conditions = Vec::<String>::new();
+        let mut values = Vec::<rusqlite::types::Value>::new();
+
+        if let Some(channel) = &query.channel {
+            conditions.push("channel_name = ?".to_owned());
+            values.push(channel.clone().into());
+        }
+
+        match query.from {
+            Some(DateBound::Date(date)) => {
+                conditions.push("local_date >= ?".to_owned());
+                values.push(date.format("%Y-%m-%d").to_string().into());
+            }
+            Some(DateBound::Time(time)) => {
+                conditions.push("started_at >= ?".to_owned());
+                values.push(time.timestamp().into());
+            }
+            None => {}
+        }
+
+        match query.to {
+            Some(DateBound::Date(date)) => {
+                conditions.push("local_date <= ?".to_owned());
+                values.push(date.format("%Y-%m-%d").to_string().into());
+            }
+            Some(DateBound::Time(time)) => {
+                conditions.push("started_at < ?".to_owned());
+                values.push(time.timestamp().into());
+            }
+            None => {}
+        }
+
+        // 챕터 중 하나라도 포함하면 찾음
+        for (column, text) in [("title", &query.title), ("category", &query.category)] {
+            let Some(text) = text.as_deref().filter(|x| !x.is_empty()) else {
+                continue;
+            };
+
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM chapters WHERE recording_id = recordings.id AND {column} LIKE ? ESCAPE '\\')"
+            ));
+            values.push(format!("%{}%", escape_like(text)).into());
+        }
+
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let connection = self.connection.lock().unwrap();
+
+        let total: u64 = connection.query_row(
+            &format!("SELECT COUNT(*) FROM recordings {filter}"),
+            params_from_iter(&values),
+            |row| row.get(0),
+        )?;
+
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        let offset = query.offset.unwrap_or_default();
+
+        let mut statement = connection.prepare(&format!(
+            "SELECT detail FROM recordings {filter}
+            ORDER BY started_at IS NULL, started_at DESC, id DESC
+            LIMIT {limit} OFFSET {offset}"
+        ))?;
+
+        let recordings = statement
+            .query_map(params_from_iter(&values), |row| row.get::<_, String>(0))?
+            .map(|json| {
+                let detail = serde_json::from_str::<RecordingDetail>(&json?)?;
+                Ok(detail.recording)
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(RecordingList { total, recordings })
+    }
+
+    pub async fn get(&self, id: String) -> Result<Option<RecordingDetail>, Error> {
+        self.blocking(move |this| {
+            let json = this
+                .connection
+                .lock()
+                .unwrap()
+                .query_row(
+                    "SELECT detail FROM recordings WHERE id = ?1",
+                    [&id],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?;
+
+            Ok(json.map(|x| serde_json::from_str(&x)).transpose()?)
+        })
+        .await
+    }
+
+    /// `every` 마다 녹화 폴더를 다시 읽음
+    pub async fn run(self, every: Duration) {
+        let mut ticker = interval(every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            match self.sync().await {
+                Ok(report) if report.indexed > 0 || report.removed > 0 || report.failed > 0 => {
+                    println!(
+                        "catalog: indexed {}, removed {}, failed {}",
+                        report.indexed, report.removed, report.failed
+                    );
+                }
+                Ok(_) => {}
+                Err(err) => eprintln!("catalog: {err}"),
+            }
+        }
+    }
+}
+
+/// LIKE 의 `%`, `_` 를 글자 그대로 찾음
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
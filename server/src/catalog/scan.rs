@@ -0,0 +1,384 @@
+//! 녹화 폴더 하나를 읽어서 `RecordingDetail` 로 만듦
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use chrono::{FixedOffset, NaiveDateTime, TimeZone, Utc};
+use encoder::{
+    chapter::{journal, with_end_times, Chapter, MatroskaChapters},
+    chat,
+    ffprobe::{ffprobe, Ffprobe, FfprobeStreamKind},
+    report::SessionReport,
+    segment::Manifest,
+    time::Time,
+};
+
+use super::{ChapterInfo, FileInfo, ProbeInfo, Recording, RecordingDetail, StreamInfo};
+
+/// 저널이 없는 이전 녹화는 폴더 이름에서 시작 시간을 읽음
+const NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+const MEDIA_EXTENSIONS: [&str; 3] = ["mkv", "mp4", "ts"];
+
+/// `<root>/<channel_name>/<name>`
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub channel_name: String,
+    pub name: String,
+    pub directory: PathBuf,
+}
+
+impl Session {
+    /// `/recordings/:id` 에 사용함
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.channel_name, self.name)
+    }
+}
+
+/// 녹화 파일이나 챕터가 있는 폴더만 녹화로 봄
+pub fn sessions(root: &Path) -> io::Result<Vec<Session>> {
+    let mut sessions = Vec::new();
+
+    for channel in fs::read_dir(root)? {
+        let channel = channel?.path();
+
+        if !channel.is_dir() {
+            continue;
+        }
+
+        let Some(channel_name) = file_name(&channel) else {
+            continue;
+        };
+
+        for entry in fs::read_dir(&channel)? {
+            let directory = entry?.path();
+
+            if !directory.is_dir() || !is_session(&directory) {
+                continue;
+            }
+
+            let Some(name) = file_name(&directory) else {
+                continue;
+            };
+
+            sessions.push(Session {
+                channel_name: channel_name.clone(),
+                name,
+                directory,
+            });
+        }
+    }
+
+    Ok(sessions)
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()?.to_str().map(ToOwned::to_owned)
+}
+
+fn is_session(directory: &Path) -> bool {
+    directory.join(journal::FILE_NAME).exists()
+        || directory.join("metadata.xml").exists()
+        || media_files(directory).is_ok_and(|x| !x.is_empty())
+}
+
+/// `index.mkv`, `index-000.mkv`, `part-1.mkv` ...
+fn media_files(directory: &Path) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let Some(name) = entry?.file_name().to_str().map(ToOwned::to_owned) else {
+            continue;
+        };
+
+        let is_media = name.rsplit_once('.').is_some_and(|(stem, ext)| {
+            (stem.starts_with("index") || stem.starts_with("part-"))
+                && MEDIA_EXTENSIONS.contains(&ext)
+        });
+
+        if is_media {
+            files.push(name);
+        }
+    }
+
+    files.sort_by_key(|x| (part_of(x), x.clone()));
+
+    Ok(files)
+}
+
+/// `part-2-001.mkv` -> 2
+fn part_of(file_name: &str) -> usize {
+    file_name
+        .strip_prefix("part-")
+        .and_then(|x| x.split(['-', '.']).next())
+        .and_then(|x| x.parse().ok())
+        .unwrap_or_default()
+}
+
+/// 폴더 안의 파일 이름, 크기, 수정 시간
+///
+/// 바뀌지 않았으면 다시 읽지 않음
+pub fn fingerprint(directory: &Path) -> io::Result<String> {
+    let mut entries = BTreeMap::new();
+
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        entries.insert(
+            entry.file_name().to_string_lossy().into_owned(),
+            (metadata.len(), modified.as_millis()),
+        );
+    }
+
+    Ok(entries
+        .iter()
+        .map(|(name, (len, modified))| format!("{name}:{len}:{modified}"))
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// `timezone`: 저널이 없어서 폴더 이름으로 시작 시간을 정할 때 사용함
+///
+/// 녹화 중인 파일은 ffprobe로 검사하지 않음
+pub fn read(session: &Session, timezone: FixedOffset) -> io::Result<RecordingDetail> {
+    let directory = &session.directory;
+
+    let replayed = journal::replay(directory)?;
+    let manifest = Manifest::read(directory)?;
+    let report = SessionReport::read(directory)?;
+
+    let finished = replayed.as_ref().is_none_or(|x| x.applied);
+
+    let started_at = match &replayed {
+        Some(replayed) => replayed.started_at,
+        None => NaiveDateTime::parse_from_str(&session.name, NAME_FORMAT)
+            .ok()
+            .and_then(|x| timezone.from_local_datetime(&x).single()),
+    };
+
+    let entries = match &manifest {
+        Some(manifest) => manifest
+            .segments
+            .iter()
+            .map(|x| (x.file_name.clone(), x.part, Some(x.start), x.end))
+            .collect(),
+        None => media_files(directory)?
+            .into_iter()
+            .map(|x| (part_of(&x), x))
+            .map(|(part, file_name)| (file_name, part, None, None))
+            .collect::<Vec<_>>(),
+    };
+
+    let mut files = Vec::with_capacity(entries.len());
+
+    for (file_name, part, start, end) in entries {
+        let path = directory.join(&file_name);
+
+        let size = match fs::metadata(&path) {
+            Ok(metadata) => Some(metadata.len()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+
+        let (probe, probe_error) = match (size, finished) {
+            (Some(_), true) => match ffprobe(&path) {
+                Ok(probe) => (Some(ProbeInfo::from(&probe)), None),
+                Err(err) => (None, Some(err.to_string())),
+            },
+            _ => (None, None),
+        };
+
+        let file_report = report
+            .as_ref()
+            .and_then(|x| x.files.iter().find(|x| x.file_name == file_name));
+
+        files.push(FileInfo {
+            file_name,
+            part,
+            start,
+            end,
+            size,
+            probe,
+            probe_error,
+            problems: file_report.map(|x| x.problems.clone()).unwrap_or_default(),
+            repaired: file_report.is_some_and(|x| x.repaired),
+        });
+    }
+
+    let duration = duration(&files);
+
+    let chapters = match &replayed {
+        Some(replayed) => {
+            let end = duration
+                .map(Time::from)
+                .or_else(|| {
+                    replayed
+                        .chapters
+                        .iter()
+                        .map(|x| x.0)
+                        .max_by_key(Time::as_secs)
+                })
+                .unwrap_or_default();
+
+            chapters(&replayed.chapters, end)
+        }
+        None => match fs::read_to_string(directory.join("metadata.xml")) {
+            Ok(xml) => MatroskaChapters::from_xml(&xml)
+                .map(|x| x.atoms)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|atom| {
+                    let (title, category) = atom.split_title();
+
+                    ChapterInfo {
+                        start: atom.start.as_secs(),
+                        end: atom.end.as_secs(),
+                        title,
+                        category,
+                        adult: false,
+                    }
+                })
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        },
+    };
+
+    let mut categories = Vec::<String>::new();
+
+    for category in chapters.iter().filter_map(|x| x.category.as_ref()) {
+        if !categories.contains(category) {
+            categories.push(category.clone());
+        }
+    }
+
+    Ok(RecordingDetail {
+        recording: Recording {
+            id: session.id(),
+            channel_name: session.channel_name.clone(),
+            session: session.name.clone(),
+            started_at,
+            duration: duration.map(|x| x.as_secs_f64()),
+            size: files.iter().filter_map(|x| x.size).sum(),
+            title: chapters.first().map(|x| x.title.clone()),
+            categories,
+            finished,
+            ok: report.as_ref().map(SessionReport::is_ok),
+            indexed_at: Utc::now(),
+        },
+        directory: directory.to_string_lossy().into_owned(),
+        parts: replayed.as_ref().map_or(1, |x| x.parts.len() + 1),
+        chapters,
+        files,
+        chat: directory.join(chat::FILE_NAME).exists(),
+    })
+}
+
+/// 파일마다 ffprobe로 읽은 길이, 없으면 manifest에 기록된 길이
+///
+/// manifest가 있으면 마지막 파일이 끝난 시간, 없으면 길이의 합
+fn duration(files: &[FileInfo]) -> Option<Duration> {
+    let mut total = None::<f64>;
+
+    for file in files {
+        let length = file
+            .probe
+            .as_ref()
+            .and_then(|x| x.duration)
+            .or_else(|| Some(file.end? - file.start?));
+
+        let Some(length) = length.filter(|x| x.is_finite() && *x >= 0.0) else {
+            continue;
+        };
+
+        total = Some(match file.start {
+            Some(start) => total.unwrap_or_default().max(start.max(0.0) + length),
+            None => total.unwrap_or_default() + length,
+        });
+    }
+
+    total.map(Duration::from_secs_f64)
+}
+
+fn chapters(chapters: &[Chapter], end: Time) -> Vec<ChapterInfo> {
+    with_end_times(chapters, end)
+        .into_iter()
+        .map(|(Chapter(start, live_status), end)| ChapterInfo {
+            start: start.as_secs(),
+            end: end.as_secs(),
+            title: live_status.live_title,
+            category: live_status.live_category,
+            adult: live_status.adult,
+        })
+        .collect()
+}
+
+impl From<&Ffprobe> for ProbeInfo {
+    fn from(probe: &Ffprobe) -> Self {
+        let format = probe.format.as_ref();
+
+        Self {
+            format_name: format.map(|x| x.format_name.clone()),
+            duration: probe.duration().map(|x| x.as_secs_f64()),
+            bit_rate: format.and_then(|x| x.bit_rate),
+            streams: probe
+                .streams
+                .iter()
+                .map(|stream| {
+                    let mut info = StreamInfo {
+                        index: stream.index,
+                        codec_type: String::new(),
+                        codec_name: stream.codec_name.clone(),
+                        profile: stream.profile.clone(),
+                        bit_rate: stream.bit_rate,
+                        width: None,
+                        height: None,
+                        frame_rate: None,
+                        pix_fmt: None,
+                        sample_rate: None,
+                        channels: None,
+                        channel_layout: None,
+                    };
+
+                    info.codec_type = match &stream.kind {
+                        FfprobeStreamKind::Video(video) => {
+                            info.width = video.width;
+                            info.height = video.height;
+                            info.frame_rate = video.avg_frame_rate.clone();
+                            info.pix_fmt = video.pix_fmt.clone();
+                            "video"
+                        }
+                        FfprobeStreamKind::Audio(audio) => {
+                            info.sample_rate = audio.sample_rate;
+                            info.channels = audio.channels;
+                            info.channel_layout = audio.channel_layout.clone();
+                            "audio"
+                        }
+                        FfprobeStreamKind::Subtitle => "subtitle",
+                        FfprobeStreamKind::Data => "data",
+                        FfprobeStreamKind::Attachment => "attachment",
+                        FfprobeStreamKind::Unknown => "unknown",
+                    }
+                    .to_owned();
+
+                    info
+                })
+                .collect(),
+        }
+    }
+}
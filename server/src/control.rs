@@ -0,0 +1,250 @@
+//! 인코더 등록과 녹화 상태
+//!
+//! 인코더는 시작할 때 등록하고, 주기적으로 채널마다 녹화 상태를 보냄.
+//! 마지막으로 받은 상태만 메모리에 두므로 서버가 재시작되면 인코더가 다시 등록함
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingState {
+    Idle,
+    Recording,
+    /// 방송이 끊겨서 다시 이어지기를 기다림
+    Interrupted,
+    /// 챕터를 적용하는 중
+    Finalizing,
+}
+
+impl RecordingState {
+    /// 여러 인코더가 같은 채널을 보고하면 녹화에 가까운 쪽을 보여줌
+    fn rank(self) -> u8 {
+        match self {
+            RecordingState::Recording => 3,
+            RecordingState::Finalizing => 2,
+            RecordingState::Interrupted => 1,
+            RecordingState::Idle => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelInfo {
+    pub channel_id: String,
+    pub channel_name: String,
+}
+
+/// `POST /encoders/register`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Register {
+    /// 쿠버네티스 pod 이름
+    pub pod: String,
+    pub version: String,
+    pub channels: Vec<ChannelInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelReport {
+    pub channel_id: String,
+    pub channel_name: String,
+    pub state: RecordingState,
+    pub live_id: Option<String>,
+    pub title: Option<String>,
+    pub category: Option<String>,
+    /// 녹화된 길이(초)
+    pub elapsed_secs: u64,
+    /// `01:02:03`
+    pub elapsed: String,
+    pub bytes_written: u64,
+}
+
+impl ChannelReport {
+    /// 등록만 하고 아직 상태를 보내지 않은 채널
+    fn idle(channel: &ChannelInfo) -> Self {
+        Self {
+            channel_id: channel.channel_id.clone(),
+            channel_name: channel.channel_name.clone(),
+            state: RecordingState::Idle,
+            live_id: None,
+            title: None,
+            category: None,
+            elapsed_secs: 0,
+            elapsed: "00:00:00".to_owned(),
+            bytes_written: 0,
+        }
+    }
+}
+
+/// `POST /encoders/heartbeat`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Heartbeat {
+    /// 등록할 때 보낸 pod 이름
+    pub pod: String,
+    pub channels: Vec<ChannelReport>,
+}
+
+/// `GET /encoders`
+#[derive(Debug, Clone, Serialize)]
+pub struct EncoderView {
+    /// `ENCODER_TOKENS` 에 등록한 이름
+    pub name: String,
+    pub pod: String,
+    pub version: String,
+    pub registered_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// 제한 시간 안에 상태를 보냈는지
+    pub online: bool,
+    pub channels: Vec<ChannelReport>,
+}
+
+/// `GET /channels/:id/status` 에서 채널을 녹화하는 인코더 하나
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelEncoder {
+    pub encoder: String,
+    pub pod: String,
+    pub online: bool,
+    pub last_seen: DateTime<Utc>,
+    #[serde(flatten)]
+    pub report: ChannelReport,
+}
+
+/// `GET /channels/:id/status`
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelStatus {
+    pub channel_id: String,
+    /// 온라인인 인코더 중 녹화에 가장 가까운 상태, 없으면 idle
+    pub state: RecordingState,
+    /// `state` 를 보고한 인코더
+    pub current: Option<ChannelEncoder>,
+    pub encoders: Vec<ChannelEncoder>,
+}
+
+struct Encoder {
+    version: String,
+    registered_at: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    channels: Vec<ChannelReport>,
+}
+
+/// 같은 토큰을 여러 pod가 사용할 수 있으므로 (이름, pod)로 구분함
+#[derive(Clone)]
+pub struct Registry {
+    encoders: Arc<RwLock<BTreeMap<(String, String), Encoder>>>,
+    /// 이 시간 동안 상태를 보내지 않으면 오프라인으로 봄
+    timeout: Duration,
+}
+
+impl Registry {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            encoders: Default::default(),
+            timeout,
+        }
+    }
+
+    fn is_online(&self, last_seen: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        (now - last_seen)
+            .to_std()
+            .map_or(true, |elapsed| elapsed <= self.timeout)
+    }
+
+    /// 같은 pod가 다시 등록하면 이전 정보를 바꿈 (pod 재시작)
+    ///
+    /// 같은 이름의 오프라인인 pod는 지움, 다시 보고하면 404를 받고 다시 등록함
+    pub async fn register(&self, name: &str, register: Register) {
+        let now = Utc::now();
+
+        let encoder = Encoder {
+            channels: register.channels.iter().map(ChannelReport::idle).collect(),
+            version: register.version,
+            registered_at: now,
+            last_seen: now,
+        };
+
+        let mut encoders = self.encoders.write().await;
+
+        encoders.retain(|(x, _), encoder| x != name || self.is_online(encoder.last_seen, now));
+        encoders.insert((name.to_owned(), register.pod), encoder);
+    }
+
+    /// return: 등록된 인코더인지
+    pub async fn heartbeat(&self, name: &str, heartbeat: Heartbeat) -> bool {
+        let mut encoders = self.encoders.write().await;
+
+        let Some(encoder) = encoders.get_mut(&(name.to_owned(), heartbeat.pod)) else {
+            return false;
+        };
+
+        encoder.last_seen = Utc::now();
+        encoder.channels = heartbeat.channels;
+
+        true
+    }
+
+    pub async fn encoders(&self) -> Vec<EncoderView> {
+        let now = Utc::now();
+
+        self.encoders
+            .read()
+            .await
+            .iter()
+            .map(|((name, pod), encoder)| EncoderView {
+                name: name.clone(),
+                pod: pod.clone(),
+                version: encoder.version.clone(),
+                registered_at: encoder.registered_at,
+                last_seen: encoder.last_seen,
+                online: self.is_online(encoder.last_seen, now),
+                channels: encoder.channels.clone(),
+            })
+            .collect()
+    }
+
+    /// return: 이 채널을 보고한 인코더가 없으면 None
+    pub async fn channel(&self, channel_id: &str) -> Option<ChannelStatus> {
+        let now = Utc::now();
+
+        let encoders = self
+            .encoders
+            .read()
+            .await
+            .iter()
+            .flat_map(|((name, pod), encoder)| {
+                encoder
+                    .channels
+                    .iter()
+                    .filter(|report| report.channel_id == channel_id)
+                    .map(|report| ChannelEncoder {
+                        encoder: name.clone(),
+                        pod: pod.clone(),
+                        online: self.is_online(encoder.last_seen, now),
+                        last_seen: encoder.last_seen,
+                        report: report.clone(),
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        if encoders.is_empty() {
+            return None;
+        }
+
+        let current = encoders
+            .iter()
+            .filter(|x| x.online)
+            .max_by_key(|x| (x.report.state.rank(), x.last_seen))
+            .cloned();
+
+        Some(ChannelStatus {
+            channel_id: channel_id.to_owned(),
+            state: current
+                .as_ref()
+                .map_or(RecordingState::Idle, |x| x.report.state),
+            current,
+            encoders,
+        })
+    }
+}
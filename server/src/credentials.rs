@@ -0,0 +1,168 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::watch, time::sleep};
+
+/// chzzk.naver.com 쿠키
+///
+/// 쿠키가 로그에 남지 않도록 Debug는 구현하지 않음
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChzzkAuth {
+    pub nid_ses: String,
+    pub nid_aut: String,
+    pub nid_jkl: String,
+}
+
+impl ChzzkAuth {
+    /// 쿠키 이름으로 된 파일들 (`NID_SES`, `NID_AUT`, `NID_JKL`)
+    ///
+    /// 쿠버네티스 Secret을 볼륨으로 마운트한 폴더
+    pub async fn read_dir(directory: &Path) -> io::Result<Self> {
+        let read = |name: &'static str| async move {
+            let value = fs::read_to_string(directory.join(name)).await?;
+            Ok::<_, io::Error>(value.trim().to_owned())
+        };
+
+        Ok(Self {
+            nid_ses: read("NID_SES").await?,
+            nid_aut: read("NID_AUT").await?,
+            nid_jkl: read("NID_JKL").await?,
+        })
+    }
+
+    /// 비어있는 쿠키가 있으면 이름을 반환함
+    pub fn missing(&self) -> Option<&'static str> {
+        [
+            ("nid_ses", &self.nid_ses),
+            ("nid_aut", &self.nid_aut),
+            ("nid_jkl", &self.nid_jkl),
+        ]
+        .into_iter()
+        .find(|(_, value)| value.trim().is_empty())
+        .map(|(name, _)| name)
+    }
+}
+
+/// 인증 정보를 어디서 받았는지
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    Env,
+    File,
+    Admin,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Version {
+    /// 바뀔 때마다 1씩 늘어남
+    pub version: u64,
+    pub updated_at: DateTime<Utc>,
+    pub origin: Origin,
+}
+
+/// 모든 요청이 공유하는 인증 정보
+///
+/// 쿠키와 버전을 함께 바꾸므로 요청은 항상 한 버전의 쿠키만 봄
+#[derive(Clone)]
+pub struct Credentials(Arc<watch::Sender<(ChzzkAuth, Version)>>);
+
+impl Credentials {
+    pub fn new(auth: ChzzkAuth, origin: Origin) -> Self {
+        let version = Version {
+            version: 1,
+            updated_at: Utc::now(),
+            origin,
+        };
+
+        let (sender, _) = watch::channel((auth, version));
+
+        Self(Arc::new(sender))
+    }
+
+    pub fn get(&self) -> (ChzzkAuth, Version) {
+        self.0.borrow().clone()
+    }
+
+    pub fn version(&self) -> Version {
+        self.0.borrow().1.clone()
+    }
+
+    /// 인증 정보가 바뀌면 알려줌
+    pub fn subscribe(&self) -> watch::Receiver<(ChzzkAuth, Version)> {
+        self.0.subscribe()
+    }
+
+    /// 쿠키가 바뀌었을 때만 버전을 올림
+    ///
+    /// returns (현재 버전, 바뀌었는지)
+    pub fn replace(&self, auth: ChzzkAuth, origin: Origin) -> (Version, bool) {
+        let mut version = None;
+
+        self.0.send_if_modified(|current| {
+            if current.0 == auth {
+                return false;
+            }
+
+            current.1 = Version {
+                version: current.1.version + 1,
+                updated_at: Utc::now(),
+                origin,
+            };
+            current.0 = auth;
+            version = Some(current.1.clone());
+
+            true
+        });
+
+        let Some(version) = version else {
+            return (self.version(), false);
+        };
+
+        println!(
+            "chzzk-auth: updated to version {} by {:?}",
+            version.version, origin
+        );
+
+        (version, true)
+    }
+
+    /// 마운트한 Secret이 바뀌면 인증 정보를 바꿈
+    ///
+    /// Secret이 바뀌어도 파일 이벤트가 오지 않는 경우가 있으므로 주기적으로 읽어서 비교함.
+    /// 파일이 바뀌었을 때만 적용하므로 admin으로 바꾼 값을 되돌리지 않음.
+    /// 처음에는 `directory` 에서 읽은 현재 값과 비교하므로 시작하는 사이에 바뀐 파일도 적용함
+    pub async fn watch(self, directory: PathBuf, interval: Duration) {
+        let mut last = Some(self.get().0);
+
+        loop {
+            sleep(interval).await;
+
+            let auth = match ChzzkAuth::read_dir(&directory).await {
+                Ok(auth) => auth,
+                Err(err) => {
+                    eprintln!("chzzk-auth: can't read {directory:?}: {err}");
+                    continue;
+                }
+            };
+
+            if last.as_ref() == Some(&auth) {
+                continue;
+            }
+
+            match auth.missing() {
+                Some(name) => eprintln!("chzzk-auth: {name} is empty in {directory:?}"),
+                None => {
+                    self.replace(auth.clone(), Origin::File);
+                }
+            }
+
+            last = Some(auth);
+        }
+    }
+}
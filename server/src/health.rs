@@ -0,0 +1,259 @@
+//! 쿠키가 아직 유효한지 주기적으로 확인함
+//!
+//! 쿠키가 만료되면 성인 방송을 녹화하지 못하므로 방송을 놓치기 전에 알려줌
+
+use std::{sync::Arc, time::Duration};
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::RwLock, time::sleep};
+
+use crate::credentials::{ChzzkAuth, Credentials};
+
+pub const USER_STATUS_URL: &str = "https://comm-api.game.naver.com/nng_main/v1/user/getUserStatus";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// 아직 확인하지 않음
+    Unknown,
+    Valid,
+    /// 로그인되지 않음, 쿠키를 바꿔야 함
+    Invalid,
+    /// chzzk에 요청하지 못해서 알 수 없음
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthHealth {
+    pub status: Status,
+    /// 확인한 인증 정보의 버전
+    pub version: Option<u64>,
+    pub checked_at: Option<DateTime<Utc>>,
+    /// 로그인된 계정
+    pub nickname: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl Default for AuthHealth {
+    fn default() -> Self {
+        Self {
+            status: Status::Unknown,
+            version: None,
+            checked_at: None,
+            nickname: None,
+            reason: None,
+        }
+    }
+}
+
+impl AuthHealth {
+    /// `GET /health/auth` 의 상태 코드
+    pub fn status_code(&self) -> StatusCode {
+        match self.status {
+            Status::Invalid => StatusCode::SERVICE_UNAVAILABLE,
+            Status::Unknown | Status::Valid | Status::Error => StatusCode::OK,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// 유효하던 쿠키가 만료됨
+    Invalid,
+    /// 만료된 뒤 다시 유효해짐
+    Recovered,
+}
+
+/// `ALERT_WEBHOOK_URL` 로 보내는 내용
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    /// 디스코드 웹훅에서 그대로 보여줄 수 있는 메시지
+    pub content: String,
+    pub kind: AlertKind,
+    pub version: Option<u64>,
+    pub reason: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    code: i64,
+    content: Option<UserStatus>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserStatus {
+    #[serde(default)]
+    logged_in: bool,
+    nickname: Option<String>,
+}
+
+pub struct HealthCheck {
+    pub http: reqwest::Client,
+    /// [`USER_STATUS_URL`]
+    pub url: String,
+    pub alert_url: Option<String>,
+    pub interval: Duration,
+}
+
+impl HealthCheck {
+    /// returns (status, nickname, reason)
+    pub async fn check(&self, auth: &ChzzkAuth) -> (Status, Option<String>, Option<String>) {
+        let ChzzkAuth {
+            nid_ses,
+            nid_aut,
+            nid_jkl,
+        } = auth;
+
+        let resp = match self
+            .http
+            .get(&self.url)
+            .header(
+                reqwest::header::COOKIE,
+                format!("NID_AUT={nid_aut}; NID_SES={nid_ses}; NID_JKL={nid_jkl}"),
+            )
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(err) => return (Status::Error, None, Some(err.to_string())),
+        };
+
+        let status = resp.status();
+
+        if matches!(
+            status,
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+        ) {
+            return (Status::Invalid, None, Some(format!("status: {status}")));
+        }
+
+        if !status.is_success() {
+            return (Status::Error, None, Some(format!("status: {status}")));
+        }
+
+        match resp.json::<Response>().await {
+            Ok(Response {
+                code: 200,
+                content:
+                    Some(UserStatus {
+                        logged_in: true,
+                        nickname,
+                    }),
+            }) => (Status::Valid, nickname, None),
+            Ok(Response { code: 200, .. }) => {
+                (Status::Invalid, None, Some("not logged in".to_owned()))
+            }
+            Ok(Response { code, .. }) => (Status::Invalid, None, Some(format!("code: {code}"))),
+            Err(err) => (Status::Error, None, Some(err.to_string())),
+        }
+    }
+
+    /// 주기적으로, 그리고 인증 정보가 바뀌면 바로 확인함
+    pub async fn run(self, credentials: Credentials, health: Health) {
+        let mut changed = credentials.subscribe();
+
+        loop {
+            let (auth, version) = changed.borrow_and_update().clone();
+
+            let (status, nickname, reason) = self.check(&auth).await;
+
+            let alert = health
+                .update(AuthHealth {
+                    status,
+                    version: Some(version.version),
+                    checked_at: Some(Utc::now()),
+                    nickname,
+                    reason,
+                })
+                .await;
+
+            if let Some(alert) = alert {
+                self.alert(alert).await;
+            }
+
+            tokio::select! {
+                _ = sleep(self.interval) => {}
+                res = changed.changed() => {
+                    if res.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn alert(&self, alert: Alert) {
+        eprintln!("ALERT {}", alert.content);
+
+        let Some(alert_url) = &self.alert_url else {
+            return;
+        };
+
+        match self.http.post(alert_url).json(&alert).send().await {
+            Ok(resp) if !resp.status().is_success() => {
+                eprintln!("alert: webhook responded with {}", resp.status())
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("alert: {err}"),
+        }
+    }
+}
+
+/// 마지막으로 확인한 결과
+#[derive(Clone, Default)]
+pub struct Health(Arc<RwLock<State>>);
+
+#[derive(Default)]
+struct State {
+    current: AuthHealth,
+    /// 요청에 실패한 경우를 빼고 마지막으로 확인한 상태
+    last_known: Option<Status>,
+}
+
+impl Health {
+    pub async fn get(&self) -> AuthHealth {
+        self.0.read().await.current.clone()
+    }
+
+    /// 유효함과 만료됨 사이를 오갈 때만 알림을 반환함
+    pub async fn update(&self, health: AuthHealth) -> Option<Alert> {
+        let mut state = self.0.write().await;
+
+        let kind = match (state.last_known, health.status) {
+            (Some(Status::Valid) | None, Status::Invalid) => Some(AlertKind::Invalid),
+            (Some(Status::Invalid), Status::Valid) => Some(AlertKind::Recovered),
+            _ => None,
+        };
+
+        if matches!(health.status, Status::Valid | Status::Invalid) {
+            state.last_known = Some(health.status);
+        }
+
+        let alert = kind.map(|kind| Alert {
+            content: match kind {
+                AlertKind::Invalid => format!(
+                    "chzzk 쿠키가 만료됨 (version {}): {}",
+                    health.version.unwrap_or_default(),
+                    health.reason.as_deref().unwrap_or("unknown")
+                ),
+                AlertKind::Recovered => format!(
+                    "chzzk 쿠키가 다시 유효함 (version {})",
+                    health.version.unwrap_or_default()
+                ),
+            },
+            kind,
+            version: health.version,
+            reason: health.reason.clone(),
+            at: health.checked_at.unwrap_or_else(Utc::now),
+        });
+
+        state.current = health;
+
+        alert
+    }
+}
@@ -0,0 +1,6 @@
+pub mod catalog;
+pub mod control;
+pub mod credentials;
+pub mod health;
+pub mod routes;
+pub mod token;
@@ -1,50 +1,98 @@
-use std::{fmt::Debug, str::FromStr};
+use std::{fmt::Debug, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
 
-use axum::{extract::State, routing::get, Json, Router};
-use serde::Serialize;
+use chrono::FixedOffset;
+use server::{
+    catalog::Catalog,
+    control::Registry,
+    credentials::{ChzzkAuth, Credentials, Origin},
+    health::{Health, HealthCheck, USER_STATUS_URL},
+    routes::{self, Context},
+};
 
 #[tokio::main]
 async fn main() {
-    let ctx = Context {
-        chzzk_auth: ChzzkAuth {
-            nid_ses: env("NID_SES"),
-            nid_aut: env("NID_AUT"),
-            nid_jkl: env("NID_JKL"),
-        },
+    // 지정하면 마운트한 Secret에서 쿠키를 읽고, 바뀌면 다시 읽음
+    let auth_dir = env_opt::<PathBuf>("CHZZK_AUTH_DIR");
+
+    let credentials = match &auth_dir {
+        Some(directory) => Credentials::new(
+            ChzzkAuth::read_dir(directory)
+                .await
+                .unwrap_or_else(|err| panic!("can't read {directory:?}: {err}")),
+            Origin::File,
+        ),
+        None => Credentials::new(
+            ChzzkAuth {
+                nid_ses: env("NID_SES"),
+                nid_aut: env("NID_AUT"),
+                nid_jkl: env("NID_JKL"),
+            },
+            Origin::Env,
+        ),
     };
 
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/chzzk-auth", get(get_chzzk_auth))
-        .with_state(ctx);
+    if let Some(directory) = auth_dir {
+        let interval = Duration::from_secs(env_opt("CHZZK_AUTH_WATCH_SECS").unwrap_or(10));
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
-}
+        tokio::spawn(credentials.clone().watch(directory, interval));
+    }
 
-// basic handler that responds with a static string
-async fn root() -> &'static str {
-    "Hello, World!"
-}
+    let health = Health::default();
 
-async fn get_chzzk_auth(
-    // this argument tells axum to parse the request body
-    // as JSON into a `CreateUser` type
-    State(ctx): State<Context>,
-) -> Json<ChzzkAuth> {
-    Json(ctx.chzzk_auth)
-}
+    let check = HealthCheck {
+        http: reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap(),
+        url: env_opt("CHZZK_USER_STATUS_URL").unwrap_or_else(|| USER_STATUS_URL.to_owned()),
+        alert_url: env_opt("ALERT_WEBHOOK_URL"),
+        interval: Duration::from_secs(env_opt("AUTH_CHECK_SECS").unwrap_or(300)),
+    };
 
-#[derive(Clone)]
-struct Context {
-    chzzk_auth: ChzzkAuth,
-}
+    tokio::spawn(check.run(credentials.clone(), health.clone()));
+
+    // 인코더와 같은 녹화 폴더를 마운트했을 때만 사용함
+    let catalog = env_opt::<PathBuf>("CATALOG_PATH").map(|root| {
+        let database = env_opt::<PathBuf>("CATALOG_DB").unwrap_or_else(|| "catalog.db".into());
+        let timezone = FixedOffset::east_opt(
+            env_opt::<i32>("TZ_HOURS").unwrap_or(0) * 3600
+                + env_opt::<i32>("TZ_MINUTES").unwrap_or(0) * 60
+                + env_opt::<i32>("TZ_SECONDS").unwrap_or(0),
+        )
+        .expect("timezone out of range");
+
+        println!("catalog = {root:?} ({database:?})");
+
+        Catalog::open(&database, root, timezone)
+            .unwrap_or_else(|err| panic!("can't open {database:?}: {err}"))
+    });
+
+    if let Some(catalog) = &catalog {
+        let interval = Duration::from_secs(env_opt("CATALOG_SCAN_SECS").unwrap_or(300));
+
+        tokio::spawn(catalog.clone().run(interval));
+    }
 
-#[derive(Clone, Serialize)]
-struct ChzzkAuth {
-    nid_ses: String,
-    nid_aut: String,
-    nid_jkl: String,
+    let ctx = Context {
+        credentials,
+        health,
+        encoder_tokens: env("ENCODER_TOKENS"),
+        admin_token: env_opt("ADMIN_TOKEN"),
+        registry: Registry::new(Duration::from_secs(
+            env_opt("ENCODER_TIMEOUT_SECS").unwrap_or(30),
+        )),
+        catalog,
+    };
+
+    println!("encoders = {:?}", ctx.encoder_tokens);
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    axum::serve(
+        listener,
+        routes::router(ctx).into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
 fn env<T>(key: &str) -> T
@@ -59,3 +107,14 @@ where
 
     var.parse().expect("Please set dotenv to valid value")
 }
+
+/// 없거나 비어있으면 None
+fn env_opt<T>(key: &str) -> Option<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Debug,
+{
+    let var = std::env::var(key).ok().filter(|x| !x.trim().is_empty())?;
+
+    Some(var.parse().expect("Please set dotenv to valid value"))
+}
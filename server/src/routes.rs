@@ -0,0 +1,252 @@
+//! HTTP API
+
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::Serialize;
+
+use crate::{
+    catalog::{self, Catalog, RecordingDetail, RecordingList, SyncReport},
+    control::{ChannelStatus, EncoderView, Heartbeat, Register, Registry},
+    credentials::{ChzzkAuth, Credentials, Origin, Version},
+    health::{AuthHealth, Health},
+    token::{bearer, constant_time_eq, EncoderTokens},
+};
+
+#[derive(Clone)]
+pub struct Context {
+    pub credentials: Credentials,
+    pub health: Health,
+    pub encoder_tokens: EncoderTokens,
+    pub admin_token: Option<String>,
+    pub registry: Registry,
+    pub catalog: Option<Catalog>,
+}
+
+/// `ConnectInfo` 를 사용하므로 `into_make_service_with_connect_info::<SocketAddr>` 로 띄워야 함
+pub fn router(ctx: Context) -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/chzzk-auth", get(get_chzzk_auth))
+        .route("/chzzk-auth/version", get(get_chzzk_auth_version))
+        .route("/admin/chzzk-auth", put(put_chzzk_auth))
+        .route("/health/auth", get(get_auth_health))
+        .route("/encoders", get(get_encoders))
+        .route("/encoders/register", post(register_encoder))
+        .route("/encoders/heartbeat", post(encoder_heartbeat))
+        .route("/channels/:id/status", get(get_channel_status))
+        .route("/recordings", get(get_recordings))
+        .route("/recordings/:id", get(get_recording))
+        .route("/admin/recordings/rebuild", post(rebuild_recordings))
+        .with_state(ctx)
+}
+
+// basic handler that responds with a static string
+async fn root() -> &'static str {
+    "Hello, World!"
+}
+
+/// `Authorization: Bearer <token>` 이 `ENCODER_TOKENS` 에 있는 인코더에게만 줌
+async fn get_chzzk_auth(
+    State(ctx): State<Context>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // 인코더가 알려준 pod 이름, 로그에만 사용함
+    let pod = headers
+        .get("x-encoder")
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or("-");
+
+    let Some(encoder) = ctx.encoder_tokens.authorize(&headers) else {
+        eprintln!("chzzk-auth: rejected {addr} ({pod})");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    let (auth, version) = ctx.credentials.get();
+
+    println!(
+        "[{encoder}] chzzk-auth: version {} fetched by {addr} ({pod})",
+        version.version
+    );
+
+    Ok((
+        [
+            (header::CACHE_CONTROL, "no-store".to_owned()),
+            (
+                header::HeaderName::from_static("x-chzzk-auth-version"),
+                version.version.to_string(),
+            ),
+        ],
+        Json(auth),
+    ))
+}
+
+/// 쿠키 없이 버전과 바뀐 시각만 줌
+async fn get_chzzk_auth_version(State(ctx): State<Context>) -> Json<Version> {
+    Json(ctx.credentials.version())
+}
+
+/// 쿠키가 만료되었으면 503
+async fn get_auth_health(State(ctx): State<Context>) -> impl IntoResponse {
+    let health = ctx.health.get().await;
+
+    (health.status_code(), Json::<AuthHealth>(health))
+}
+
+async fn register_encoder(
+    State(ctx): State<Context>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(register): Json<Register>,
+) -> StatusCode {
+    let Some(encoder) = ctx.encoder_tokens.authorize(&headers) else {
+        eprintln!("register: rejected {addr} ({})", register.pod);
+        return StatusCode::UNAUTHORIZED;
+    };
+
+    println!(
+        "[{encoder}] registered {} ({}) version {} with {:?}",
+        register.pod,
+        addr,
+        register.version,
+        register
+            .channels
+            .iter()
+            .map(|x| x.channel_name.as_str())
+            .collect::<Vec<_>>()
+    );
+
+    ctx.registry.register(encoder, register).await;
+
+    StatusCode::NO_CONTENT
+}
+
+/// 등록되지 않은 인코더면 404, 인코더는 다시 등록함
+async fn encoder_heartbeat(
+    State(ctx): State<Context>,
+    headers: HeaderMap,
+    Json(heartbeat): Json<Heartbeat>,
+) -> StatusCode {
+    let Some(encoder) = ctx.encoder_tokens.authorize(&headers) else {
+        return StatusCode::UNAUTHORIZED;
+    };
+
+    if ctx.registry.heartbeat(encoder, heartbeat).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn get_encoders(State(ctx): State<Context>) -> Json<Vec<EncoderView>> {
+    Json(ctx.registry.encoders().await)
+}
+
+async fn get_channel_status(
+    State(ctx): State<Context>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<ChannelStatus>, StatusCode> {
+    ctx.registry
+        .channel(&channel_id)
+        .await
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// `CATALOG_PATH` 를 지정하지 않았으면 404
+fn catalog(ctx: &Context) -> Result<&Catalog, (StatusCode, String)> {
+    ctx.catalog
+        .as_ref()
+        .ok_or((StatusCode::NOT_FOUND, "catalog is disabled".to_owned()))
+}
+
+fn catalog_error(err: catalog::Error) -> (StatusCode, String) {
+    eprintln!("catalog: {err}");
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
+
+async fn get_recordings(
+    State(ctx): State<Context>,
+    Query(query): Query<catalog::Query>,
+) -> Result<Json<RecordingList>, (StatusCode, String)> {
+    catalog(&ctx)?
+        .list(query)
+        .await
+        .map(Json)
+        .map_err(catalog_error)
+}
+
+async fn get_recording(
+    State(ctx): State<Context>,
+    Path(id): Path<String>,
+) -> Result<Json<RecordingDetail>, (StatusCode, String)> {
+    catalog(&ctx)?
+        .get(id)
+        .await
+        .map_err(catalog_error)?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "not found".to_owned()))
+}
+
+/// DB를 비우고 녹화 폴더를 모두 다시 읽음, `ADMIN_TOKEN` 이 필요함
+async fn rebuild_recordings(
+    State(ctx): State<Context>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Result<Json<SyncReport>, (StatusCode, String)> {
+    if !is_admin(&ctx, &headers) {
+        eprintln!("admin: rejected {addr}");
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".to_owned()));
+    }
+
+    let report = catalog(&ctx)?.rebuild().await.map_err(catalog_error)?;
+
+    println!("catalog: rebuilt by {addr}: {report:?}");
+
+    Ok(Json(report))
+}
+
+fn is_admin(ctx: &Context, headers: &HeaderMap) -> bool {
+    match (&ctx.admin_token, bearer(headers)) {
+        (Some(admin_token), Some(token)) => {
+            constant_time_eq(admin_token.as_bytes(), token.as_bytes())
+        }
+        _ => false,
+    }
+}
+
+#[derive(Serialize)]
+struct Replaced {
+    #[serde(flatten)]
+    version: Version,
+    /// 같은 쿠키면 false
+    changed: bool,
+}
+
+/// `ADMIN_TOKEN` 을 지정했을 때만 사용할 수 있음
+async fn put_chzzk_auth(
+    State(ctx): State<Context>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(auth): Json<ChzzkAuth>,
+) -> Result<Json<Replaced>, (StatusCode, String)> {
+    if !is_admin(&ctx, &headers) {
+        eprintln!("admin: rejected {addr}");
+        return Err((StatusCode::UNAUTHORIZED, "unauthorized".to_owned()));
+    }
+
+    if let Some(name) = auth.missing() {
+        return Err((StatusCode::BAD_REQUEST, format!("{name} must not be empty")));
+    }
+
+    let (version, changed) = ctx.credentials.replace(auth, Origin::Admin);
+
+    Ok(Json(Replaced { version, changed }))
+}
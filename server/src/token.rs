@@ -0,0 +1,82 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use axum::http::{header, HeaderMap};
+
+/// 인코더마다 발급한 토큰
+///
+/// `ENCODER_TOKENS=encoder-a=token1,encoder-b=token2`
+#[derive(Clone)]
+pub struct EncoderTokens(Arc<Vec<(String, String)>>);
+
+impl EncoderTokens {
+    /// 토큰을 가진 인코더의 이름
+    pub fn find(&self, token: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(_, x)| constant_time_eq(x.as_bytes(), token.as_bytes()))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// `Authorization: Bearer <token>` 에 해당하는 인코더의 이름
+    pub fn authorize(&self, headers: &HeaderMap) -> Option<&str> {
+        self.find(bearer(headers)?)
+    }
+}
+
+impl FromStr for EncoderTokens {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = Vec::new();
+
+        for entry in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let Some((name, token)) = entry.split_once('=') else {
+                return Err(format!("expected <name>=<token>, got {entry:?}"));
+            };
+
+            let (name, token) = (name.trim(), token.trim());
+
+            if name.is_empty() || token.is_empty() {
+                return Err(format!("expected <name>=<token>, got {entry:?}"));
+            }
+
+            if tokens.iter().any(|(x, _): &(String, String)| x == name) {
+                return Err(format!("duplicate encoder name {name:?}"));
+            }
+
+            tokens.push((name.to_owned(), token.to_owned()));
+        }
+
+        if tokens.is_empty() {
+            return Err("must not be empty".to_owned());
+        }
+
+        Ok(Self(Arc::new(tokens)))
+    }
+}
+
+impl fmt::Debug for EncoderTokens {
+    /// 토큰은 출력하지 않음
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+/// `Authorization: Bearer <token>`
+pub fn bearer(headers: &HeaderMap) -> Option<&str> {
+    Some(
+        headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?
+            .trim(),
+    )
+}
+
+/// 토큰을 비교하는데 걸리는 시간으로 내용을 알 수 없게 함
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
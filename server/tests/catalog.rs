@@ -0,0 +1,339 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, FixedOffset};
+use encoder::{
+    chapter::{
+        journal::{Journal, JournalEntry},
+        Chapter,
+    },
+    ffmpeg::OutputFormat,
+    report::{FileReport, Problem, SessionReport},
+    segment::{Manifest, ManifestEntry},
+    source::{LiveStatus, LiveStatusType},
+    time::Time,
+};
+use server::catalog::{Catalog, DateBound, Query, SyncReport};
+
+fn kst() -> FixedOffset {
+    FixedOffset::east_opt(9 * 3600).unwrap()
+}
+
+fn chapter(secs: u64, title: &str, category: &str) -> Chapter {
+    Chapter(
+        Time::from(Duration::from_secs(secs)),
+        LiveStatus {
+            live_title: title.to_owned(),
+            status: LiveStatusType::Open,
+            live_category: Some(category.to_owned()),
+            adult: false,
+            extra: Default::default(),
+        },
+    )
+}
+
+/// 녹화가 끝난 폴더, `applied` 가 false면 녹화 중
+fn session(
+    root: &Path,
+    channel_name: &str,
+    started_at: &str,
+    chapters: &[Chapter],
+    applied: bool,
+) -> PathBuf {
+    let started_at = DateTime::parse_from_rfc3339(started_at).unwrap();
+    let directory = root
+        .join(channel_name)
+        .join(started_at.format("%Y-%m-%d_%H-%M-%S").to_string());
+
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("index.mkv"), vec![0; 1024]).unwrap();
+
+    let mut journal = Journal::create(&directory, started_at, OutputFormat::Matroska).unwrap();
+
+    for chapter in chapters {
+        journal.append_chapter(chapter).unwrap();
+    }
+
+    if applied {
+        journal.append(&JournalEntry::Applied).unwrap();
+    }
+
+    Manifest {
+        segments: vec![ManifestEntry {
+            file_name: "index.mkv".to_owned(),
+            part: 0,
+            start: 0.0,
+            end: applied.then_some(3600.0),
+            size: Some(1024),
+            incomplete: false,
+        }],
+    }
+    .write(&directory)
+    .unwrap();
+
+    directory
+}
+
+fn query() -> Query {
+    Query::default()
+}
+
+async fn ids(catalog: &Catalog, query: Query) -> Vec<String> {
+    catalog
+        .list(query)
+        .await
+        .unwrap()
+        .recordings
+        .into_iter()
+        .map(|x| x.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn indexes_and_filters_recordings() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path();
+
+    session(
+        root,
+        "a",
+        "2024-03-01T20:00:00+09:00",
+        &[
+            chapter(0, "morning talk", "Talk"),
+            chapter(1800, "ranked 100%", "League_of_Legends"),
+        ],
+        true,
+    );
+    let recording = session(
+        root,
+        "a",
+        "2024-03-03T01:00:00+09:00",
+        &[chapter(0, "late night", "Talk")],
+        false,
+    );
+
+    SessionReport {
+        files: vec![FileReport {
+            file_name: "index.mkv".to_owned(),
+            expected_duration: 3600.0,
+            duration: None,
+            problems: vec![Problem::MissingCues],
+            repaired: false,
+        }],
+    }
+    .write(&root.join("a/2024-03-01_20-00-00"))
+    .unwrap();
+
+    // 저널이 없는 이전 녹화
+    let legacy = root.join("b/2024-03-02_10-00-00");
+
+    fs::create_dir_all(&legacy).unwrap();
+    fs::write(legacy.join("index.mkv"), vec![0; 10]).unwrap();
+    fs::write(
+        legacy.join("metadata.xml"),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><Chapters><EditionEntry>\
+        <ChapterAtom><ChapterTimeStart>00:00:00.000</ChapterTimeStart><ChapterTimeEnd>01:00:00.000</ChapterTimeEnd>\
+        <ChapterDisplay><ChapterString>Q&amp;A Playing Just Chatting</ChapterString><ChapterLanguage>ko</ChapterLanguage></ChapterDisplay></ChapterAtom>\
+        </EditionEntry></Chapters>",
+    )
+    .unwrap();
+
+    // 녹화가 아닌 폴더
+    fs::create_dir_all(root.join("b/empty")).unwrap();
+
+    let catalog = Catalog::open(root.join("catalog.db"), root, kst()).unwrap();
+
+    let report = catalog.sync().await.unwrap();
+
+    assert_eq!(report.indexed, 3);
+    assert_eq!(report.failed, 0);
+
+    // 최근에 시작한 녹화부터
+    assert_eq!(
+        ids(&catalog, query()).await,
+        [
+            "a:2024-03-03_01-00-00",
+            "b:2024-03-02_10-00-00",
+            "a:2024-03-01_20-00-00"
+        ]
+    );
+
+    let by = |f: fn(&mut Query)| {
+        let mut query = query();
+        f(&mut query);
+        query
+    };
+
+    assert_eq!(
+        ids(&catalog, by(|x| x.channel = Some("b".to_owned()))).await,
+        ["b:2024-03-02_10-00-00"]
+    );
+    assert_eq!(
+        ids(&catalog, by(|x| x.title = Some("RANKED 100".to_owned()))).await,
+        ["a:2024-03-01_20-00-00"]
+    );
+    // `%` 는 글자 그대로 찾음
+    assert!(ids(&catalog, by(|x| x.title = Some("%night".to_owned())))
+        .await
+        .is_empty());
+    assert_eq!(
+        ids(&catalog, by(|x| x.category = Some("legends".to_owned()))).await,
+        ["a:2024-03-01_20-00-00"]
+    );
+    assert_eq!(
+        ids(
+            &catalog,
+            by(|x| {
+                x.from = Some("2024-03-02".parse().unwrap());
+                x.to = Some("2024-03-02".parse().unwrap());
+            })
+        )
+        .await,
+        ["b:2024-03-02_10-00-00"]
+    );
+    // 2024-03-02T16:00:00Z == 2024-03-03T01:00:00+09:00
+    assert_eq!(
+        ids(
+            &catalog,
+            by(|x| x.from = Some("2024-03-02T16:00:00Z".parse().unwrap()))
+        )
+        .await,
+        ["a:2024-03-03_01-00-00"]
+    );
+
+    let page = catalog
+        .list(Query {
+            limit: Some(1),
+            offset: Some(1),
+            ..query()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(page.total, 3);
+    assert_eq!(page.recordings[0].id, "b:2024-03-02_10-00-00");
+
+    let detail = catalog
+        .get("a:2024-03-01_20-00-00".to_owned())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(detail.recording.finished);
+    assert_eq!(detail.recording.ok, Some(false));
+    assert_eq!(detail.recording.duration, Some(3600.0));
+    assert_eq!(detail.recording.size, 1024);
+    assert_eq!(detail.recording.title.as_deref(), Some("morning talk"));
+    assert_eq!(detail.recording.categories, ["Talk", "League_of_Legends"]);
+    assert_eq!(
+        detail
+            .chapters
+            .iter()
+            .map(|x| (x.start, x.end))
+            .collect::<Vec<_>>(),
+        [(0, 1800), (1800, 3600)]
+    );
+    assert_eq!(detail.files[0].problems, [Problem::MissingCues]);
+
+    // 녹화 중이면 ffprobe로 검사하지 않음
+    let detail = catalog
+        .get("a:2024-03-03_01-00-00".to_owned())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(!detail.recording.finished);
+    assert!(detail.files[0].probe.is_none());
+    assert!(detail.files[0].probe_error.is_none());
+    assert_eq!(detail.directory, recording.to_string_lossy());
+
+    let detail = catalog
+        .get("b:2024-03-02_10-00-00".to_owned())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        detail.recording.started_at,
+        Some(DateTime::parse_from_rfc3339("2024-03-02T10:00:00+09:00").unwrap())
+    );
+    assert_eq!(detail.chapters[0].title, "Q&A");
+    assert_eq!(
+        detail.chapters[0].category.as_deref(),
+        Some("Just Chatting")
+    );
+    assert_eq!(detail.chapters[0].end, 3600);
+
+    assert!(catalog.get("a:unknown".to_owned()).await.unwrap().is_none());
+    assert!("2024/03/01".parse::<DateBound>().is_err());
+}
+
+#[tokio::test]
+async fn sync_follows_directories() {
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path();
+    let database = root.join("catalog.db");
+
+    let directory = session(
+        root,
+        "a",
+        "2024-03-01T20:00:00+09:00",
+        &[chapter(0, "first", "Talk")],
+        false,
+    );
+    session(
+        root,
+        "a",
+        "2024-03-02T20:00:00+09:00",
+        &[chapter(0, "second", "Talk")],
+        true,
+    );
+
+    let catalog = Catalog::open(&database, root, kst()).unwrap();
+
+    assert_eq!(catalog.sync().await.unwrap().indexed, 2);
+    assert_eq!(
+        catalog.sync().await.unwrap(),
+        SyncReport {
+            unchanged: 2,
+            ..Default::default()
+        }
+    );
+
+    // 녹화 중에 챕터가 바뀜
+    Journal::open(&directory)
+        .unwrap()
+        .append_chapter(&chapter(600, "renamed", "Game"))
+        .unwrap();
+
+    assert_eq!(catalog.sync().await.unwrap().indexed, 1);
+    assert_eq!(
+        ids(
+            &catalog,
+            Query {
+                title: Some("renamed".to_owned()),
+                ..query()
+            }
+        )
+        .await,
+        ["a:2024-03-01_20-00-00"]
+    );
+
+    fs::remove_dir_all(&directory).unwrap();
+
+    let report = catalog.sync().await.unwrap();
+
+    assert_eq!((report.removed, report.unchanged), (1, 1));
+    assert_eq!(catalog.list(query()).await.unwrap().total, 1);
+
+    // DB 파일에 남아있음
+    drop(catalog);
+
+    let catalog = Catalog::open(&database, root, kst()).unwrap();
+
+    assert_eq!(catalog.list(query()).await.unwrap().total, 1);
+    assert_eq!(catalog.rebuild().await.unwrap().indexed, 1);
+}
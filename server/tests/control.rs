@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use server::control::{ChannelInfo, ChannelReport, Heartbeat, RecordingState, Register, Registry};
+
+fn channel(channel_id: &str) -> ChannelInfo {
+    ChannelInfo {
+        channel_id: channel_id.to_owned(),
+        channel_name: format!("name-{channel_id}"),
+    }
+}
+
+fn register(pod: &str, channels: &[&str]) -> Register {
+    Register {
+        pod: pod.to_owned(),
+        version: "0.2.3".to_owned(),
+        channels: channels.iter().map(|x| channel(x)).collect(),
+    }
+}
+
+fn recording(channel_id: &str, title: &str, elapsed_secs: u64) -> ChannelReport {
+    ChannelReport {
+        channel_id: channel_id.to_owned(),
+        channel_name: format!("name-{channel_id}"),
+        state: RecordingState::Recording,
+        live_id: Some("1".to_owned()),
+        title: Some(title.to_owned()),
+        category: Some("Talk".to_owned()),
+        elapsed_secs,
+        elapsed: "00:01:00".to_owned(),
+        bytes_written: 1024,
+    }
+}
+
+#[tokio::test]
+async fn heartbeat_requires_registration() {
+    let registry = Registry::new(Duration::from_secs(30));
+
+    let heartbeat = Heartbeat {
+        pod: "pod-a".to_owned(),
+        channels: vec![recording("a", "title", 60)],
+    };
+
+    assert!(!registry.heartbeat("encoder-a", heartbeat.clone()).await);
+
+    registry
+        .register("encoder-a", register("pod-a", &["a"]))
+        .await;
+
+    // 등록하면 상태를 보내기 전까지 idle
+    let encoders = registry.encoders().await;
+
+    assert_eq!(encoders.len(), 1);
+    assert!(encoders[0].online);
+    assert_eq!(encoders[0].channels[0].state, RecordingState::Idle);
+
+    assert!(registry.heartbeat("encoder-a", heartbeat).await);
+
+    let status = registry.channel("a").await.unwrap();
+
+    assert_eq!(status.state, RecordingState::Recording);
+
+    let current = status.current.unwrap();
+
+    assert_eq!(current.encoder, "encoder-a");
+    assert_eq!(current.pod, "pod-a");
+    assert_eq!(current.report.title.as_deref(), Some("title"));
+    assert_eq!(current.report.elapsed_secs, 60);
+
+    assert!(registry.channel("unknown").await.is_none());
+}
+
+#[tokio::test]
+async fn channel_status_prefers_recording_encoder() {
+    let registry = Registry::new(Duration::from_secs(30));
+
+    registry
+        .register("idle", register("pod-1", &["a", "b"]))
+        .await;
+    registry.register("busy", register("pod-2", &["a"])).await;
+
+    registry
+        .heartbeat(
+            "busy",
+            Heartbeat {
+                pod: "pod-2".to_owned(),
+                channels: vec![recording("a", "title", 10)],
+            },
+        )
+        .await;
+
+    let status = registry.channel("a").await.unwrap();
+
+    assert_eq!(status.encoders.len(), 2);
+    assert_eq!(status.state, RecordingState::Recording);
+    assert_eq!(status.current.unwrap().encoder, "busy");
+
+    assert_eq!(
+        registry.channel("b").await.unwrap().state,
+        RecordingState::Idle
+    );
+}
+
+#[tokio::test]
+async fn offline_encoders_are_not_current() {
+    let registry = Registry::new(Duration::ZERO);
+
+    registry
+        .register("encoder-a", register("pod-a", &["a"]))
+        .await;
+    registry
+        .heartbeat(
+            "encoder-a",
+            Heartbeat {
+                pod: "pod-a".to_owned(),
+                channels: vec![recording("a", "title", 10)],
+            },
+        )
+        .await;
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let status = registry.channel("a").await.unwrap();
+
+    assert!(!status.encoders[0].online);
+    assert!(status.current.is_none());
+    assert_eq!(status.state, RecordingState::Idle);
+    assert!(!registry.encoders().await[0].online);
+}
+
+#[tokio::test]
+async fn pods_sharing_a_token_are_kept_apart() {
+    let registry = Registry::new(Duration::from_secs(30));
+
+    registry
+        .register("encoder", register("encoder-0", &["a"]))
+        .await;
+    registry
+        .register("encoder", register("encoder-1", &["b"]))
+        .await;
+
+    assert!(
+        registry
+            .heartbeat(
+                "encoder",
+                Heartbeat {
+                    pod: "encoder-1".to_owned(),
+                    channels: vec![recording("b", "title", 10)],
+                },
+            )
+            .await
+    );
+    assert!(
+        !registry
+            .heartbeat(
+                "encoder",
+                Heartbeat {
+                    pod: "encoder-2".to_owned(),
+                    channels: vec![recording("c", "title", 10)],
+                },
+            )
+            .await
+    );
+
+    let encoders = registry.encoders().await;
+
+    assert_eq!(
+        encoders.iter().map(|x| x.pod.as_str()).collect::<Vec<_>>(),
+        ["encoder-0", "encoder-1"]
+    );
+    assert_eq!(
+        registry.channel("a").await.unwrap().state,
+        RecordingState::Idle
+    );
+    assert_eq!(
+        registry.channel("b").await.unwrap().current.unwrap().pod,
+        "encoder-1"
+    );
+}
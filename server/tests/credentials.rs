@@ -0,0 +1,91 @@
+use std::{fs, path::Path, time::Duration};
+
+use server::credentials::{ChzzkAuth, Credentials, Origin};
+use tokio::time::{sleep, timeout};
+
+fn auth(nid_aut: &str) -> ChzzkAuth {
+    ChzzkAuth {
+        nid_ses: "ses".to_owned(),
+        nid_aut: nid_aut.to_owned(),
+        nid_jkl: "jkl".to_owned(),
+    }
+}
+
+fn write_dir(directory: &Path, auth: &ChzzkAuth) {
+    fs::write(directory.join("NID_SES"), &auth.nid_ses).unwrap();
+    fs::write(directory.join("NID_AUT"), format!("{}\n", auth.nid_aut)).unwrap();
+    fs::write(directory.join("NID_JKL"), &auth.nid_jkl).unwrap();
+}
+
+#[test]
+fn replace_bumps_version_only_when_changed() {
+    let credentials = Credentials::new(auth("a"), Origin::Env);
+    let created = credentials.version();
+
+    assert_eq!((created.version, created.origin), (1, Origin::Env));
+
+    let (version, changed) = credentials.replace(auth("a"), Origin::Admin);
+
+    assert!(!changed);
+    assert_eq!((version.version, version.origin), (1, Origin::Env));
+    assert_eq!(version.updated_at, created.updated_at);
+
+    let (version, changed) = credentials.replace(auth("b"), Origin::Admin);
+
+    assert!(changed);
+    assert_eq!((version.version, version.origin), (2, Origin::Admin));
+    assert!(version.updated_at >= created.updated_at);
+
+    let (current, current_version) = credentials.get();
+
+    assert!(current == auth("b"));
+    assert_eq!(current_version.version, 2);
+}
+
+#[tokio::test]
+async fn watch_follows_mounted_secret() {
+    let directory = tempfile::tempdir().unwrap();
+
+    write_dir(directory.path(), &auth("a"));
+
+    let credentials = Credentials::new(
+        ChzzkAuth::read_dir(directory.path()).await.unwrap(),
+        Origin::File,
+    );
+    let mut changes = credentials.subscribe();
+
+    tokio::spawn(
+        credentials
+            .clone()
+            .watch(directory.path().to_path_buf(), Duration::from_millis(10)),
+    );
+
+    // 파일이 바뀌면 적용함
+    write_dir(directory.path(), &auth("b"));
+
+    timeout(Duration::from_secs(5), changes.changed())
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (current, version) = credentials.get();
+
+    assert!(current == auth("b"));
+    assert_eq!((version.version, version.origin), (2, Origin::File));
+
+    // 비어있는 쿠키는 적용하지 않음
+    write_dir(directory.path(), &auth(""));
+    sleep(Duration::from_millis(100)).await;
+
+    assert!(credentials.get().0 == auth("b"));
+    assert_eq!(credentials.version().version, 2);
+
+    // 파일이 그대로면 admin으로 바꾼 값을 되돌리지 않음
+    credentials.replace(auth("admin"), Origin::Admin);
+    sleep(Duration::from_millis(100)).await;
+
+    let (current, version) = credentials.get();
+
+    assert!(current == auth("admin"));
+    assert_eq!((version.version, version.origin), (3, Origin::Admin));
+}
@@ -0,0 +1,173 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::{json, Value};
+use server::{
+    credentials::{ChzzkAuth, Credentials, Origin},
+    health::{AuthHealth, Health, HealthCheck, Status},
+};
+use tokio::{net::TcpListener, time::sleep};
+
+type Alerts = Arc<Mutex<Vec<Value>>>;
+
+/// chzzk user-status API 대신 `NID_AUT` 로 응답을 정함
+async fn user_status(headers: HeaderMap) -> impl IntoResponse {
+    let cookie = headers
+        .get(header::COOKIE)
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or_default();
+
+    if cookie.contains("NID_AUT=good;") {
+        (
+            StatusCode::OK,
+            Json(json!({
+                "code": 200,
+                "content": { "loggedIn": true, "nickname": "recorder" },
+            })),
+        )
+    } else if cookie.contains("NID_AUT=blocked;") {
+        (StatusCode::UNAUTHORIZED, Json(json!({})))
+    } else if cookie.contains("NID_AUT=down;") {
+        (StatusCode::BAD_GATEWAY, Json(json!({})))
+    } else {
+        (
+            StatusCode::OK,
+            Json(json!({
+                "code": 200,
+                "content": { "loggedIn": false, "nickname": null },
+            })),
+        )
+    }
+}
+
+async fn alert(State(alerts): State<Alerts>, Json(body): Json<Value>) {
+    alerts.lock().unwrap().push(body);
+}
+
+/// returns (user-status url, alert url, 받은 알림)
+async fn stand_in() -> (String, String, Alerts) {
+    let alerts = Alerts::default();
+
+    let app = Router::new()
+        .route("/user-status", get(user_status))
+        .route("/alert", post(alert))
+        .with_state(alerts.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    (
+        format!("http://{addr}/user-status"),
+        format!("http://{addr}/alert"),
+        alerts,
+    )
+}
+
+fn auth(nid_aut: &str) -> ChzzkAuth {
+    ChzzkAuth {
+        nid_ses: "ses".to_owned(),
+        nid_aut: nid_aut.to_owned(),
+        nid_jkl: "jkl".to_owned(),
+    }
+}
+
+fn health_check(url: String, alert_url: Option<String>) -> HealthCheck {
+    HealthCheck {
+        http: reqwest::Client::new(),
+        url,
+        alert_url,
+        interval: Duration::from_secs(3600),
+    }
+}
+
+/// `version` 을 확인할 때까지 기다림
+async fn wait_for(health: &Health, version: u64) -> AuthHealth {
+    for _ in 0..100 {
+        let current = health.get().await;
+
+        if current.version == Some(version) {
+            return current;
+        }
+
+        sleep(Duration::from_millis(20)).await;
+    }
+
+    panic!("version {version} was not checked");
+}
+
+#[tokio::test]
+async fn check_classifies_responses() {
+    let (url, _, _) = stand_in().await;
+    let check = health_check(url, None);
+
+    assert_eq!(
+        check.check(&auth("good")).await,
+        (Status::Valid, Some("recorder".to_owned()), None)
+    );
+    assert_eq!(check.check(&auth("expired")).await.0, Status::Invalid);
+    assert_eq!(check.check(&auth("blocked")).await.0, Status::Invalid);
+    assert_eq!(check.check(&auth("down")).await.0, Status::Error);
+
+    // 서버에 연결할 수 없음
+    let check = health_check("http://127.0.0.1:1/user-status".to_owned(), None);
+
+    assert_eq!(check.check(&auth("good")).await.0, Status::Error);
+}
+
+#[tokio::test]
+async fn alerts_when_credentials_become_invalid() {
+    let (url, alert_url, alerts) = stand_in().await;
+
+    let credentials = Credentials::new(auth("good"), Origin::Env);
+    let health = Health::default();
+
+    tokio::spawn(health_check(url, Some(alert_url)).run(credentials.clone(), health.clone()));
+
+    let current = wait_for(&health, 1).await;
+
+    assert_eq!(current.status, Status::Valid);
+    assert_eq!(current.status_code(), StatusCode::OK);
+
+    // 인증 정보가 바뀌면 주기를 기다리지 않고 바로 확인함
+    credentials.replace(auth("expired"), Origin::Admin);
+
+    let current = wait_for(&health, 2).await;
+
+    assert_eq!(current.status, Status::Invalid);
+    assert_eq!(current.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // 요청에 실패한 것은 만료로 보지 않음
+    credentials.replace(auth("down"), Origin::Admin);
+    assert_eq!(wait_for(&health, 3).await.status, Status::Error);
+
+    credentials.replace(auth("good"), Origin::Admin);
+    assert_eq!(wait_for(&health, 4).await.status, Status::Valid);
+
+    // 알림은 응답을 받은 뒤 보내므로 잠시 기다림
+    sleep(Duration::from_millis(100)).await;
+
+    let alerts = alerts.lock().unwrap().clone();
+
+    assert_eq!(
+        alerts
+            .iter()
+            .map(|x| (x["kind"].as_str().unwrap(), x["version"].as_u64().unwrap()))
+            .collect::<Vec<_>>(),
+        [("invalid", 2), ("recovered", 4)]
+    );
+    assert!(alerts[0]["content"]
+        .as_str()
+        .unwrap()
+        .contains("not logged in"));
+}
@@ -0,0 +1,132 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::http::{header, HeaderMap, HeaderValue};
+use reqwest::StatusCode;
+use serde_json::Value;
+use server::{
+    control::Registry,
+    credentials::{ChzzkAuth, Credentials, Origin},
+    health::Health,
+    routes::{self, Context},
+    token::{bearer, constant_time_eq, EncoderTokens},
+};
+use tokio::net::TcpListener;
+
+fn tokens() -> EncoderTokens {
+    " encoder-a = token-a ,encoder-b=token-b,".parse().unwrap()
+}
+
+fn authorization(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+    headers
+}
+
+#[test]
+fn parses_tokens() {
+    let tokens = tokens();
+
+    assert_eq!(format!("{tokens:?}"), r#"["encoder-a", "encoder-b"]"#);
+    assert_eq!(tokens.find("token-a"), Some("encoder-a"));
+    assert_eq!(tokens.find("token-b"), Some("encoder-b"));
+
+    for invalid in [
+        "",
+        " , ",
+        "encoder-a",
+        "encoder-a=",
+        "=token-a",
+        "encoder-a=token-a,encoder-b",
+        "encoder-a=token-a,encoder-a=token-b",
+    ] {
+        assert!(invalid.parse::<EncoderTokens>().is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn authorizes_bearer_tokens() {
+    let tokens = tokens();
+
+    assert_eq!(bearer(&authorization("Bearer token-a ")), Some("token-a"));
+    assert_eq!(bearer(&authorization("token-a")), None);
+    assert_eq!(bearer(&authorization("Basic token-a")), None);
+    assert_eq!(bearer(&HeaderMap::new()), None);
+
+    assert_eq!(
+        tokens.authorize(&authorization("Bearer token-b")),
+        Some("encoder-b")
+    );
+    assert_eq!(tokens.authorize(&authorization("Bearer token-c")), None);
+    assert_eq!(tokens.authorize(&authorization("Bearer token-")), None);
+    assert_eq!(tokens.authorize(&authorization("token-a")), None);
+
+    assert!(constant_time_eq(b"token", b"token"));
+    assert!(!constant_time_eq(b"token", b"tokem"));
+    assert!(!constant_time_eq(b"token", b"token-a"));
+    assert!(!constant_time_eq(b"", b"token"));
+}
+
+#[tokio::test]
+async fn chzzk_auth_requires_encoder_token() {
+    let ctx = Context {
+        credentials: Credentials::new(
+            ChzzkAuth {
+                nid_ses: "ses".to_owned(),
+                nid_aut: "aut".to_owned(),
+                nid_jkl: "jkl".to_owned(),
+            },
+            Origin::Env,
+        ),
+        health: Health::default(),
+        encoder_tokens: tokens(),
+        admin_token: None,
+        registry: Registry::new(Duration::from_secs(30)),
+        catalog: None,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            routes::router(ctx).into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap()
+    });
+
+    let http = reqwest::Client::new();
+    let url = format!("http://{addr}/chzzk-auth");
+
+    let status = |resp: reqwest::Response| resp.status();
+
+    assert_eq!(
+        status(http.get(&url).send().await.unwrap()),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(
+            http.get(&url)
+                .header("authorization", "token-a")
+                .send()
+                .await
+                .unwrap()
+        ),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(http.get(&url).bearer_auth("wrong").send().await.unwrap()),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let resp = http.get(&url).bearer_auth("token-b").send().await.unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["cache-control"], "no-store");
+    assert_eq!(resp.headers()["x-chzzk-auth-version"], "1");
+
+    let body = resp.json::<Value>().await.unwrap();
+
+    assert_eq!(body["nid_aut"], "aut");
+}